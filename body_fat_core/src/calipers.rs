@@ -0,0 +1,69 @@
+use crate::measurements::{ALL_SITES, Measurements};
+
+/// A skinfold caliper model. Calipers differ in jaw pressure and read the
+/// same fold systematically differently, so readings are best compared on
+/// one device.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Caliper {
+    pub name: &'static str,
+    /// How the device is known to read compared with a Harpenden.
+    pub note: &'static str,
+    /// Approximate factor taking a reading on this device to the Harpenden
+    /// equivalent, the reference used to validate most equations.
+    pub harpenden_factor: f64,
+}
+
+/// Supported caliper presets, reference device first.
+pub const CALIPERS: [Caliper; 4] = [
+    Caliper {
+        name: "Harpenden",
+        note: "Constant jaw pressure of about 10 g/mm²; the reference device for Durnin & Womersley.",
+        harpenden_factor: 1.0,
+    },
+    Caliper {
+        name: "Lange",
+        note: "Constant jaw pressure like the Harpenden, but studies report readings a few percent higher on the same fold.",
+        harpenden_factor: 0.96,
+    },
+    Caliper {
+        name: "Slim Guide",
+        note: "Designed to match the Harpenden; published comparisons find close agreement.",
+        harpenden_factor: 1.0,
+    },
+    Caliper {
+        name: "Plastic caliper",
+        note: "Jaw pressure changes with how far the caliper opens, so readings drift from the Harpenden on thick folds. No reliable correction; compare sessions on the same device.",
+        harpenden_factor: 1.0,
+    },
+];
+
+pub fn caliper(name: &str) -> Option<&'static Caliper> {
+    CALIPERS.iter().find(|caliper| caliper.name == name)
+}
+
+/// Readings converted to their Harpenden equivalent.
+pub fn harpenden_equivalent(measurements: &Measurements, caliper: &Caliper) -> Measurements {
+    let mut corrected = Measurements::new();
+    for site in ALL_SITES {
+        corrected.set_measurement(site, measurements.get(site) * caliper.harpenden_factor);
+    }
+    corrected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_harpenden_equivalent() {
+        let mut measurements = Measurements::new();
+        measurements.chest = 12.5;
+        let lange = caliper("Lange").unwrap();
+        assert!((harpenden_equivalent(&measurements, lange).chest - 12.0).abs() < 1e-9);
+        assert_eq!(
+            harpenden_equivalent(&measurements, &CALIPERS[0]).chest,
+            12.5
+        );
+        assert_eq!(caliper("Tape"), None);
+    }
+}
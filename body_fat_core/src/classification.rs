@@ -0,0 +1,296 @@
+/// Categories from leanest to highest, shared by every cohort.
+pub const CATEGORIES: [&str; 5] = ["Excellent", "Good", "Average", "Below Average", "Poor"];
+
+/// Category below the essential fat threshold.
+pub const BELOW_ESSENTIAL: &str = "Extremely Lean (Below Essential Fat)";
+
+/// Body fat ranges for one age group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cohort {
+    pub min_age: u32,
+    pub max_age: u32,
+    /// Highest body fat in each category before Poor, leanest first. Values
+    /// between one bound and the next category's published start go to the
+    /// nearer side.
+    pub upper_bounds: [f64; 4],
+    /// Where the ranges don't come straight from the published tables, why.
+    pub note: Option<&'static str>,
+}
+
+/// Every cohort for one sex, youngest first. New age groups are new rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassificationTable {
+    /// Essential fat; anything lower is flagged rather than graded.
+    pub essential_fat: f64,
+    /// Gap between one category's upper bound and the next one's start in
+    /// the published tables, e.g. 13.8 then 13.9.
+    pub step: f64,
+    pub cohorts: &'static [Cohort],
+}
+
+impl ClassificationTable {
+    pub fn cohort(&self, age: u32) -> Option<&'static Cohort> {
+        self.cohorts
+            .iter()
+            .find(|cohort| (cohort.min_age..=cohort.max_age).contains(&age))
+    }
+
+    /// Midpoints between adjacent categories, leanest first.
+    fn boundaries(&self, cohort: &Cohort) -> [f64; 4] {
+        cohort.upper_bounds.map(|bound| bound + self.step / 2.0)
+    }
+
+    /// Category for `bf` percent body fat at `age`, "Unclassified" for ages
+    /// with no cohort.
+    pub fn classify(&self, age: u32, bf: f64) -> &'static str {
+        if bf < self.essential_fat {
+            return BELOW_ESSENTIAL;
+        }
+        let Some(cohort) = self.cohort(age) else {
+            return "Unclassified";
+        };
+        let index = self
+            .boundaries(cohort)
+            .iter()
+            .position(|boundary| bf < *boundary)
+            .unwrap_or(CATEGORIES.len() - 1);
+        CATEGORIES[index]
+    }
+}
+
+const TEENS: Option<&str> = Some("20-29 ranges applied to ages 18-19");
+const SEVENTIES: Option<&str> = Some("70+ ranges extrapolated from ages 50-69");
+
+/// ACSM ranges for men, with the 60s' trend carried on past 69.
+pub const MALE_TABLE: ClassificationTable = ClassificationTable {
+    essential_fat: 5.0,
+    step: 0.1,
+    cohorts: &[
+        Cohort {
+            min_age: 18,
+            max_age: 19,
+            upper_bounds: [13.8, 17.4, 20.4, 24.1],
+            note: TEENS,
+        },
+        Cohort {
+            min_age: 20,
+            max_age: 29,
+            upper_bounds: [13.8, 17.4, 20.4, 24.1],
+            note: None,
+        },
+        Cohort {
+            min_age: 30,
+            max_age: 39,
+            upper_bounds: [14.9, 18.9, 21.4, 25.1],
+            note: None,
+        },
+        Cohort {
+            min_age: 40,
+            max_age: 49,
+            upper_bounds: [16.9, 19.9, 22.4, 26.1],
+            note: None,
+        },
+        Cohort {
+            min_age: 50,
+            max_age: 59,
+            upper_bounds: [18.9, 21.9, 24.4, 28.1],
+            note: None,
+        },
+        Cohort {
+            min_age: 60,
+            max_age: 69,
+            upper_bounds: [20.9, 23.9, 26.4, 30.1],
+            note: None,
+        },
+        Cohort {
+            min_age: 70,
+            max_age: u32::MAX,
+            upper_bounds: [22.9, 25.9, 28.4, 32.1],
+            note: SEVENTIES,
+        },
+    ],
+};
+
+/// ACE-style ranges for women, with the 60s' trend carried on past 69.
+pub const FEMALE_TABLE: ClassificationTable = ClassificationTable {
+    essential_fat: 10.0,
+    step: 1.0,
+    cohorts: &[
+        Cohort {
+            min_age: 18,
+            max_age: 19,
+            upper_bounds: [18.0, 23.0, 29.0, 35.0],
+            note: TEENS,
+        },
+        Cohort {
+            min_age: 20,
+            max_age: 29,
+            upper_bounds: [18.0, 23.0, 29.0, 35.0],
+            note: None,
+        },
+        Cohort {
+            min_age: 30,
+            max_age: 39,
+            upper_bounds: [19.0, 24.0, 30.0, 36.0],
+            note: None,
+        },
+        Cohort {
+            min_age: 40,
+            max_age: 49,
+            upper_bounds: [20.0, 25.0, 31.0, 37.0],
+            note: None,
+        },
+        Cohort {
+            min_age: 50,
+            max_age: 59,
+            upper_bounds: [21.0, 26.0, 32.0, 38.0],
+            note: None,
+        },
+        Cohort {
+            min_age: 60,
+            max_age: 69,
+            upper_bounds: [22.0, 27.0, 33.0, 39.0],
+            note: None,
+        },
+        Cohort {
+            min_age: 70,
+            max_age: u32::MAX,
+            upper_bounds: [23.0, 28.0, 34.0, 40.0],
+            note: SEVENTIES,
+        },
+    ],
+};
+
+/// Category for a man of `age` with `bf` percent body fat.
+pub fn classify_body_fat_male(age: u32, bf: f64) -> &'static str {
+    MALE_TABLE.classify(age, bf)
+}
+
+/// Category for a woman of `age` with `bf` percent body fat.
+pub fn classify_body_fat_female(age: u32, bf: f64) -> &'static str {
+    FEMALE_TABLE.classify(age, bf)
+}
+
+/// A category together with how close the result sits to its neighbours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub category: &'static str,
+    /// Category on the other side of the nearest boundary, when the result is
+    /// within the uncertainty margin of it.
+    pub borderline: Option<&'static str>,
+    /// Distance in percentage points to the nearest category boundary.
+    pub boundary_distance: f64,
+    /// Set when the age group's ranges are borrowed or extrapolated.
+    pub cohort_note: Option<&'static str>,
+}
+
+impl Classification {
+    pub fn label(&self) -> String {
+        match (self.borderline, self.cohort_note) {
+            (Some(neighbour), Some(note)) => {
+                format!("{} (borderline {}; {})", self.category, neighbour, note)
+            }
+            (Some(neighbour), None) => format!("{} (borderline {})", self.category, neighbour),
+            (None, Some(note)) => format!("{} ({})", self.category, note),
+            (None, None) => self.category.to_string(),
+        }
+    }
+}
+
+/// Classify `bf` and flag it as borderline when a category boundary lies
+/// within `margin` percentage points. Boundaries sit midway between adjacent
+/// ranges, plus the essential fat threshold.
+pub fn classify_body_fat_with_margin(
+    age: u32,
+    bf: f64,
+    is_male: bool,
+    margin: f64,
+) -> Classification {
+    let table = if is_male { &MALE_TABLE } else { &FEMALE_TABLE };
+    let category = table.classify(age, bf);
+
+    let Some(cohort) = table.cohort(age) else {
+        return Classification {
+            category,
+            borderline: None,
+            boundary_distance: f64::INFINITY,
+            cohort_note: None,
+        };
+    };
+
+    let boundaries = std::iter::once((table.essential_fat, BELOW_ESSENTIAL, CATEGORIES[0])).chain(
+        table
+            .boundaries(cohort)
+            .into_iter()
+            .zip(CATEGORIES.windows(2))
+            .map(|(boundary, pair)| (boundary, pair[0], pair[1])),
+    );
+    let (boundary, below, above) = boundaries
+        .min_by(|a, b| (bf - a.0).abs().total_cmp(&(bf - b.0).abs()))
+        .expect("every cohort has at least one boundary");
+
+    let boundary_distance = (bf - boundary).abs();
+    let neighbour = if bf < boundary { above } else { below };
+    Classification {
+        category,
+        borderline: (boundary_distance <= margin && neighbour != category).then_some(neighbour),
+        boundary_distance,
+        cohort_note: cohort.note,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borderline_classification() {
+        // Male 20-29: Good ends at 17.4, Average starts at 17.5
+        let near = classify_body_fat_with_margin(25, 17.3, true, 0.5);
+        assert_eq!(near.category, "Good");
+        assert_eq!(near.borderline, Some("Average"));
+        assert_eq!(near.label(), "Good (borderline Average)");
+        assert!((near.boundary_distance - 0.15).abs() < 1e-9);
+
+        let clear = classify_body_fat_with_margin(25, 15.5, true, 0.5);
+        assert_eq!(clear.borderline, None);
+        assert_eq!(clear.label(), "Good");
+
+        let lean = classify_body_fat_with_margin(25, 10.2, false, 0.5);
+        assert_eq!(
+            lean.label(),
+            "Excellent (borderline Extremely Lean (Below Essential Fat))"
+        );
+    }
+
+    #[test]
+    fn test_cohorts_cover_teens_and_seventies() {
+        // Values in the gaps between published ranges go to the nearer side
+        assert_eq!(classify_body_fat_male(25, 13.84), "Excellent");
+        assert_eq!(classify_body_fat_male(25, 13.86), "Good");
+        assert_eq!(classify_body_fat_female(35, 19.4), "Excellent");
+        assert_eq!(classify_body_fat_female(35, 37.0), "Poor");
+
+        assert_eq!(classify_body_fat_male(19, 15.0), "Good");
+        assert_eq!(classify_body_fat_male(75, 24.0), "Good");
+        assert_eq!(classify_body_fat_female(82, 41.5), "Poor");
+        assert_eq!(classify_body_fat_male(16, 15.0), "Unclassified");
+        assert_eq!(classify_body_fat_female(16, 8.0), BELOW_ESSENTIAL);
+
+        let old = classify_body_fat_with_margin(72, 26.0, true, 0.5);
+        assert_eq!(
+            old.label(),
+            "Average (borderline Good; 70+ ranges extrapolated from ages 50-69)"
+        );
+        assert_eq!(
+            classify_body_fat_with_margin(45, 18.0, true, 0.5).cohort_note,
+            None
+        );
+
+        for table in [MALE_TABLE, FEMALE_TABLE] {
+            for pair in table.cohorts.windows(2) {
+                assert_eq!(pair[0].max_age + 1, pair[1].min_age);
+            }
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use crate::classification::{Classification, classify_body_fat_with_margin};
+use crate::formulas::{AUTOMATIC_PROTOCOL, Formula, select_formula};
+use crate::measurements::{ALL_SITES, Measurements, site_label};
+use crate::uncertainty::reading_uncertainty;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Plausible skinfold thickness at any site, in mm. Readings outside this
+/// are almost always typos or a misread caliper.
+pub const SKINFOLD_RANGE_MM: RangeInclusive<f64> = 1.0..=80.0;
+
+/// Acceptable readings at a site for an age group, replacing
+/// `SKINFOLD_RANGE_MM` there, e.g. tighter limits for youth athletes or
+/// looser ones for bariatric clients.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteRange {
+    /// Site key, or "*" for every site.
+    pub site: String,
+    /// Ages the range applies to, every age when `None`.
+    pub ages: Option<RangeInclusive<u32>>,
+    pub range_mm: RangeInclusive<f64>,
+}
+
+/// Range a reading at `site` must fall in at `age`: the last of `ranges`
+/// that matches, or `SKINFOLD_RANGE_MM` when none do.
+pub fn skinfold_range(ranges: &[SiteRange], site: &str, age: u32) -> RangeInclusive<f64> {
+    ranges
+        .iter()
+        .rev()
+        .find(|range| {
+            (range.site == "*" || range.site == site)
+                && range.ages.as_ref().is_none_or(|ages| ages.contains(&age))
+        })
+        .map_or(SKINFOLD_RANGE_MM, |range| range.range_mm.clone())
+}
+
+/// Ages the Jackson & Pollock equations were validated on.
+pub const VALIDATED_AGES: RangeInclusive<u32> = 18..=61;
+
+/// Why a set of inputs could not produce a result.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalculationError {
+    /// A site the formula needs has no reading.
+    MissedSite(&'static str),
+    /// A reading was entered but is not a number.
+    InvalidNumber(&'static str),
+    /// A reading is outside `min`–`max` mm, `SKINFOLD_RANGE_MM` unless the
+    /// subject's `ranges` say otherwise.
+    OutOfRange {
+        site: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    /// Age is outside 1–119.
+    InvalidAge,
+    /// Age was entered but is not a whole number.
+    NonNumericAge,
+    /// A tape measurement, or the height, is missing or not a positive
+    /// number of `unit`.
+    InvalidCircumference {
+        field: &'static str,
+        unit: &'static str,
+    },
+    /// The waist, plus the hip for women, is no larger than the neck, which
+    /// the US Navy equation can't take the logarithm of.
+    WaistNotAboveNeck { is_male: bool },
+    /// The chosen protocol needs sites marked as impossible to measure.
+    ExcludedSites {
+        formula: Formula,
+        sites: Vec<&'static str>,
+    },
+    /// No formula works without the excluded sites.
+    NoFormula { excluded: Vec<String> },
+    /// The protocol is neither a formula's label nor "Automatic".
+    UnknownProtocol(String),
+}
+
+impl CalculationError {
+    /// Input the error belongs to: a site key, "age", or "protocol".
+    pub fn field(&self) -> &'static str {
+        match self {
+            CalculationError::MissedSite(site)
+            | CalculationError::InvalidNumber(site)
+            | CalculationError::OutOfRange { site, .. } => site,
+            CalculationError::InvalidAge | CalculationError::NonNumericAge => "age",
+            CalculationError::InvalidCircumference { field, .. } => field,
+            CalculationError::WaistNotAboveNeck { .. } => "waist",
+            CalculationError::ExcludedSites { .. }
+            | CalculationError::NoFormula { .. }
+            | CalculationError::UnknownProtocol(_) => "protocol",
+        }
+    }
+}
+
+impl fmt::Display for CalculationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let labels = |sites: &[&str]| {
+            sites
+                .iter()
+                .map(|site| site_label(site))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            CalculationError::MissedSite(site) => {
+                write!(f, "{} measurement is required", site_label(site))
+            }
+            CalculationError::InvalidNumber(site) => {
+                write!(f, "{} measurement must be a valid number", site_label(site))
+            }
+            CalculationError::OutOfRange {
+                site,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "{} measurement of {} mm is outside the plausible {}–{} mm range",
+                site_label(site),
+                value,
+                min,
+                max
+            ),
+            CalculationError::InvalidAge => {
+                write!(f, "Age must be a valid number between 1 and 119")
+            }
+            CalculationError::NonNumericAge => write!(f, "Age must be a whole number of years"),
+            CalculationError::InvalidCircumference { field, unit } => write!(
+                f,
+                "{} must be a positive number of {}",
+                tape_label(field),
+                unit
+            ),
+            CalculationError::WaistNotAboveNeck { is_male: true } => {
+                write!(f, "Waist must be larger than neck")
+            }
+            CalculationError::WaistNotAboveNeck { is_male: false } => {
+                write!(f, "Waist plus hip must be larger than neck")
+            }
+            CalculationError::ExcludedSites { formula, sites } => write!(
+                f,
+                "The {} formula needs the {} site(s)",
+                formula.name(),
+                labels(sites)
+            ),
+            CalculationError::NoFormula { excluded } => write!(
+                f,
+                "No supported formula works without the {} site(s)",
+                excluded.join(", ")
+            ),
+            CalculationError::UnknownProtocol(protocol) => write!(
+                f,
+                "Unknown protocol \"{}\"; expected {} or one of {}",
+                protocol,
+                AUTOMATIC_PROTOCOL,
+                Formula::ALL
+                    .iter()
+                    .map(|formula| formula.protocol_label())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CalculationError {}
+
+/// Display name of a tape measurement field.
+pub fn tape_label(field: &str) -> &'static str {
+    match field {
+        "height" => "Height",
+        "neck" => "Neck",
+        "waist" => "Waist",
+        "hip" => "Hip",
+        _ => "Measurement",
+    }
+}
+
+/// Age in whole years as typed, checked against the range the equations
+/// accept.
+pub fn parse_age(text: &str) -> Result<u32, CalculationError> {
+    let text = text.trim();
+    match text.parse::<u32>() {
+        Ok(age) if (1..120).contains(&age) => Ok(age),
+        // Negative or huge whole numbers are numbers, just not plausible ages
+        _ if text.parse::<i64>().is_ok() => Err(CalculationError::InvalidAge),
+        _ => Err(CalculationError::NonNumericAge),
+    }
+}
+
+/// Something that does not stop the calculation but makes the result less
+/// trustworthy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalculationWarning {
+    /// Age is outside `VALIDATED_AGES`.
+    AgeOutsideValidation(u32),
+}
+
+impl CalculationWarning {
+    /// Input the warning belongs to.
+    pub fn field(&self) -> &'static str {
+        match self {
+            CalculationWarning::AgeOutsideValidation(_) => "age",
+        }
+    }
+}
+
+impl fmt::Display for CalculationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalculationWarning::AgeOutsideValidation(age) => write!(
+                f,
+                "The skinfold equations were validated for ages {}–{}; results at {} are less reliable",
+                VALIDATED_AGES.start(),
+                VALIDATED_AGES.end(),
+                age
+            ),
+        }
+    }
+}
+
+/// The person measured and how they were measured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subject<'a> {
+    pub age: u32,
+    pub is_male: bool,
+    /// Protocol picker label, "Automatic" to pick from the measured sites.
+    pub protocol: &'a str,
+    /// Sites that could not be measured.
+    pub excluded: &'a [&'a str],
+    /// Overrides of the plausible reading range; empty for the built-in one.
+    pub ranges: &'a [SiteRange],
+}
+
+/// Everything worked out from one set of skinfold readings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyComposition {
+    pub formula: Formula,
+    /// Sum of the formula's skinfolds in mm.
+    pub sum: f64,
+    pub body_fat: f64,
+    /// Expected spread of the result from reading error, in percentage points.
+    pub uncertainty: f64,
+    pub classification: Classification,
+    pub warnings: Vec<CalculationWarning>,
+}
+
+/// Select the formula, check the readings it needs and estimate body fat,
+/// reporting every problem at once.
+pub fn compute(
+    measurements: &Measurements,
+    subject: &Subject,
+) -> Result<BodyComposition, Vec<CalculationError>> {
+    let mut errors = Vec::new();
+    if !(1..120).contains(&subject.age) {
+        errors.push(CalculationError::InvalidAge);
+    }
+    // Zero means the site was not measured
+    for site in ALL_SITES {
+        let value = measurements.get(site);
+        let range = skinfold_range(subject.ranges, site, subject.age);
+        if value != 0.0 && !range.contains(&value) {
+            errors.push(CalculationError::OutOfRange {
+                site,
+                value,
+                min: *range.start(),
+                max: *range.end(),
+            });
+        }
+    }
+
+    let formula = match select_formula(subject.protocol, subject.excluded, subject.is_male) {
+        Ok(formula) => Some(formula),
+        Err(e) => {
+            errors.push(e);
+            None
+        }
+    };
+    for site in formula.map_or(&[][..], |formula| formula.sites(subject.is_male)) {
+        if measurements.get(site) == 0.0 {
+            errors.push(CalculationError::MissedSite(site));
+        }
+    }
+
+    let Some(formula) = formula.filter(|_| errors.is_empty()) else {
+        return Err(errors);
+    };
+    let sum = formula.sum(measurements, subject.is_male);
+    let body_fat = formula.body_fat(sum, subject.age, subject.is_male);
+    let uncertainty = reading_uncertainty(formula, sum, subject.age, subject.is_male);
+    let mut warnings = Vec::new();
+    if !VALIDATED_AGES.contains(&subject.age) {
+        warnings.push(CalculationWarning::AgeOutsideValidation(subject.age));
+    }
+    Ok(BodyComposition {
+        formula,
+        sum,
+        body_fat,
+        uncertainty,
+        classification: classify_body_fat_with_margin(
+            subject.age,
+            body_fat,
+            subject.is_male,
+            uncertainty,
+        ),
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_collects_typed_errors() {
+        let mut measurements = Measurements::new();
+        measurements.set_measurement("chest", 10.0);
+        measurements.set_measurement("abdominal", -2.0);
+        let subject = Subject {
+            age: 0,
+            is_male: true,
+            protocol: "3-site",
+            excluded: &[],
+            ranges: &[],
+        };
+        let errors = compute(&measurements, &subject).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                CalculationError::InvalidAge,
+                CalculationError::OutOfRange {
+                    site: "abdominal",
+                    value: -2.0,
+                    min: 1.0,
+                    max: 80.0,
+                },
+                CalculationError::MissedSite("thigh"),
+            ]
+        );
+        assert_eq!(errors[2].field(), "thigh");
+        assert_eq!(errors[2].to_string(), "Thigh measurement is required");
+        assert_eq!(parse_age(" 30 "), Ok(30));
+        assert_eq!(parse_age("-4"), Err(CalculationError::InvalidAge));
+        assert_eq!(parse_age("120"), Err(CalculationError::InvalidAge));
+        assert_eq!(parse_age("30.5"), Err(CalculationError::NonNumericAge));
+        assert_eq!(parse_age(""), Err(CalculationError::NonNumericAge));
+
+        measurements.set_measurement("abdominal", 20.0);
+        measurements.set_measurement("thigh", 15.0);
+        let composition = compute(&measurements, &Subject { age: 30, ..subject }).unwrap();
+        assert_eq!(composition.formula, Formula::JacksonPollock3);
+        assert_eq!(composition.sum, 45.0);
+        assert!(composition.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_implausible_inputs() {
+        let mut measurements = Measurements::new();
+        for site in ["chest", "abdominal", "thigh"] {
+            measurements.set_measurement(site, 12.0);
+        }
+        let subject = Subject {
+            age: 70,
+            is_male: true,
+            protocol: "3-site",
+            excluded: &[],
+            ranges: &[],
+        };
+        let composition = compute(&measurements, &subject).unwrap();
+        assert_eq!(
+            composition.warnings,
+            vec![CalculationWarning::AgeOutsideValidation(70)]
+        );
+
+        measurements.set_measurement("thigh", 0.5);
+        measurements.set_measurement("biceps", 95.0);
+        let errors = compute(&measurements, &subject).unwrap_err();
+        assert_eq!(
+            errors.iter().map(|e| e.field()).collect::<Vec<_>>(),
+            ["thigh", "biceps"]
+        );
+    }
+
+    #[test]
+    fn test_site_ranges_override_the_built_in_one() {
+        let ranges = [
+            SiteRange {
+                site: "*".to_string(),
+                ages: None,
+                range_mm: 1.0..=120.0,
+            },
+            SiteRange {
+                site: "thigh".to_string(),
+                ages: Some(12..=17),
+                range_mm: 2.0..=40.0,
+            },
+        ];
+        assert_eq!(skinfold_range(&[], "thigh", 30), SKINFOLD_RANGE_MM);
+        assert_eq!(skinfold_range(&ranges, "thigh", 30), 1.0..=120.0);
+        assert_eq!(skinfold_range(&ranges, "thigh", 15), 2.0..=40.0);
+        assert_eq!(skinfold_range(&ranges, "chest", 15), 1.0..=120.0);
+
+        let mut measurements = Measurements::new();
+        measurements.set_measurement("chest", 95.0);
+        measurements.set_measurement("abdominal", 100.0);
+        measurements.set_measurement("thigh", 45.0);
+        let subject = Subject {
+            age: 16,
+            is_male: true,
+            protocol: "3-site",
+            excluded: &[],
+            ranges: &ranges,
+        };
+        assert_eq!(
+            compute(&measurements, &subject).unwrap_err(),
+            vec![CalculationError::OutOfRange {
+                site: "thigh",
+                value: 45.0,
+                min: 2.0,
+                max: 40.0,
+            }]
+        );
+        assert!(compute(&measurements, &Subject { age: 40, ..subject }).is_ok());
+    }
+}
@@ -0,0 +1,44 @@
+/// Activity levels and the multiplier applied to BMR to estimate total daily
+/// energy expenditure, least active first.
+pub const ACTIVITY_LEVELS: [(&str, f64); 5] = [
+    ("Sedentary", 1.2),
+    ("Lightly active", 1.375),
+    ("Moderately active", 1.55),
+    ("Very active", 1.725),
+    ("Extra active", 1.9),
+];
+
+/// Fat-free mass in kg for a body weight in kg and a body fat percentage.
+pub fn lean_body_mass(weight_kg: f64, body_fat_percentage: f64) -> f64 {
+    weight_kg * (1.0 - body_fat_percentage / 100.0)
+}
+
+/// Katch-McArdle basal metabolic rate in kcal/day. It depends only on lean
+/// mass, so it needs no age or sex correction.
+pub fn katch_mcardle_bmr(lean_mass_kg: f64) -> f64 {
+    370.0 + 21.6 * lean_mass_kg
+}
+
+/// Total daily energy expenditure in kcal/day for an `ACTIVITY_LEVELS` label.
+pub fn tdee(bmr: f64, activity: &str) -> Option<f64> {
+    ACTIVITY_LEVELS
+        .iter()
+        .find(|(label, _)| *label == activity)
+        .map(|(_, multiplier)| bmr * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_katch_mcardle_energy() {
+        let lean_mass = lean_body_mass(80.0, 20.0);
+        assert!((lean_mass - 64.0).abs() < 1e-9);
+        let bmr = katch_mcardle_bmr(lean_mass);
+        assert!((bmr - 1752.4).abs() < 1e-9);
+        assert!((tdee(bmr, "Sedentary").unwrap() - 2102.88).abs() < 1e-9);
+        assert!((tdee(bmr, "Moderately active").unwrap() - 2716.22).abs() < 1e-9);
+        assert_eq!(tdee(bmr, "Couch"), None);
+    }
+}
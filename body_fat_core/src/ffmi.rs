@@ -0,0 +1,39 @@
+/// Fat-free mass index: lean mass in kg over height in metres squared.
+pub fn ffmi(lean_mass_kg: f64, height_m: f64) -> f64 {
+    lean_mass_kg / (height_m * height_m)
+}
+
+/// FFMI adjusted to a height of 1.8 m, so tall and short lifters compare
+/// fairly (Kouri et al., 1995).
+pub fn normalized_ffmi(ffmi: f64, height_m: f64) -> f64 {
+    ffmi + 6.1 * (1.8 - height_m)
+}
+
+/// Category for a normalized FFMI. Thresholds follow the bodybuilding
+/// literature for men; women's are set 3 points lower to match their
+/// typically lower lean mass.
+pub fn classify_ffmi(normalized_ffmi: f64, is_male: bool) -> &'static str {
+    let offset = if is_male { 0.0 } else { 3.0 };
+    match normalized_ffmi + offset {
+        value if value < 18.0 => "Below average",
+        value if value < 20.0 => "Average",
+        value if value < 22.0 => "Above average",
+        _ => "Exceptional",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffmi() {
+        let index = ffmi(64.0, 1.75);
+        assert!((index - 20.897_959_183_673_47).abs() < 1e-9);
+        assert!((normalized_ffmi(index, 1.75) - 21.202_959_183_673_47).abs() < 1e-9);
+        assert_eq!(classify_ffmi(21.2, true), "Above average");
+        assert_eq!(classify_ffmi(21.2, false), "Exceptional");
+        assert_eq!(classify_ffmi(15.0, false), "Average");
+        assert_eq!(classify_ffmi(17.9, true), "Below average");
+    }
+}
@@ -0,0 +1,428 @@
+use crate::compute::CalculationError;
+use crate::measurements::Measurements;
+
+/// Jackson & Pollock 7-site estimate from the sum of all seven skinfolds.
+pub fn calculate_body_fat(total_measurement: f64, age: u32, is_male: bool) -> f64 {
+    let body_density = if is_male {
+        // Male Jackson & Pollock 7-site equation
+        1.112 - 0.00043499 * total_measurement + 0.00000055 * total_measurement.powi(2)
+            - 0.00028826 * (age as f64)
+    } else {
+        // Female Jackson & Pollock 7-site equation
+        1.097 - 0.00046971 * total_measurement + 0.00000056 * total_measurement.powi(2)
+            - 0.00012828 * (age as f64)
+    };
+
+    (495.0 / body_density) - 450.0
+}
+
+/// Jackson & Pollock 3-site estimate; see [`Formula::sites`] for the sites.
+pub fn calculate_body_fat_3_site(total_measurement: f64, age: u32, is_male: bool) -> f64 {
+    let body_density = if is_male {
+        // Male Jackson & Pollock 3-site equation (chest, abdominal, thigh)
+        1.10938 - 0.0008267 * total_measurement + 0.0000016 * total_measurement.powi(2)
+            - 0.0002574 * (age as f64)
+    } else {
+        // Female Jackson & Pollock 3-site equation (triceps, suprailiac, thigh)
+        1.0994921 - 0.0009929 * total_measurement + 0.0000023 * total_measurement.powi(2)
+            - 0.0001392 * (age as f64)
+    };
+
+    (495.0 / body_density) - 450.0
+}
+
+/// Jackson & Pollock alternate 3-site estimate.
+pub fn calculate_body_fat_3_site_alt(total_measurement: f64, age: u32, is_male: bool) -> f64 {
+    let body_density = if is_male {
+        // Male Jackson & Pollock alternate 3-site equation (chest, triceps, subscapular)
+        1.1125025 - 0.0013125 * total_measurement + 0.0000055 * total_measurement.powi(2)
+            - 0.000244 * (age as f64)
+    } else {
+        // Female Jackson & Pollock alternate 3-site equation (triceps, abdominal, suprailiac)
+        1.089733 - 0.0009245 * total_measurement + 0.0000025 * total_measurement.powi(2)
+            - 0.0000979 * (age as f64)
+    };
+
+    (495.0 / body_density) - 450.0
+}
+
+/// Durnin & Womersley density coefficients (c, m) by minimum age, for
+/// density = c - m * log10(sum of four skinfolds). Under-17s use the youngest band.
+const DURNIN_WOMERSLEY_MALE: [(u32, f64, f64); 5] = [
+    (17, 1.1620, 0.0630),
+    (20, 1.1631, 0.0632),
+    (30, 1.1422, 0.0544),
+    (40, 1.1620, 0.0700),
+    (50, 1.1715, 0.0779),
+];
+
+const DURNIN_WOMERSLEY_FEMALE: [(u32, f64, f64); 5] = [
+    (17, 1.1549, 0.0678),
+    (20, 1.1599, 0.0717),
+    (30, 1.1423, 0.0632),
+    (40, 1.1333, 0.0612),
+    (50, 1.1339, 0.0645),
+];
+
+/// Durnin & Womersley 4-site estimate from the sum of the biceps, triceps,
+/// subscapular and suprailiac skinfolds.
+pub fn calculate_body_fat_durnin_womersley(total_measurement: f64, age: u32, is_male: bool) -> f64 {
+    let table = if is_male {
+        &DURNIN_WOMERSLEY_MALE
+    } else {
+        &DURNIN_WOMERSLEY_FEMALE
+    };
+    let (_, c, m) = table
+        .iter()
+        .rev()
+        .find(|(min_age, _, _)| age >= *min_age)
+        .unwrap_or(&table[0]);
+    let body_density = c - m * total_measurement.log10();
+
+    (495.0 / body_density) - 450.0
+}
+
+/// Formula name recorded for tape measure sessions.
+pub const NAVY_FORMULA: &str = "US Navy";
+
+/// US Navy circumference estimate. Circumferences and height are in
+/// centimetres; `hip_cm` is only used for women.
+pub fn calculate_body_fat_navy(
+    neck_cm: f64,
+    waist_cm: f64,
+    hip_cm: f64,
+    height_cm: f64,
+    is_male: bool,
+) -> Result<f64, CalculationError> {
+    let mut fields = vec![
+        ("height", height_cm),
+        ("neck", neck_cm),
+        ("waist", waist_cm),
+    ];
+    if !is_male {
+        fields.push(("hip", hip_cm));
+    }
+    if let Some((field, _)) = fields
+        .into_iter()
+        .find(|(_, value)| !value.is_finite() || *value <= 0.0)
+    {
+        return Err(CalculationError::InvalidCircumference { field, unit: "cm" });
+    }
+
+    let body_density = if is_male {
+        if waist_cm <= neck_cm {
+            return Err(CalculationError::WaistNotAboveNeck { is_male });
+        }
+        // Male US Navy equation
+        1.0324 - 0.19077 * (waist_cm - neck_cm).log10() + 0.15456 * height_cm.log10()
+    } else {
+        if waist_cm + hip_cm <= neck_cm {
+            return Err(CalculationError::WaistNotAboveNeck { is_male });
+        }
+        // Female US Navy equation
+        1.29579 - 0.35004 * (waist_cm + hip_cm - neck_cm).log10() + 0.22100 * height_cm.log10()
+    };
+
+    Ok((495.0 / body_density) - 450.0)
+}
+
+/// Sites used by the Jackson & Pollock 7-site equation.
+const JACKSON_POLLOCK_7_SITES: [&str; 7] = [
+    "chest",
+    "abdominal",
+    "thigh",
+    "triceps",
+    "subscapular",
+    "suprailiac",
+    "midaxillary",
+];
+
+/// Skinfold equations the calculator can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Formula {
+    JacksonPollock7,
+    JacksonPollock3,
+    JacksonPollock3Alt,
+    DurninWomersley,
+}
+
+impl Formula {
+    /// Every supported formula, most preferred first.
+    pub const ALL: [Formula; 4] = [
+        Formula::JacksonPollock7,
+        Formula::JacksonPollock3,
+        Formula::JacksonPollock3Alt,
+        Formula::DurninWomersley,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Formula::JacksonPollock7 => "Jackson & Pollock 7-site",
+            Formula::JacksonPollock3 => "Jackson & Pollock 3-site",
+            Formula::JacksonPollock3Alt => "Jackson & Pollock alternate 3-site",
+            Formula::DurninWomersley => "Durnin & Womersley 4-site",
+        }
+    }
+
+    /// Short name shown in the protocol picker.
+    pub fn protocol_label(self) -> &'static str {
+        match self {
+            Formula::JacksonPollock7 => "7-site",
+            Formula::JacksonPollock3 => "3-site",
+            Formula::JacksonPollock3Alt => "Alternate 3-site",
+            Formula::DurninWomersley => "Durnin-Womersley",
+        }
+    }
+
+    pub fn from_protocol_label(label: &str) -> Option<Formula> {
+        Formula::ALL
+            .into_iter()
+            .find(|formula| formula.protocol_label() == label)
+    }
+
+    pub fn sites(self, is_male: bool) -> &'static [&'static str] {
+        match (self, is_male) {
+            (Formula::JacksonPollock7, _) => &JACKSON_POLLOCK_7_SITES,
+            (Formula::JacksonPollock3, true) => &["chest", "abdominal", "thigh"],
+            (Formula::JacksonPollock3, false) => &["triceps", "suprailiac", "thigh"],
+            (Formula::JacksonPollock3Alt, true) => &["chest", "triceps", "subscapular"],
+            (Formula::JacksonPollock3Alt, false) => &["triceps", "abdominal", "suprailiac"],
+            (Formula::DurninWomersley, _) => &["biceps", "triceps", "subscapular", "suprailiac"],
+        }
+    }
+
+    pub fn sum(self, measurements: &Measurements, is_male: bool) -> f64 {
+        match self {
+            Formula::JacksonPollock7 => measurements.total(),
+            _ => self
+                .sites(is_male)
+                .iter()
+                .map(|site| measurements.get(site))
+                .sum(),
+        }
+    }
+
+    pub fn body_fat(self, total_measurement: f64, age: u32, is_male: bool) -> f64 {
+        match self {
+            Formula::JacksonPollock7 => calculate_body_fat(total_measurement, age, is_male),
+            Formula::JacksonPollock3 => calculate_body_fat_3_site(total_measurement, age, is_male),
+            Formula::JacksonPollock3Alt => {
+                calculate_body_fat_3_site_alt(total_measurement, age, is_male)
+            }
+            Formula::DurninWomersley => {
+                calculate_body_fat_durnin_womersley(total_measurement, age, is_male)
+            }
+        }
+    }
+
+    /// Most preferred formula that needs none of the `excluded` sites.
+    pub fn best_available(excluded: &[&str], is_male: bool) -> Option<Formula> {
+        Formula::ALL.into_iter().find(|formula| {
+            formula
+                .sites(is_male)
+                .iter()
+                .all(|site| !excluded.contains(site))
+        })
+    }
+}
+
+/// Protocol label that picks the most preferred formula the measured sites
+/// allow.
+pub const AUTOMATIC_PROTOCOL: &str = "Automatic";
+
+/// Formula for the chosen protocol, or the best one that avoids the excluded
+/// sites when the protocol is "Automatic". Any other label is an error rather
+/// than a fallback, so a misspelt protocol is never silently replaced.
+pub fn select_formula(
+    protocol: &str,
+    excluded: &[&str],
+    is_male: bool,
+) -> Result<Formula, CalculationError> {
+    if protocol == AUTOMATIC_PROTOCOL {
+        return Formula::best_available(excluded, is_male).ok_or_else(|| {
+            CalculationError::NoFormula {
+                excluded: excluded.iter().map(|site| site.to_string()).collect(),
+            }
+        });
+    }
+    let formula = Formula::from_protocol_label(protocol)
+        .ok_or_else(|| CalculationError::UnknownProtocol(protocol.to_string()))?;
+    let sites: Vec<&'static str> = formula
+        .sites(is_male)
+        .iter()
+        .copied()
+        .filter(|site| excluded.contains(site))
+        .collect();
+    if sites.is_empty() {
+        Ok(formula)
+    } else {
+        Err(CalculationError::ExcludedSites { formula, sites })
+    }
+}
+
+/// Largest expected gap, in percentage points, between the 7-site and 3-site
+/// estimates for the same person. Both equations share sites, so genuine
+/// disagreement beyond this usually means one of the extra sites was misread.
+pub const SITE_CONSISTENCY_TOLERANCE: f64 = 3.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsistencyCheck {
+    pub three_site: f64,
+    pub difference: f64,
+}
+
+impl ConsistencyCheck {
+    pub fn is_consistent(&self) -> bool {
+        self.difference.abs() <= SITE_CONSISTENCY_TOLERANCE
+    }
+}
+
+/// Compare a 7-site result against the 3-site estimate from the same readings.
+pub fn check_site_consistency(
+    measurements: &Measurements,
+    seven_site: f64,
+    age: u32,
+    is_male: bool,
+) -> ConsistencyCheck {
+    let formula = Formula::JacksonPollock3;
+    let three_site = formula.body_fat(formula.sum(measurements, is_male), age, is_male);
+    ConsistencyCheck {
+        three_site,
+        difference: seven_site - three_site,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_body_fat_male() {
+        let bf = calculate_body_fat(100.0, 30, true);
+        assert!(bf > 0.0 && bf < 50.0); // Reasonable range
+    }
+
+    #[test]
+    fn test_calculate_body_fat_female() {
+        let bf = calculate_body_fat(100.0, 30, false);
+        assert!(bf > 0.0 && bf < 50.0); // Reasonable range
+    }
+
+    #[test]
+    fn test_durnin_womersley() {
+        // 40 mm over four sites for a 25-year-old man: density 1.1631 - 0.0632 * log10(40)
+        let density: f64 = 1.1631 - 0.0632 * 40f64.log10();
+        let expected = 495.0 / density - 450.0;
+        assert!((calculate_body_fat_durnin_womersley(40.0, 25, true) - expected).abs() < 1e-9);
+
+        // Teenagers use the youngest band, over-50s the oldest
+        assert_eq!(
+            calculate_body_fat_durnin_womersley(40.0, 15, false),
+            calculate_body_fat_durnin_womersley(40.0, 18, false)
+        );
+        assert_eq!(
+            calculate_body_fat_durnin_womersley(40.0, 80, true),
+            calculate_body_fat_durnin_womersley(40.0, 55, true)
+        );
+        assert_eq!(
+            Formula::DurninWomersley.sites(false),
+            ["biceps", "triceps", "subscapular", "suprailiac"]
+        );
+    }
+
+    #[test]
+    fn test_navy_body_fat() {
+        let male = calculate_body_fat_navy(38.0, 85.0, 0.0, 180.0, true).unwrap();
+        assert!((male - 16.1).abs() < 0.1);
+        let female = calculate_body_fat_navy(32.0, 72.0, 98.0, 165.0, false).unwrap();
+        assert!((female - 27.4).abs() < 0.1);
+
+        assert_eq!(
+            calculate_body_fat_navy(40.0, 40.0, 0.0, 180.0, true),
+            Err(CalculationError::WaistNotAboveNeck { is_male: true })
+        );
+        assert_eq!(
+            calculate_body_fat_navy(32.0, 72.0, f64::NAN, 165.0, false),
+            Err(CalculationError::InvalidCircumference {
+                field: "hip",
+                unit: "cm"
+            })
+        );
+        // Hip is ignored for men
+        assert_eq!(
+            calculate_body_fat_navy(38.0, 85.0, 0.0, 180.0, true),
+            calculate_body_fat_navy(38.0, 85.0, 100.0, 180.0, true)
+        );
+    }
+
+    #[test]
+    fn test_site_consistency_flags_misread_site() {
+        let mut measurements = Measurements::new();
+        for site in [
+            "chest",
+            "abdominal",
+            "thigh",
+            "triceps",
+            "subscapular",
+            "suprailiac",
+            "midaxillary",
+        ] {
+            measurements.set_measurement(site, 15.0);
+        }
+        let seven_site = calculate_body_fat(measurements.total(), 30, true);
+        assert!(check_site_consistency(&measurements, seven_site, 30, true).is_consistent());
+
+        // A midaxillary reading typed as 150 instead of 15 only affects the 7-site sum
+        measurements.midaxillary = 150.0;
+        let seven_site = calculate_body_fat(measurements.total(), 30, true);
+        assert!(!check_site_consistency(&measurements, seven_site, 30, true).is_consistent());
+    }
+
+    #[test]
+    fn test_formula_fallback_skips_excluded_sites() {
+        assert_eq!(
+            Formula::best_available(&[], true),
+            Some(Formula::JacksonPollock7)
+        );
+        assert_eq!(
+            Formula::best_available(&["midaxillary"], true),
+            Some(Formula::JacksonPollock3)
+        );
+        assert_eq!(
+            Formula::best_available(&["thigh"], true),
+            Some(Formula::JacksonPollock3Alt)
+        );
+        assert_eq!(
+            Formula::best_available(&["thigh"], false),
+            Some(Formula::JacksonPollock3Alt)
+        );
+        assert_eq!(
+            Formula::best_available(&["thigh", "chest"], true),
+            Some(Formula::DurninWomersley)
+        );
+        assert_eq!(Formula::best_available(&["thigh", "triceps"], true), None);
+    }
+
+    #[test]
+    fn test_select_formula_honours_protocol() {
+        assert_eq!(
+            select_formula("Automatic", &["midaxillary"], true),
+            Ok(Formula::JacksonPollock3)
+        );
+        assert_eq!(
+            select_formula("3-site", &["midaxillary"], false),
+            Ok(Formula::JacksonPollock3)
+        );
+        assert_eq!(
+            select_formula("Alternate 3-site", &[], true),
+            Ok(Formula::JacksonPollock3Alt)
+        );
+        // An explicit protocol never falls back to another formula
+        assert!(select_formula("3-site", &["thigh"], true).is_err());
+        assert!(select_formula("Automatic", &crate::ALL_SITES, true).is_err());
+        assert_eq!(
+            select_formula("3 site", &[], true),
+            Err(CalculationError::UnknownProtocol("3 site".to_string()))
+        );
+    }
+}
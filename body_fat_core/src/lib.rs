@@ -0,0 +1,80 @@
+//! Skinfold body fat equations, reference classifications, measurement
+//! uncertainty, energy estimates and derived time series, free of any UI
+//! dependency.
+
+pub mod calipers;
+pub mod classification;
+pub mod compute;
+pub mod energy;
+pub mod ffmi;
+pub mod formulas;
+pub mod measurements;
+pub mod series;
+pub mod uncertainty;
+pub mod units;
+
+pub use calipers::{CALIPERS, Caliper, caliper, harpenden_equivalent};
+pub use classification::{
+    CATEGORIES, Classification, ClassificationTable, Cohort, FEMALE_TABLE, MALE_TABLE,
+    classify_body_fat_female, classify_body_fat_male, classify_body_fat_with_margin,
+};
+pub use compute::{
+    BodyComposition, CalculationError, CalculationWarning, SKINFOLD_RANGE_MM, SiteRange, Subject,
+    VALIDATED_AGES, compute, parse_age, skinfold_range, tape_label,
+};
+pub use energy::{ACTIVITY_LEVELS, katch_mcardle_bmr, lean_body_mass, tdee};
+pub use ffmi::{classify_ffmi, ffmi, normalized_ffmi};
+pub use formulas::{
+    AUTOMATIC_PROTOCOL, ConsistencyCheck, Formula, NAVY_FORMULA, calculate_body_fat,
+    calculate_body_fat_3_site, calculate_body_fat_3_site_alt, calculate_body_fat_durnin_womersley,
+    calculate_body_fat_navy, check_site_consistency, select_formula,
+};
+pub use measurements::{
+    ALL_SITES, Measurements, READING_SPREAD_TOLERANCE, combine_readings, readings_disagree,
+    site_label,
+};
+pub use series::{
+    DEFAULT_HALF_LIFE_DAYS, LinearTrend, Sample, lean_mass_series, linear_trend, smoothed_body_fat,
+    weekly_rate_of_change,
+};
+pub use uncertainty::{
+    MONTE_CARLO_RUNS, UncertaintySummary, reading_uncertainty, sensitivity_report,
+    simulate_body_fat_uncertainty,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Formula, classification and sensitivity for one set of readings must
+    /// stay well inside a frame, even in debug builds.
+    const CALCULATION_BUDGET: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_calculation_stays_within_budget() {
+        let mut measurements = Measurements::new();
+        for site in ALL_SITES {
+            measurements.set_measurement(site, 15.0);
+        }
+
+        const ITERATIONS: u32 = 1000;
+        for formula in Formula::ALL {
+            let start = Instant::now();
+            for _ in 0..ITERATIONS {
+                let total = formula.sum(&measurements, true);
+                let bf = formula.body_fat(total, 35, true);
+                let margin = reading_uncertainty(formula, total, 35, true);
+                std::hint::black_box(classify_body_fat_with_margin(35, bf, true, margin));
+                std::hint::black_box(sensitivity_report(formula, total, 35, true));
+            }
+            let average = start.elapsed() / ITERATIONS;
+            assert!(
+                average < CALCULATION_BUDGET,
+                "{} took {:?} per calculation",
+                formula.name(),
+                average
+            );
+        }
+    }
+}
@@ -0,0 +1,149 @@
+/// Skinfold readings in millimetres, zero when not taken.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurements {
+    pub chest: f64,
+    pub abdominal: f64,
+    pub thigh: f64,
+    pub triceps: f64,
+    pub subscapular: f64,
+    pub suprailiac: f64,
+    pub midaxillary: f64,
+    pub biceps: f64,
+}
+
+impl Measurements {
+    pub fn new() -> Self {
+        Self {
+            chest: 0.0,
+            abdominal: 0.0,
+            thigh: 0.0,
+            triceps: 0.0,
+            subscapular: 0.0,
+            suprailiac: 0.0,
+            midaxillary: 0.0,
+            biceps: 0.0,
+        }
+    }
+
+    /// Sum of the seven Jackson & Pollock sites.
+    pub fn total(&self) -> f64 {
+        self.chest
+            + self.abdominal
+            + self.thigh
+            + self.triceps
+            + self.subscapular
+            + self.suprailiac
+            + self.midaxillary
+    }
+
+    pub fn get(&self, site: &str) -> f64 {
+        match site {
+            "chest" => self.chest,
+            "abdominal" => self.abdominal,
+            "thigh" => self.thigh,
+            "triceps" => self.triceps,
+            "subscapular" => self.subscapular,
+            "suprailiac" => self.suprailiac,
+            "midaxillary" => self.midaxillary,
+            "biceps" => self.biceps,
+            _ => 0.0,
+        }
+    }
+
+    pub fn set_measurement(&mut self, site: &str, value: f64) {
+        match site {
+            "chest" => self.chest = value,
+            "abdominal" => self.abdominal = value,
+            "thigh" => self.thigh = value,
+            "triceps" => self.triceps = value,
+            "subscapular" => self.subscapular = value,
+            "suprailiac" => self.suprailiac = value,
+            "midaxillary" => self.midaxillary = value,
+            "biceps" => self.biceps = value,
+            _ => {}
+        }
+    }
+}
+
+impl Default for Measurements {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Every skinfold site, in the order the UI lists them.
+pub const ALL_SITES: [&str; 8] = [
+    "chest",
+    "abdominal",
+    "thigh",
+    "triceps",
+    "subscapular",
+    "suprailiac",
+    "midaxillary",
+    "biceps",
+];
+
+/// Site key capitalised for display, e.g. "triceps" becomes "Triceps".
+pub fn site_label(site: &str) -> String {
+    let mut chars = site.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Largest spread between repeated readings at one site, as a fraction of
+/// their mean, before they should be retaken.
+pub const READING_SPREAD_TOLERANCE: f64 = 0.10;
+
+/// Combine repeated readings at one site: the mean of two, or the median of
+/// three or more so a single misread fold is ignored.
+pub fn combine_readings(readings: &[f64]) -> Option<f64> {
+    match readings {
+        [] => None,
+        [a, b] => Some((a + b) / 2.0),
+        _ => {
+            let mut sorted = readings.to_vec();
+            sorted.sort_by(f64::total_cmp);
+            let middle = sorted.len() / 2;
+            Some(if sorted.len().is_multiple_of(2) {
+                (sorted[middle - 1] + sorted[middle]) / 2.0
+            } else {
+                sorted[middle]
+            })
+        }
+    }
+}
+
+/// Whether repeated readings differ by more than `READING_SPREAD_TOLERANCE`.
+pub fn readings_disagree(readings: &[f64]) -> bool {
+    let low = readings.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = readings.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let mean = readings.iter().sum::<f64>() / readings.len() as f64;
+    readings.len() > 1 && (high - low) > READING_SPREAD_TOLERANCE * mean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measurements_total() {
+        let mut measurements = Measurements::new();
+        measurements.chest = 10.0;
+        measurements.abdominal = 15.0;
+        assert_eq!(measurements.total(), 25.0);
+    }
+
+    #[test]
+    fn test_combine_repeated_readings() {
+        assert_eq!(combine_readings(&[]), None);
+        assert_eq!(combine_readings(&[12.0]), Some(12.0));
+        assert_eq!(combine_readings(&[12.0, 13.0]), Some(12.5));
+        assert_eq!(combine_readings(&[12.0, 19.0, 12.4]), Some(12.4));
+        assert!(!readings_disagree(&[12.0, 12.5, 12.2]));
+        assert!(readings_disagree(&[12.0, 14.0, 12.2]));
+        assert!(!readings_disagree(&[12.0]));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::energy::lean_body_mass;
+
+/// One measurement in a time series. `day` is time in days from any fixed
+/// origin, so callers can use whatever clock or date type they store.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sample {
+    pub day: f64,
+    pub body_fat: f64,
+    pub weight_kg: Option<f64>,
+}
+
+/// Half-life in days used when smoothing unless the caller has a reason to
+/// pick another; about one week of day-to-day noise is damped out.
+pub const DEFAULT_HALF_LIFE_DAYS: f64 = 7.0;
+
+/// Body fat smoothed with an exponential moving average whose weight decays
+/// with elapsed time, so irregular gaps between sessions are handled.
+/// Samples must be oldest first; returns `(day, smoothed)` pairs.
+pub fn smoothed_body_fat(samples: &[Sample], half_life_days: f64) -> Vec<(f64, f64)> {
+    let mut smoothed: Option<(f64, f64)> = None;
+    samples
+        .iter()
+        .map(|sample| {
+            let value = match smoothed {
+                Some((day, previous)) if half_life_days > 0.0 => {
+                    let keep = 0.5_f64.powf((sample.day - day).max(0.0) / half_life_days);
+                    keep * previous + (1.0 - keep) * sample.body_fat
+                }
+                _ => sample.body_fat,
+            };
+            smoothed = Some((sample.day, value));
+            (sample.day, value)
+        })
+        .collect()
+}
+
+/// Lean body mass in kg for every sample that has a weight.
+pub fn lean_mass_series(samples: &[Sample]) -> Vec<(f64, f64)> {
+    samples
+        .iter()
+        .filter_map(|sample| {
+            let weight = sample.weight_kg?;
+            Some((sample.day, lean_body_mass(weight, sample.body_fat)))
+        })
+        .collect()
+}
+
+/// Change in body fat percentage points per week between consecutive
+/// samples, placed at the later one. Samples on the same day are skipped.
+pub fn weekly_rate_of_change(samples: &[Sample]) -> Vec<(f64, f64)> {
+    samples
+        .windows(2)
+        .filter(|pair| pair[1].day > pair[0].day)
+        .map(|pair| {
+            let weeks = (pair[1].day - pair[0].day) / 7.0;
+            (pair[1].day, (pair[1].body_fat - pair[0].body_fat) / weeks)
+        })
+        .collect()
+}
+
+/// Least-squares straight line through body fat against time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearTrend {
+    /// Body fat the line passes through on day 0.
+    pub intercept: f64,
+    /// Change in body fat per day, negative when losing.
+    pub slope_per_day: f64,
+}
+
+impl LinearTrend {
+    pub fn value_at(&self, day: f64) -> f64 {
+        self.intercept + self.slope_per_day * day
+    }
+}
+
+/// Linear regression of body fat on day. Needs samples on two or more
+/// different days.
+pub fn linear_trend(samples: &[Sample]) -> Option<LinearTrend> {
+    if samples.is_empty() {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let mean_x = samples.iter().map(|s| s.day).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|s| s.body_fat).sum::<f64>() / n;
+    let (sxy, sxx) = samples.iter().fold((0.0, 0.0), |(sxy, sxx), s| {
+        let dx = s.day - mean_x;
+        (sxy + dx * (s.body_fat - mean_y), sxx + dx * dx)
+    });
+    if sxx == 0.0 {
+        return None;
+    }
+    let slope_per_day = sxy / sxx;
+    Some(LinearTrend {
+        intercept: mean_y - slope_per_day * mean_x,
+        slope_per_day,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(day: f64, body_fat: f64, weight_kg: Option<f64>) -> Sample {
+        Sample {
+            day,
+            body_fat,
+            weight_kg,
+        }
+    }
+
+    #[test]
+    fn test_derived_series() {
+        let samples = [
+            sample(0.0, 20.0, Some(80.0)),
+            sample(7.0, 19.0, None),
+            sample(7.0, 19.5, None),
+            sample(21.0, 18.5, Some(78.0)),
+        ];
+
+        let smoothed = smoothed_body_fat(&samples, DEFAULT_HALF_LIFE_DAYS);
+        assert_eq!(smoothed[0], (0.0, 20.0));
+        assert!((smoothed[1].1 - 19.5).abs() < 1e-9);
+        assert_eq!(smoothed[2], smoothed[1]);
+        assert!((smoothed[3].1 - 18.75).abs() < 1e-9);
+
+        let lean = lean_mass_series(&samples);
+        assert_eq!(lean.len(), 2);
+        assert!((lean[0].1 - 64.0).abs() < 1e-9);
+
+        let rates = weekly_rate_of_change(&samples);
+        assert_eq!(rates.len(), 2);
+        assert!((rates[0].1 + 1.0).abs() < 1e-9);
+        assert!((rates[1].1 + 0.5).abs() < 1e-9);
+
+        let trend = linear_trend(&[sample(0.0, 20.0, None), sample(14.0, 18.6, None)]).unwrap();
+        assert!((trend.slope_per_day + 0.1).abs() < 1e-9);
+        assert!((trend.value_at(28.0) - 17.2).abs() < 1e-9);
+        assert_eq!(linear_trend(&samples[1..3]), None);
+    }
+}
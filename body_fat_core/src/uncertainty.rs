@@ -0,0 +1,181 @@
+use crate::formulas::Formula;
+use crate::measurements::Measurements;
+
+/// Approximate technical error of measurement (mm) for a trained tester at each site.
+/// Looser folds such as the abdomen and thigh are harder to pinch consistently.
+pub const TYPICAL_SITE_ERROR_MM: [(&str, f64); 8] = [
+    ("chest", 1.0),
+    ("abdominal", 2.0),
+    ("thigh", 1.8),
+    ("triceps", 0.8),
+    ("subscapular", 1.0),
+    ("suprailiac", 1.2),
+    ("midaxillary", 1.0),
+    ("biceps", 0.8),
+];
+
+/// Typical reading error for `site`, 1 mm for unknown sites.
+pub fn typical_site_error(site: &str) -> f64 {
+    TYPICAL_SITE_ERROR_MM
+        .iter()
+        .find(|(key, _)| *key == site)
+        .map_or(1.0, |(_, error_mm)| *error_mm)
+}
+
+/// Change in body fat percentage per millimetre of skinfold, evaluated at the
+/// current sum. Uses a central difference so it works for any equation.
+pub fn body_fat_sensitivity(
+    formula: Formula,
+    total_measurement: f64,
+    age: u32,
+    is_male: bool,
+) -> f64 {
+    const STEP_MM: f64 = 0.01;
+    let above = formula.body_fat(total_measurement + STEP_MM, age, is_male);
+    let below = formula.body_fat(total_measurement - STEP_MM, age, is_male);
+    (above - below) / (2.0 * STEP_MM)
+}
+
+/// How far a typical reading error at each of the formula's sites moves the
+/// result, largest first.
+pub fn sensitivity_report(
+    formula: Formula,
+    total_measurement: f64,
+    age: u32,
+    is_male: bool,
+) -> Vec<(&'static str, f64)> {
+    let per_mm = body_fat_sensitivity(formula, total_measurement, age, is_male).abs();
+    let mut report: Vec<_> = formula
+        .sites(is_male)
+        .iter()
+        .map(|site| (*site, per_mm * typical_site_error(site)))
+        .collect();
+    report.sort_by(|a, b| b.1.total_cmp(&a.1));
+    report
+}
+
+/// Simulations run for the uncertainty estimate shown in the UI.
+pub const MONTE_CARLO_RUNS: usize = 5000;
+
+/// Small SplitMix64 generator; good enough for simulation and keeps runs
+/// reproducible from a seed in tests.
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in (0, 1].
+    pub fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal sample via the Box-Muller transform.
+    pub fn next_gaussian(&mut self) -> f64 {
+        let u1 = self.next_f64();
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UncertaintySummary {
+    pub median: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Perturb each reading by its typical measurement error and report the
+/// median and 90% interval of the resulting body fat percentages.
+pub fn simulate_body_fat_uncertainty(
+    formula: Formula,
+    measurements: &Measurements,
+    age: u32,
+    is_male: bool,
+    runs: usize,
+    seed: u64,
+) -> UncertaintySummary {
+    let mut rng = SplitMix64(seed);
+    let sites = formula.sites(is_male);
+
+    let mut results: Vec<f64> = (0..runs.max(1))
+        .map(|_| {
+            let total: f64 = sites
+                .iter()
+                .map(|site| {
+                    let reading = measurements.get(site);
+                    (reading + rng.next_gaussian() * typical_site_error(site)).max(0.0)
+                })
+                .sum();
+            formula.body_fat(total, age, is_male)
+        })
+        .collect();
+    results.sort_by(f64::total_cmp);
+
+    let percentile = |p: f64| results[((results.len() - 1) as f64 * p).round() as usize];
+    UncertaintySummary {
+        median: percentile(0.5),
+        lower: percentile(0.05),
+        upper: percentile(0.95),
+    }
+}
+
+/// One standard deviation of the result from typical reading errors at
+/// every site, combined in quadrature.
+pub fn reading_uncertainty(
+    formula: Formula,
+    total_measurement: f64,
+    age: u32,
+    is_male: bool,
+) -> f64 {
+    sensitivity_report(formula, total_measurement, age, is_male)
+        .iter()
+        .map(|(_, impact)| impact * impact)
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formulas::calculate_body_fat;
+
+    #[test]
+    fn test_sensitivity_report_ranks_by_typical_error() {
+        let report = sensitivity_report(Formula::JacksonPollock7, 100.0, 30, true);
+        assert_eq!(report.len(), 7);
+        assert_eq!(report[0].0, "abdominal");
+        assert_eq!(report[6].0, "triceps");
+        assert!(report.iter().all(|(_, impact)| *impact > 0.0));
+    }
+
+    #[test]
+    fn test_uncertainty_interval_brackets_point_estimate() {
+        let mut measurements = Measurements::new();
+        for site in [
+            "chest",
+            "abdominal",
+            "thigh",
+            "triceps",
+            "subscapular",
+            "suprailiac",
+            "midaxillary",
+        ] {
+            measurements.set_measurement(site, 15.0);
+        }
+        let formula = Formula::JacksonPollock7;
+        let point = calculate_body_fat(measurements.total(), 30, true);
+        let summary = simulate_body_fat_uncertainty(formula, &measurements, 30, true, 2000, 42);
+        assert!(summary.lower < point && point < summary.upper);
+        assert!((summary.median - point).abs() < 0.5);
+        assert_eq!(
+            summary,
+            simulate_body_fat_uncertainty(formula, &measurements, 30, true, 2000, 42)
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Conversions for values users may enter in imperial units. Everything is
+//! stored and calculated in metric; skinfolds are always millimetres.
+
+pub const CM_PER_INCH: f64 = 2.54;
+pub const KG_PER_POUND: f64 = 0.453_592_37;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitSystem {
+    Metric,
+    Imperial,
+}
+
+impl UnitSystem {
+    pub fn from_label(label: &str) -> Option<UnitSystem> {
+        match label {
+            "Metric" => Some(UnitSystem::Metric),
+            "Imperial" => Some(UnitSystem::Imperial),
+            _ => None,
+        }
+    }
+
+    pub fn length_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "cm",
+            UnitSystem::Imperial => "in",
+        }
+    }
+
+    pub fn weight_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "kg",
+            UnitSystem::Imperial => "lb",
+        }
+    }
+}
+
+pub fn length_to_cm(value: f64, system: UnitSystem) -> f64 {
+    match system {
+        UnitSystem::Metric => value,
+        UnitSystem::Imperial => value * CM_PER_INCH,
+    }
+}
+
+pub fn weight_to_kg(value: f64, system: UnitSystem) -> f64 {
+    match system {
+        UnitSystem::Metric => value,
+        UnitSystem::Imperial => value * KG_PER_POUND,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imperial_conversions() {
+        assert_eq!(length_to_cm(70.0, UnitSystem::Imperial), 177.8);
+        assert_eq!(length_to_cm(180.0, UnitSystem::Metric), 180.0);
+        assert!((weight_to_kg(180.0, UnitSystem::Imperial) - 81.646_626_6).abs() < 1e-6);
+        assert_eq!(
+            UnitSystem::from_label("Imperial"),
+            Some(UnitSystem::Imperial)
+        );
+        assert_eq!(UnitSystem::Imperial.length_unit(), "in");
+    }
+}
@@ -0,0 +1,143 @@
+#ifndef BODY_FAT_H
+#define BODY_FAT_H
+
+/* Generated by cbindgen from src/lib.rs; edit that file instead. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Bits for sites that could not be measured, combined with `|` and passed
+ * to `bf_select_formula`.
+ */
+#define BF_SITE_CHEST (1 << 0)
+
+#define BF_SITE_ABDOMINAL (1 << 1)
+
+#define BF_SITE_THIGH (1 << 2)
+
+#define BF_SITE_TRICEPS (1 << 3)
+
+#define BF_SITE_SUBSCAPULAR (1 << 4)
+
+#define BF_SITE_SUPRAILIAC (1 << 5)
+
+#define BF_SITE_MIDAXILLARY (1 << 6)
+
+#define BF_SITE_BICEPS (1 << 7)
+
+/**
+ * Skinfold equations, as in `body_fat_core::Formula`.
+ */
+typedef enum BfFormula {
+  BF_FORMULA_JACKSON_POLLOCK7,
+  BF_FORMULA_JACKSON_POLLOCK3,
+  BF_FORMULA_JACKSON_POLLOCK3_ALT,
+  BF_FORMULA_DURNIN_WOMERSLEY,
+} BfFormula;
+
+/**
+ * Result of a call. Outputs are only written on `BF_STATUS_OK`; the other
+ * statuses mirror `body_fat_core::CalculationError`.
+ */
+typedef enum BfStatus {
+  BF_STATUS_OK,
+  /**
+   * A required pointer argument was null.
+   */
+  BF_STATUS_NULL_POINTER,
+  /**
+   * The protocol isn't valid UTF-8, a formula's label or "Automatic".
+   */
+  BF_STATUS_INVALID_PROTOCOL,
+  /**
+   * A site the formula needs is zero.
+   */
+  BF_STATUS_MISSED_SITE,
+  /**
+   * A reading is outside 1–80 mm, negative, NaN or infinite.
+   */
+  BF_STATUS_OUT_OF_RANGE,
+  /**
+   * Age is outside 1–119.
+   */
+  BF_STATUS_INVALID_AGE,
+  /**
+   * The chosen protocol needs an excluded site.
+   */
+  BF_STATUS_EXCLUDED_SITES,
+  /**
+   * Automatic selection found no formula without the excluded sites.
+   */
+  BF_STATUS_NO_FORMULA,
+} BfStatus;
+
+/**
+ * Skinfold readings in millimetres, zero when not taken.
+ */
+typedef struct BfMeasurements {
+  double chest;
+  double abdominal;
+  double thigh;
+  double triceps;
+  double subscapular;
+  double suprailiac;
+  double midaxillary;
+  double biceps;
+} BfMeasurements;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Body fat percentage from `measurements` with `formula`, written to
+ * `body_fat`. Only the formula's sites are used, and they are checked as the
+ * app checks them; when several are wrong the first problem is returned, in
+ * the order age, out-of-range readings, missed sites.
+ *
+ * # Safety
+ *
+ * `measurements` must point to a valid `BfMeasurements` and `body_fat` to
+ * writable memory for a `double`, or either may be null.
+ */
+BfStatus bf_calculate_body_fat(BfFormula formula,
+                               const BfMeasurements *measurements,
+                               uint32_t age,
+                               bool is_male,
+                               double *body_fat);
+
+/**
+ * Formula for a protocol label ("7-site", "3-site", "Alternate 3-site",
+ * "Durnin-Womersley"), or the best one avoiding `excluded_sites` for
+ * "Automatic". Written to `formula`.
+ *
+ * # Safety
+ *
+ * `protocol` must be a NUL-terminated string and `formula` must point to
+ * writable memory for a `BfFormula`, or either may be null.
+ */
+BfStatus bf_select_formula(const char *protocol,
+                           uint32_t excluded_sites,
+                           bool is_male,
+                           BfFormula *formula);
+
+/**
+ * Reference category for the result, e.g. "Good". The string is static and
+ * must not be freed.
+ */
+const char *bf_classify(uint32_t age, double body_fat, bool is_male);
+
+/**
+ * Display name of the formula, e.g. "Jackson & Pollock 7-site". The string
+ * is static and must not be freed.
+ */
+const char *bf_formula_name(BfFormula formula);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BODY_FAT_H */
@@ -0,0 +1,354 @@
+//! C API over `body_fat_core` for gym software written in C, C#, Swift or
+//! anything else that can call a C library. `include/body_fat.h` is
+//! generated from this file with cbindgen.
+
+use body_fat_core::classification::BELOW_ESSENTIAL;
+use body_fat_core::{
+    CATEGORIES, CalculationError, Formula, Measurements, Subject, classify_body_fat_with_margin,
+    compute,
+};
+use std::ffi::{CStr, c_char};
+
+/// Skinfold equations, as in `body_fat_core::Formula`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BfFormula {
+    JacksonPollock7,
+    JacksonPollock3,
+    JacksonPollock3Alt,
+    DurninWomersley,
+}
+
+impl From<Formula> for BfFormula {
+    fn from(formula: Formula) -> Self {
+        match formula {
+            Formula::JacksonPollock7 => BfFormula::JacksonPollock7,
+            Formula::JacksonPollock3 => BfFormula::JacksonPollock3,
+            Formula::JacksonPollock3Alt => BfFormula::JacksonPollock3Alt,
+            Formula::DurninWomersley => BfFormula::DurninWomersley,
+        }
+    }
+}
+
+impl From<BfFormula> for Formula {
+    fn from(formula: BfFormula) -> Self {
+        match formula {
+            BfFormula::JacksonPollock7 => Formula::JacksonPollock7,
+            BfFormula::JacksonPollock3 => Formula::JacksonPollock3,
+            BfFormula::JacksonPollock3Alt => Formula::JacksonPollock3Alt,
+            BfFormula::DurninWomersley => Formula::DurninWomersley,
+        }
+    }
+}
+
+/// Result of a call. Outputs are only written on `BF_STATUS_OK`; the other
+/// statuses mirror `body_fat_core::CalculationError`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BfStatus {
+    Ok,
+    /// A required pointer argument was null.
+    NullPointer,
+    /// The protocol isn't valid UTF-8, a formula's label or "Automatic".
+    InvalidProtocol,
+    /// A site the formula needs is zero.
+    MissedSite,
+    /// A reading is outside 1–80 mm, negative, NaN or infinite.
+    OutOfRange,
+    /// Age is outside 1–119.
+    InvalidAge,
+    /// The chosen protocol needs an excluded site.
+    ExcludedSites,
+    /// Automatic selection found no formula without the excluded sites.
+    NoFormula,
+}
+
+impl From<&CalculationError> for BfStatus {
+    fn from(error: &CalculationError) -> Self {
+        match error {
+            // Readings arrive as doubles, so none can fail to parse
+            CalculationError::MissedSite(_) | CalculationError::InvalidNumber(_) => {
+                BfStatus::MissedSite
+            }
+            CalculationError::OutOfRange { .. } => BfStatus::OutOfRange,
+            CalculationError::InvalidAge | CalculationError::NonNumericAge => BfStatus::InvalidAge,
+            // Tape measurements aren't part of the C API
+            CalculationError::InvalidCircumference { .. }
+            | CalculationError::WaistNotAboveNeck { .. } => BfStatus::OutOfRange,
+            CalculationError::ExcludedSites { .. } => BfStatus::ExcludedSites,
+            CalculationError::NoFormula { .. } => BfStatus::NoFormula,
+            CalculationError::UnknownProtocol(_) => BfStatus::InvalidProtocol,
+        }
+    }
+}
+
+/// Skinfold readings in millimetres, zero when not taken.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BfMeasurements {
+    pub chest: f64,
+    pub abdominal: f64,
+    pub thigh: f64,
+    pub triceps: f64,
+    pub subscapular: f64,
+    pub suprailiac: f64,
+    pub midaxillary: f64,
+    pub biceps: f64,
+}
+
+impl From<&BfMeasurements> for Measurements {
+    fn from(m: &BfMeasurements) -> Self {
+        Measurements {
+            chest: m.chest,
+            abdominal: m.abdominal,
+            thigh: m.thigh,
+            triceps: m.triceps,
+            subscapular: m.subscapular,
+            suprailiac: m.suprailiac,
+            midaxillary: m.midaxillary,
+            biceps: m.biceps,
+        }
+    }
+}
+
+/// Bits for sites that could not be measured, combined with `|` and passed
+/// to `bf_select_formula`.
+pub const BF_SITE_CHEST: u32 = 1 << 0;
+pub const BF_SITE_ABDOMINAL: u32 = 1 << 1;
+pub const BF_SITE_THIGH: u32 = 1 << 2;
+pub const BF_SITE_TRICEPS: u32 = 1 << 3;
+pub const BF_SITE_SUBSCAPULAR: u32 = 1 << 4;
+pub const BF_SITE_SUPRAILIAC: u32 = 1 << 5;
+pub const BF_SITE_MIDAXILLARY: u32 = 1 << 6;
+pub const BF_SITE_BICEPS: u32 = 1 << 7;
+
+/// Site keys in bit order.
+const SITE_BITS: [(u32, &str); 8] = [
+    (BF_SITE_CHEST, "chest"),
+    (BF_SITE_ABDOMINAL, "abdominal"),
+    (BF_SITE_THIGH, "thigh"),
+    (BF_SITE_TRICEPS, "triceps"),
+    (BF_SITE_SUBSCAPULAR, "subscapular"),
+    (BF_SITE_SUPRAILIAC, "suprailiac"),
+    (BF_SITE_MIDAXILLARY, "midaxillary"),
+    (BF_SITE_BICEPS, "biceps"),
+];
+
+/// Body fat percentage from `measurements` with `formula`, written to
+/// `body_fat`. Only the formula's sites are used, and they are checked as the
+/// app checks them; when several are wrong the first problem is returned, in
+/// the order age, out-of-range readings, missed sites.
+///
+/// # Safety
+///
+/// `measurements` must point to a valid `BfMeasurements` and `body_fat` to
+/// writable memory for a `double`, or either may be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bf_calculate_body_fat(
+    formula: BfFormula,
+    measurements: *const BfMeasurements,
+    age: u32,
+    is_male: bool,
+    body_fat: *mut f64,
+) -> BfStatus {
+    // SAFETY: the caller guarantees both pointers are valid when not null
+    let (Some(measurements), Some(body_fat)) = (unsafe { measurements.as_ref() }, unsafe {
+        body_fat.as_mut()
+    }) else {
+        return BfStatus::NullPointer;
+    };
+    let subject = Subject {
+        age,
+        is_male,
+        protocol: Formula::from(formula).protocol_label(),
+        excluded: &[],
+        ranges: &[],
+    };
+    match compute(&Measurements::from(measurements), &subject) {
+        Ok(composition) => {
+            *body_fat = composition.body_fat;
+            BfStatus::Ok
+        }
+        Err(errors) => errors.first().map_or(BfStatus::NoFormula, BfStatus::from),
+    }
+}
+
+/// Formula for a protocol label ("7-site", "3-site", "Alternate 3-site",
+/// "Durnin-Womersley"), or the best one avoiding `excluded_sites` for
+/// "Automatic". Written to `formula`.
+///
+/// # Safety
+///
+/// `protocol` must be a NUL-terminated string and `formula` must point to
+/// writable memory for a `BfFormula`, or either may be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bf_select_formula(
+    protocol: *const c_char,
+    excluded_sites: u32,
+    is_male: bool,
+    formula: *mut BfFormula,
+) -> BfStatus {
+    if protocol.is_null() {
+        return BfStatus::NullPointer;
+    }
+    // SAFETY: the caller guarantees a non-null protocol is NUL-terminated
+    let Ok(protocol) = unsafe { CStr::from_ptr(protocol) }.to_str() else {
+        return BfStatus::InvalidProtocol;
+    };
+    // SAFETY: the caller guarantees a non-null formula is writable
+    let Some(formula) = (unsafe { formula.as_mut() }) else {
+        return BfStatus::NullPointer;
+    };
+    let excluded: Vec<&str> = SITE_BITS
+        .iter()
+        .filter(|(bit, _)| excluded_sites & bit != 0)
+        .map(|(_, site)| *site)
+        .collect();
+    match body_fat_core::select_formula(protocol, &excluded, is_male) {
+        Ok(selected) => {
+            *formula = selected.into();
+            BfStatus::Ok
+        }
+        Err(e) => BfStatus::from(&e),
+    }
+}
+
+/// Static C string for each category the classification can return.
+fn category_c_str(category: &str) -> &'static CStr {
+    match category {
+        _ if category == CATEGORIES[0] => c"Excellent",
+        _ if category == CATEGORIES[1] => c"Good",
+        _ if category == CATEGORIES[2] => c"Average",
+        _ if category == CATEGORIES[3] => c"Below Average",
+        _ if category == CATEGORIES[4] => c"Poor",
+        _ if category == BELOW_ESSENTIAL => c"Extremely Lean (Below Essential Fat)",
+        _ => c"Unclassified",
+    }
+}
+
+/// Reference category for the result, e.g. "Good". The string is static and
+/// must not be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn bf_classify(age: u32, body_fat: f64, is_male: bool) -> *const c_char {
+    category_c_str(classify_body_fat_with_margin(age, body_fat, is_male, 0.0).category).as_ptr()
+}
+
+/// Display name of the formula, e.g. "Jackson & Pollock 7-site". The string
+/// is static and must not be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn bf_formula_name(formula: BfFormula) -> *const c_char {
+    match formula {
+        BfFormula::JacksonPollock7 => c"Jackson & Pollock 7-site",
+        BfFormula::JacksonPollock3 => c"Jackson & Pollock 3-site",
+        BfFormula::JacksonPollock3Alt => c"Jackson & Pollock alternate 3-site",
+        BfFormula::DurninWomersley => c"Durnin & Womersley 4-site",
+    }
+    .as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn test_c_api_matches_core() {
+        let measurements = BfMeasurements {
+            chest: 10.0,
+            abdominal: 20.0,
+            thigh: 15.0,
+            ..BfMeasurements::default()
+        };
+        let mut body_fat = 0.0;
+        let status = unsafe {
+            bf_calculate_body_fat(
+                BfFormula::JacksonPollock3,
+                &measurements,
+                30,
+                true,
+                &mut body_fat,
+            )
+        };
+        assert_eq!(status, BfStatus::Ok);
+        assert_eq!(
+            body_fat,
+            body_fat_core::calculate_body_fat_3_site(45.0, 30, true)
+        );
+        let status = unsafe {
+            bf_calculate_body_fat(
+                BfFormula::JacksonPollock7,
+                ptr::null(),
+                30,
+                true,
+                &mut body_fat,
+            )
+        };
+        assert_eq!(status, BfStatus::NullPointer);
+        let calculate = |measurements: &BfMeasurements, age| {
+            let mut body_fat = -1.0;
+            let status = unsafe {
+                bf_calculate_body_fat(
+                    BfFormula::JacksonPollock3,
+                    measurements,
+                    age,
+                    true,
+                    &mut body_fat,
+                )
+            };
+            (status, body_fat)
+        };
+        assert_eq!(calculate(&measurements, 0), (BfStatus::InvalidAge, -1.0));
+        let thigh = |thigh| BfMeasurements {
+            thigh,
+            ..measurements
+        };
+        assert_eq!(calculate(&thigh(0.0), 30).0, BfStatus::MissedSite);
+        assert_eq!(calculate(&thigh(-15.0), 30).0, BfStatus::OutOfRange);
+        assert_eq!(calculate(&thigh(f64::NAN), 30).0, BfStatus::OutOfRange);
+
+        // Empty Durnin & Womersley sites are missed rather than summing to a
+        // log10 of zero
+        let status = unsafe {
+            bf_calculate_body_fat(
+                BfFormula::DurninWomersley,
+                &BfMeasurements::default(),
+                30,
+                true,
+                &mut body_fat,
+            )
+        };
+        assert_eq!(status, BfStatus::MissedSite);
+
+        let mut formula = BfFormula::JacksonPollock7;
+        let status = unsafe {
+            bf_select_formula(
+                c"Automatic".as_ptr(),
+                BF_SITE_MIDAXILLARY,
+                true,
+                &mut formula,
+            )
+        };
+        assert_eq!(status, BfStatus::Ok);
+        assert_eq!(
+            Formula::from(formula),
+            Formula::best_available(&["midaxillary"], true).unwrap()
+        );
+        let status =
+            unsafe { bf_select_formula(c"7-site".as_ptr(), BF_SITE_CHEST, true, &mut formula) };
+        assert_eq!(status, BfStatus::ExcludedSites);
+        let status = unsafe { bf_select_formula(c"7 site".as_ptr(), 0, true, &mut formula) };
+        assert_eq!(status, BfStatus::InvalidProtocol);
+        let status = unsafe { bf_select_formula(c"Automatic".as_ptr(), 0xff, true, &mut formula) };
+        assert_eq!(status, BfStatus::NoFormula);
+
+        let category = unsafe { CStr::from_ptr(bf_classify(25, 15.0, true)) };
+        assert_eq!(category.to_str().unwrap(), "Good");
+        for formula in Formula::ALL {
+            let name = unsafe { CStr::from_ptr(bf_formula_name(formula.into())) };
+            assert_eq!(name.to_str().unwrap(), formula.name());
+        }
+        for category in CATEGORIES.iter().chain([&BELOW_ESSENTIAL, &"Unclassified"]) {
+            assert_eq!(category_c_str(category).to_str().unwrap(), *category);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Python bindings for `body_fat_core`, so cohort data can be processed in
+//! pandas with the same equations as the app. Formulas are named by their
+//! protocol labels ("7-site", "3-site", "Alternate 3-site",
+//! "Durnin-Womersley") and sites by their keys ("chest", "thigh", ...).
+//!
+//! The functions here are plain Rust; the `python` feature wraps them in the
+//! `body_fat_py` module, raising `ValueError` where these return `Err`.
+
+use body_fat_core::{ALL_SITES, Formula, Measurements, classify_body_fat_with_margin};
+use std::collections::HashMap;
+
+/// Protocol labels accepted wherever a formula is asked for.
+pub const FORMULAS: [&str; 4] = ["7-site", "3-site", "Alternate 3-site", "Durnin-Womersley"];
+
+fn formula(label: &str) -> Result<Formula, String> {
+    Formula::from_protocol_label(label).ok_or_else(|| {
+        format!(
+            "Unknown formula {:?}; expected one of {}",
+            label,
+            FORMULAS.join(", ")
+        )
+    })
+}
+
+/// Whether `millimetres` can be a skinfold reading or sum. Zero and
+/// negative values would otherwise reach `log10` in Durnin & Womersley.
+fn is_skinfold(millimetres: f64) -> bool {
+    millimetres.is_finite() && millimetres > 0.0
+}
+
+/// Body fat percentage from skinfolds in millimetres keyed by site. Every
+/// site the formula uses must be present and positive; other sites are
+/// ignored.
+pub fn body_fat(
+    formula_label: &str,
+    skinfolds: &HashMap<String, f64>,
+    age: u32,
+    is_male: bool,
+) -> Result<f64, String> {
+    let formula = formula(formula_label)?;
+    if let Some(site) = skinfolds
+        .keys()
+        .find(|site| !ALL_SITES.contains(&site.as_str()))
+    {
+        return Err(format!("Unknown site {:?}", site));
+    }
+    let missing: Vec<&str> = formula
+        .sites(is_male)
+        .iter()
+        .copied()
+        .filter(|site| !skinfolds.contains_key(*site))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("{} needs {}", formula.name(), missing.join(", ")));
+    }
+    if let Some(site) = formula
+        .sites(is_male)
+        .iter()
+        .find(|site| !is_skinfold(skinfolds[**site]))
+    {
+        return Err(format!(
+            "{} must be a positive number of millimetres, not {}",
+            site, skinfolds[*site]
+        ));
+    }
+    let mut measurements = Measurements::new();
+    for (site, value) in skinfolds {
+        measurements.set_measurement(site, *value);
+    }
+    Ok(formula.body_fat(formula.sum(&measurements, is_male), age, is_male))
+}
+
+/// Body fat for columns of skinfold sums, ages and sexes of equal length,
+/// e.g. straight from a DataFrame. Every sum must be positive.
+pub fn body_fat_from_sums(
+    formula_label: &str,
+    sums: &[f64],
+    ages: &[u32],
+    is_male: &[bool],
+) -> Result<Vec<f64>, String> {
+    let formula = formula(formula_label)?;
+    if ages.len() != sums.len() || is_male.len() != sums.len() {
+        return Err(format!(
+            "Columns differ in length: {} sums, {} ages, {} sexes",
+            sums.len(),
+            ages.len(),
+            is_male.len()
+        ));
+    }
+    if let Some((row, sum)) = sums.iter().enumerate().find(|(_, sum)| !is_skinfold(**sum)) {
+        return Err(format!(
+            "Sum in row {} must be a positive number of millimetres, not {}",
+            row, sum
+        ));
+    }
+    Ok(sums
+        .iter()
+        .zip(ages)
+        .zip(is_male)
+        .map(|((sum, age), is_male)| formula.body_fat(*sum, *age, *is_male))
+        .collect())
+}
+
+/// Formula for `protocol`, or the best one avoiding `excluded` sites when
+/// it is "Automatic", as a protocol label.
+pub fn select_formula(
+    protocol: &str,
+    excluded: &[String],
+    is_male: bool,
+) -> Result<&'static str, String> {
+    let excluded: Vec<&str> = excluded.iter().map(String::as_str).collect();
+    body_fat_core::select_formula(protocol, &excluded, is_male)
+        .map(Formula::protocol_label)
+        .map_err(|e| e.to_string())
+}
+
+/// Reference category for a result, e.g. "Good".
+pub fn classify(age: u32, body_fat: f64, is_male: bool) -> &'static str {
+    classify_body_fat_with_margin(age, body_fat, is_male, 0.0).category
+}
+
+/// Categories for columns of ages, results and sexes of equal length.
+pub fn classify_many(
+    ages: &[u32],
+    body_fat: &[f64],
+    is_male: &[bool],
+) -> Result<Vec<&'static str>, String> {
+    if body_fat.len() != ages.len() || is_male.len() != ages.len() {
+        return Err(format!(
+            "Columns differ in length: {} ages, {} results, {} sexes",
+            ages.len(),
+            body_fat.len(),
+            is_male.len()
+        ));
+    }
+    Ok(ages
+        .iter()
+        .zip(body_fat)
+        .zip(is_male)
+        .map(|((age, body_fat), is_male)| classify(*age, *body_fat, *is_male))
+        .collect())
+}
+
+#[cfg(feature = "python")]
+mod python {
+    use pyo3::exceptions::PyValueError;
+    use pyo3::prelude::*;
+    use std::collections::HashMap;
+
+    #[pyfunction]
+    fn body_fat(
+        formula: &str,
+        skinfolds: HashMap<String, f64>,
+        age: u32,
+        is_male: bool,
+    ) -> PyResult<f64> {
+        super::body_fat(formula, &skinfolds, age, is_male).map_err(PyValueError::new_err)
+    }
+
+    #[pyfunction]
+    fn body_fat_from_sums(
+        formula: &str,
+        sums: Vec<f64>,
+        ages: Vec<u32>,
+        is_male: Vec<bool>,
+    ) -> PyResult<Vec<f64>> {
+        super::body_fat_from_sums(formula, &sums, &ages, &is_male).map_err(PyValueError::new_err)
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (protocol, excluded = Vec::new(), is_male = true))]
+    fn select_formula(
+        protocol: &str,
+        excluded: Vec<String>,
+        is_male: bool,
+    ) -> PyResult<&'static str> {
+        super::select_formula(protocol, &excluded, is_male).map_err(PyValueError::new_err)
+    }
+
+    #[pyfunction]
+    fn classify(age: u32, body_fat: f64, is_male: bool) -> &'static str {
+        super::classify(age, body_fat, is_male)
+    }
+
+    #[pyfunction]
+    fn classify_many(
+        ages: Vec<u32>,
+        body_fat: Vec<f64>,
+        is_male: Vec<bool>,
+    ) -> PyResult<Vec<&'static str>> {
+        super::classify_many(&ages, &body_fat, &is_male).map_err(PyValueError::new_err)
+    }
+
+    #[pymodule]
+    fn body_fat_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+        m.add("FORMULAS", super::FORMULAS.to_vec())?;
+        m.add("SITES", body_fat_core::ALL_SITES.to_vec())?;
+        m.add_function(wrap_pyfunction!(body_fat, m)?)?;
+        m.add_function(wrap_pyfunction!(body_fat_from_sums, m)?)?;
+        m.add_function(wrap_pyfunction!(select_formula, m)?)?;
+        m.add_function(wrap_pyfunction!(classify, m)?)?;
+        m.add_function(wrap_pyfunction!(classify_many, m)?)?;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use pyo3::types::PyDict;
+
+        #[test]
+        fn test_module_init_exposes_the_api() {
+            Python::initialize();
+            Python::attach(|py| {
+                let module = PyModule::new(py, "body_fat_py").unwrap();
+                body_fat_py(&module).unwrap();
+
+                let formulas: Vec<String> = module.getattr("FORMULAS").unwrap().extract().unwrap();
+                assert_eq!(formulas, super::super::FORMULAS);
+
+                let skinfolds = PyDict::new(py);
+                for (site, value) in [("chest", 10.0), ("abdominal", 20.0), ("thigh", 15.0)] {
+                    skinfolds.set_item(site, value).unwrap();
+                }
+                let body_fat: f64 = module
+                    .getattr("body_fat")
+                    .unwrap()
+                    .call1(("3-site", skinfolds, 30, true))
+                    .unwrap()
+                    .extract()
+                    .unwrap();
+                assert_eq!(
+                    body_fat,
+                    body_fat_core::calculate_body_fat_3_site(45.0, 30, true)
+                );
+
+                let error = module
+                    .getattr("select_formula")
+                    .unwrap()
+                    .call1(("3 site",))
+                    .unwrap_err();
+                assert!(error.is_instance_of::<PyValueError>(py));
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings_match_core() {
+        let skinfolds: HashMap<String, f64> =
+            [("chest", 10.0), ("abdominal", 20.0), ("thigh", 15.0)]
+                .into_iter()
+                .map(|(site, value)| (site.to_string(), value))
+                .collect();
+        let expected = body_fat_core::calculate_body_fat_3_site(45.0, 30, true);
+        assert_eq!(body_fat("3-site", &skinfolds, 30, true), Ok(expected));
+        assert!(
+            body_fat("7-site", &skinfolds, 30, true)
+                .unwrap_err()
+                .contains("triceps")
+        );
+        assert!(body_fat("4-site", &skinfolds, 30, true).is_err());
+
+        assert_eq!(
+            body_fat_from_sums("3-site", &[45.0, 45.0], &[30, 30], &[true, true]),
+            Ok(vec![expected, expected])
+        );
+        assert!(body_fat_from_sums("3-site", &[45.0], &[30, 40], &[true]).is_err());
+
+        // Zero or negative skinfolds are refused rather than reaching log10
+        let four_site: HashMap<String, f64> = ["biceps", "triceps", "subscapular", "suprailiac"]
+            .into_iter()
+            .map(|site| (site.to_string(), 0.0))
+            .collect();
+        assert!(
+            body_fat("Durnin-Womersley", &four_site, 30, true)
+                .unwrap_err()
+                .contains("positive")
+        );
+        for sum in [0.0, -12.0, f64::NAN] {
+            assert!(
+                body_fat_from_sums("Durnin-Womersley", &[40.0, sum], &[30, 30], &[true, true])
+                    .unwrap_err()
+                    .contains("row 1")
+            );
+        }
+
+        assert_eq!(
+            select_formula("Automatic", &["midaxillary".to_string()], true),
+            Ok("3-site")
+        );
+        assert!(select_formula("7-site", &["chest".to_string()], true).is_err());
+        assert_eq!(classify(25, 15.0, true), "Good");
+        assert_eq!(
+            classify_many(&[25, 25], &[15.0, 40.0], &[true, false]),
+            Ok(vec!["Good", "Poor"])
+        );
+        assert!(classify_many(&[25], &[], &[true]).is_err());
+        assert!(FORMULAS.iter().all(|label| formula(label).is_ok()));
+    }
+}
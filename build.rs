@@ -1,3 +1,7 @@
 fn main() {
-    slint_build::compile("ui/main.slint").unwrap();
+    // Translations in lang/<language>/LC_MESSAGES are compiled into the binary
+    // and picked from the system locale at startup; English is the source text.
+    let config = slint_build::CompilerConfiguration::new()
+        .with_bundled_translations(concat!(env!("CARGO_MANIFEST_DIR"), "/lang"));
+    slint_build::compile_with_config("ui/main.slint", config).unwrap();
 }
@@ -0,0 +1,82 @@
+use crate::streak;
+use chrono::{DateTime, Local, NaiveDate};
+
+/// A milestone a profile can reach. Badges are kept once earned, even if the
+/// sessions behind them are later deleted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Badge {
+    /// Key stored in the database.
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+}
+
+pub const BADGES: [Badge; 3] = [
+    Badge {
+        id: "ten-sessions",
+        title: "Ten sessions",
+        description: "Saved 10 sessions",
+    },
+    Badge {
+        id: "first-point-lost",
+        title: "First point down",
+        description: "Body fat 1 percentage point below the first session",
+    },
+    Badge {
+        id: "six-month-streak",
+        title: "Six-month streak",
+        description: "Measured every week for 26 weeks in a row",
+    },
+];
+
+/// Weeks in a row for the six-month streak badge.
+const SIX_MONTHS_IN_WEEKS: u32 = 26;
+
+pub fn badge(id: &str) -> Option<&'static Badge> {
+    BADGES.iter().find(|badge| badge.id == id)
+}
+
+/// Ids of every badge the profile's sessions, oldest first, qualify for.
+pub fn earned(series: &[(DateTime<Local>, f64)]) -> Vec<&'static str> {
+    let mut earned = Vec::new();
+    if series.len() >= 10 {
+        earned.push("ten-sessions");
+    }
+    if let Some((_, first)) = series.first()
+        && series.iter().any(|(_, body_fat)| *body_fat <= first - 1.0)
+    {
+        earned.push("first-point-lost");
+    }
+    let dates: Vec<NaiveDate> = series.iter().map(|(at, _)| at.date_naive()).collect();
+    // The longest run doesn't depend on today, only on the sessions
+    let today = dates.last().copied().unwrap_or_default();
+    if streak::streaks(&dates, today).longest >= SIX_MONTHS_IN_WEEKS {
+        earned.push("six-month-streak");
+    }
+    earned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_badges_earned_from_sessions() {
+        let start = Local.with_ymd_and_hms(2025, 1, 6, 8, 0, 0).unwrap();
+        let weekly = |weeks: i64, loss: f64| -> Vec<(DateTime<Local>, f64)> {
+            (0..weeks)
+                .map(|week| (start + Duration::weeks(week), 20.0 - loss * week as f64))
+                .collect()
+        };
+        assert!(earned(&weekly(3, 0.0)).is_empty());
+        assert_eq!(earned(&weekly(3, 0.5)), ["first-point-lost"]);
+        assert_eq!(earned(&weekly(10, 0.0)), ["ten-sessions"]);
+        assert_eq!(
+            earned(&weekly(26, 0.0)),
+            ["ten-sessions", "six-month-streak"]
+        );
+        assert_eq!(badge("ten-sessions").unwrap().title, "Ten sessions");
+        assert!(BADGES.iter().all(|entry| badge(entry.id).is_some()));
+    }
+}
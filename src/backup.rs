@@ -0,0 +1,226 @@
+use crate::goal::Goal;
+use crate::profile::Profile;
+use crate::storage::{Session, Storage};
+use body_fat_core::Measurements;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Format written by this version; newer backups are refused rather than
+/// half-read.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Start of backup file names, e.g. `body-fat-calculator-backup.json`.
+pub const FILE_PREFIX: &str = "body-fat-calculator-backup";
+
+/// Everything the app keeps, as one portable JSON document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub format_version: u32,
+    /// Skinfold readings in the form when the backup was made.
+    pub measurements: Measurements,
+    pub profiles: Vec<Profile>,
+    /// Goals by profile id.
+    #[serde(default)]
+    pub goals: Vec<(i64, Goal)>,
+    /// Badge ids with when they were earned, by profile id. Backups from
+    /// before badges restore without them; they are earned again on the
+    /// next save.
+    #[serde(default)]
+    pub badges: Vec<(i64, String, DateTime<Local>)>,
+    pub sessions: Vec<Session>,
+}
+
+impl Backup {
+    pub fn from_storage(storage: &Storage, measurements: Measurements) -> rusqlite::Result<Self> {
+        Ok(Backup {
+            format_version: FORMAT_VERSION,
+            measurements,
+            profiles: storage.profiles()?,
+            goals: storage.goals()?,
+            badges: storage.all_badges()?,
+            sessions: storage.all_sessions()?,
+        })
+    }
+
+    /// Whatever can still be read from a damaged database: each table that
+    /// reads back whole and every session that does on its own, with how
+    /// many listed sessions could not be read.
+    pub fn salvage(storage: &Storage) -> (Self, usize) {
+        let ids = storage.session_ids().unwrap_or_default();
+        let sessions: Vec<Session> = ids
+            .iter()
+            .filter_map(|id| storage.session(*id).ok().flatten())
+            .collect();
+        let lost = ids.len() - sessions.len();
+        let backup = Backup {
+            format_version: FORMAT_VERSION,
+            measurements: Measurements::new(),
+            profiles: storage.profiles().unwrap_or_default(),
+            goals: storage.goals().unwrap_or_default(),
+            badges: storage.all_badges().unwrap_or_default(),
+            sessions,
+        };
+        (backup, lost)
+    }
+
+    /// Replace the stored profiles, goals, badges and sessions with the
+    /// backup's.
+    pub fn restore(&self, storage: &mut Storage) -> rusqlite::Result<()> {
+        storage.replace_all(&self.profiles, &self.goals, &self.badges, &self.sessions)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<Backup, String> {
+        let backup: Backup =
+            serde_json::from_str(text).map_err(|e| format!("Not a valid backup: {}", e))?;
+        if backup.format_version > FORMAT_VERSION {
+            return Err(format!(
+                "The backup was made by a newer version of the app (format {})",
+                backup.format_version
+            ));
+        }
+        Ok(backup)
+    }
+}
+
+/// The most recently saved backup in `dir` that still opens, with its path.
+pub fn newest_good(dir: &Path) -> Option<(PathBuf, Backup)> {
+    let mut candidates: Vec<_> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with(FILE_PREFIX) && name.ends_with(".json")
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    candidates.sort();
+    candidates.into_iter().rev().find_map(|(_, path)| {
+        let backup = Backup::from_json(&std::fs::read_to_string(&path).ok()?).ok()?;
+        Some((path, backup))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, NaiveDate, TimeZone};
+
+    #[test]
+    fn test_backup_round_trip() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        let id = storage
+            .save_profile(&Profile {
+                id: None,
+                name: "Alex".to_string(),
+                birth_date: NaiveDate::from_ymd_opt(1991, 4, 2).unwrap(),
+                is_male: true,
+                preferred_formula: "3-site".to_string(),
+            })
+            .unwrap();
+        let goal = Goal {
+            body_fat: 15.0,
+            target_date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+        };
+        storage.save_goal(id, &goal).unwrap();
+        let earned_at = Local.with_ymd_and_hms(2025, 3, 1, 9, 30, 0).unwrap();
+        storage
+            .award_badges(id, &["ten-sessions"], earned_at)
+            .unwrap();
+        for (profile_id, day) in [(Some(id), 1), (None, 2)] {
+            storage
+                .save(&Session {
+                    profile_id,
+                    recorded_at: Local.with_ymd_and_hms(2025, 3, day, 9, 30, 0).unwrap(),
+                    formula: "Jackson & Pollock 3-site".to_string(),
+                    age: 34,
+                    is_male: true,
+                    body_fat: 18.2,
+                    readings: vec![("chest".to_string(), 12.0)],
+                    trials: vec![("chest".to_string(), 11.5), ("chest".to_string(), 12.5)],
+                    caliper: None,
+                    tag: None,
+                    excluded_sites: Vec::new(),
+                })
+                .unwrap();
+        }
+        let mut measurements = Measurements::new();
+        measurements.chest = 12.5;
+
+        let json = Backup::from_storage(&storage, measurements)
+            .unwrap()
+            .to_json();
+        let backup = Backup::from_json(&json).unwrap();
+        assert_eq!(backup.measurements.chest, 12.5);
+
+        let mut restored = Storage::open_in_memory().unwrap();
+        restored.save(&storage.all_sessions().unwrap()[1]).unwrap();
+        backup.restore(&mut restored).unwrap();
+        assert_eq!(restored.profiles().unwrap(), storage.profiles().unwrap());
+        assert_eq!(restored.goals().unwrap(), vec![(id, goal)]);
+        assert_eq!(
+            restored.badges(id).unwrap(),
+            vec![("ten-sessions".to_string(), earned_at)]
+        );
+        assert_eq!(
+            restored.all_sessions().unwrap(),
+            storage.all_sessions().unwrap()
+        );
+
+        assert!(
+            Backup::from_json(&json.replace("\"format_version\": 1", "\"format_version\": 2"))
+                .is_err()
+        );
+
+        // Backups made before badges existed still open
+        let mut old: serde_json::Value = serde_json::from_str(&json).unwrap();
+        old.as_object_mut().unwrap().remove("badges");
+        let old = Backup::from_json(&old.to_string()).unwrap();
+        assert!(old.badges.is_empty());
+        old.restore(&mut restored).unwrap();
+        assert_eq!(restored.badges(id).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_newest_good_backup_skips_broken_files() {
+        let storage = Storage::open_in_memory().unwrap();
+        let (salvaged, lost) = Backup::salvage(&storage);
+        assert_eq!((salvaged.sessions.len(), lost), (0, 0));
+        let json = salvaged.to_json();
+
+        let dir = std::env::temp_dir().join(format!("bfc-backups-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(newest_good(&dir).is_none());
+        let now = std::time::SystemTime::now();
+        let minutes_ago = |minutes: u64| now - std::time::Duration::from_secs(minutes * 60);
+        for (name, contents, modified) in [
+            (
+                "body-fat-calculator-backup.json",
+                json.as_str(),
+                minutes_ago(10),
+            ),
+            (
+                "body-fat-calculator-backup (2).json",
+                "{ truncated",
+                minutes_ago(5),
+            ),
+            ("notes.json", json.as_str(), minutes_ago(1)),
+        ] {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        let (path, _) = newest_good(&dir).unwrap();
+        assert_eq!(path, dir.join("body-fat-calculator-backup.json"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,375 @@
+use crate::goal::{self, Goal};
+use chrono::{DateTime, Datelike, Duration, Local};
+
+/// Width and height of the chart's path viewbox.
+pub const VIEWBOX: f64 = 100.0;
+
+/// Date ranges offered above the trend chart, with how far back each reaches.
+pub const RANGES: [(&str, Option<i64>); 4] = [
+    ("Last month", Some(31)),
+    ("Last 3 months", Some(92)),
+    ("Last year", Some(365)),
+    ("All time", None),
+];
+
+/// Start of the named range, or `None` for all time.
+pub fn range_start(range: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    RANGES
+        .iter()
+        .find(|(label, _)| *label == range)
+        .and_then(|(_, days)| *days)
+        .map(|days| now - Duration::days(days))
+}
+
+/// Sessions further apart than this many days are a break in the record.
+pub const GAP_DAYS: i64 = 42;
+
+/// Whether the trend line goes through every session or through averages
+/// over calendar periods, which read better over several years.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Grouping {
+    #[default]
+    Sessions,
+    /// Averages over ISO weeks, Monday to Sunday.
+    Weekly,
+    Monthly,
+}
+
+impl Grouping {
+    /// The grouping for a chart setting label, every session when
+    /// unrecognised.
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "Weekly average" => Grouping::Weekly,
+            "Monthly average" => Grouping::Monthly,
+            _ => Grouping::Sessions,
+        }
+    }
+}
+
+/// How the trend line is drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    /// Lift the line over breaks longer than `GAP_DAYS` instead of joining
+    /// the sessions either side as though measured throughout.
+    pub break_gaps: bool,
+    pub grouping: Grouping,
+}
+
+/// `points` (oldest first) as (time, mean, standard deviation) per period
+/// of `grouping`, each at the mean time of its sessions. Sessions are kept
+/// one by one, with no spread, for `Grouping::Sessions`.
+pub fn group(
+    points: &[(DateTime<Local>, f64)],
+    grouping: Grouping,
+) -> Vec<(DateTime<Local>, f64, f64)> {
+    let period = |at: &DateTime<Local>| match grouping {
+        Grouping::Sessions => None,
+        Grouping::Weekly => Some((at.iso_week().year(), at.iso_week().week())),
+        Grouping::Monthly => Some((at.year(), at.month())),
+    };
+    points
+        .chunk_by(|a, b| period(&a.0).is_some() && period(&a.0) == period(&b.0))
+        .map(|sessions| {
+            let n = sessions.len() as f64;
+            let first = sessions[0].0;
+            let offset = sessions
+                .iter()
+                .map(|(at, _)| (*at - first).num_seconds() as f64)
+                .sum::<f64>()
+                / n;
+            let mean = sessions.iter().map(|(_, bf)| bf).sum::<f64>() / n;
+            // Sample standard deviation, none for a period with one session
+            let spread = if sessions.len() > 1 {
+                (sessions
+                    .iter()
+                    .map(|(_, bf)| (bf - mean).powi(2))
+                    .sum::<f64>()
+                    / (n - 1.0))
+                    .sqrt()
+            } else {
+                0.0
+            };
+            (
+                first + Duration::seconds(offset.round() as i64),
+                mean,
+                spread,
+            )
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrendChart {
+    /// SVG path commands in a `VIEWBOX` square, y growing downwards.
+    pub commands: String,
+    /// Straight lines across the breaks the trend line was lifted over, to
+    /// draw faintly. Empty unless `Style::break_gaps` is set.
+    pub gaps: String,
+    /// A vertical bar a standard deviation either side of each period's
+    /// average. Empty unless `Style::grouping` averages sessions.
+    pub error_bars: String,
+    /// Regression line from the last session to the goal date, empty without
+    /// a goal still ahead.
+    pub projection: String,
+    /// Horizontal line at the goal's body fat, empty without a goal.
+    pub goal: String,
+    /// Body fat percentages at the bottom and top of the chart.
+    pub low: f64,
+    pub high: f64,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+/// Line through `points` (oldest first), scaled by time on x and body fat on
+/// y. Needs at least two points to show a trend.
+pub fn trend_chart(points: &[(DateTime<Local>, f64)]) -> Option<TrendChart> {
+    goal_chart(points, None, Style::default())
+}
+
+/// `trend_chart` drawn in `style`, with the goal marked and, while its date
+/// is still ahead, the regression line projected out to it. The regression
+/// is fitted to the sessions themselves, even when averages are drawn.
+pub fn goal_chart(
+    points: &[(DateTime<Local>, f64)],
+    goal: Option<&Goal>,
+    style: Style,
+) -> Option<TrendChart> {
+    let _span = tracing::info_span!("chart", points = points.len()).entered();
+    let grouped = group(points, style.grouping);
+    let line: Vec<(DateTime<Local>, f64)> =
+        grouped.iter().map(|(at, mean, _)| (*at, *mean)).collect();
+    let (first, last) = (line.first()?, line.last()?);
+    if line.len() < 2 {
+        return None;
+    }
+    let projection = goal
+        .filter(|goal| goal.target_time() > last.0)
+        .and_then(|goal| {
+            let fit = goal::fit(points)?;
+            let value = |at| fit.value_at(at).clamp(0.0, 100.0);
+            Some([
+                (last.0, value(last.0)),
+                (goal.target_time(), value(goal.target_time())),
+            ])
+        });
+    let end = projection.map_or(last.0, |projection| projection[1].0);
+
+    // Pad by a point either side so flat trends don't sit on the border
+    let values = || {
+        grouped
+            .iter()
+            .flat_map(|(_, mean, spread)| [mean - spread, mean + spread])
+            .chain(projection.iter().flatten().map(|(_, bf)| *bf))
+            .chain(goal.map(|goal| goal.body_fat))
+    };
+    let low = values().fold(f64::INFINITY, f64::min) - 1.0;
+    let high = values().fold(f64::NEG_INFINITY, f64::max) + 1.0;
+    let span = (end - first.0).num_seconds().max(1) as f64;
+    let y = |bf: f64| VIEWBOX - (bf - low) / (high - low) * VIEWBOX;
+
+    let x = |at: DateTime<Local>| (at - first.0).num_seconds() as f64 / span * VIEWBOX;
+    let point = |(at, bf): &(DateTime<Local>, f64)| format!("{:.2} {:.2}", x(*at), y(*bf));
+    let path = |points: &[(DateTime<Local>, f64)]| {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, session)| format!("{} {}", if i == 0 { "M" } else { "L" }, point(session)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    // A broken line moves to the session after each break, with the break
+    // itself kept as a separate faint line
+    let mut commands = path(&line[..1]);
+    let mut gaps = Vec::new();
+    for pair in line.windows(2) {
+        let broken = style.break_gaps && pair[1].0 - pair[0].0 > Duration::days(GAP_DAYS);
+        if broken {
+            gaps.push(path(pair));
+        }
+        commands.push_str(&format!(
+            " {} {}",
+            if broken { "M" } else { "L" },
+            point(&pair[1])
+        ));
+    }
+
+    let error_bars = grouped
+        .iter()
+        .filter(|(_, _, spread)| *spread > 0.0)
+        .map(|(at, mean, spread)| {
+            let x = x(*at);
+            format!(
+                "M {:.2} {:.2} L {:.2} {:.2}",
+                x,
+                y(mean + spread),
+                x,
+                y(mean - spread)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Some(TrendChart {
+        commands,
+        gaps: gaps.join(" "),
+        error_bars,
+        projection: projection.map(|p| path(&p)).unwrap_or_default(),
+        goal: goal
+            .map(|goal| {
+                let y = y(goal.body_fat);
+                format!("M 0.00 {:.2} L {:.2} {:.2}", y, VIEWBOX, y)
+            })
+            .unwrap_or_default(),
+        low,
+        high,
+        start: first.0,
+        end,
+    })
+}
+
+/// Small line through `values` spaced evenly across a `VIEWBOX` square,
+/// for spotting a reading that breaks from the ones before it. Empty for
+/// fewer than two values.
+pub fn sparkline(values: &[f64]) -> String {
+    if values.len() < 2 {
+        return String::new();
+    }
+    let low = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let step = VIEWBOX / (values.len() - 1) as f64;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            // A flat run sits across the middle
+            let y = if high > low {
+                VIEWBOX - (value - low) / (high - low) * VIEWBOX
+            } else {
+                VIEWBOX / 2.0
+            };
+            format!(
+                "{} {:.2} {:.2}",
+                if i == 0 { "M" } else { "L" },
+                i as f64 * step,
+                y
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_trend_chart_scales_to_viewbox() {
+        let day = |d| Local.with_ymd_and_hms(2025, 1, d, 8, 0, 0).unwrap();
+        assert_eq!(trend_chart(&[(day(1), 20.0)]), None);
+
+        let chart = trend_chart(&[(day(1), 20.0), (day(3), 18.0), (day(5), 19.0)]).unwrap();
+        assert_eq!(chart.commands, "M 0.00 25.00 L 50.00 75.00 L 100.00 50.00");
+        assert_eq!((chart.low, chart.high), (17.0, 21.0));
+        assert_eq!((chart.start, chart.end), (day(1), day(5)));
+        assert_eq!((chart.projection.as_str(), chart.goal.as_str()), ("", ""));
+    }
+
+    #[test]
+    fn test_goal_chart_projects_to_target_date() {
+        let day = |d| Local.with_ymd_and_hms(2025, 1, d, 12, 0, 0).unwrap();
+        let goal = Goal {
+            body_fat: 16.0,
+            target_date: chrono::NaiveDate::from_ymd_opt(2025, 1, 29).unwrap(),
+        };
+        let chart = goal_chart(
+            &[(day(1), 20.0), (day(15), 18.6)],
+            Some(&goal),
+            Style::default(),
+        )
+        .unwrap();
+        assert_eq!((chart.low, chart.high), (15.0, 21.0));
+        assert_eq!(chart.end, day(29));
+        assert_eq!(chart.commands, "M 0.00 16.67 L 50.00 40.00");
+        assert_eq!(chart.projection, "M 50.00 40.00 L 100.00 63.33");
+        assert_eq!(chart.goal, "M 0.00 83.33 L 100.00 83.33");
+    }
+
+    #[test]
+    fn test_broken_line_lifts_over_long_gaps() {
+        let day = |d| Local.with_ymd_and_hms(2025, 1, 1, 8, 0, 0).unwrap() + Duration::days(d);
+        let points = [(day(0), 20.0), (day(7), 18.0), (day(100), 19.0)];
+
+        let joined = trend_chart(&points).unwrap();
+        assert_eq!(joined.commands, "M 0.00 25.00 L 7.00 75.00 L 100.00 50.00");
+        assert_eq!(joined.gaps, "");
+
+        let broken = goal_chart(
+            &points,
+            None,
+            Style {
+                break_gaps: true,
+                ..Style::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(broken.commands, "M 0.00 25.00 L 7.00 75.00 M 100.00 50.00");
+        assert_eq!(broken.gaps, "M 7.00 75.00 L 100.00 50.00");
+    }
+
+    #[test]
+    fn test_monthly_averages_carry_error_bars() {
+        let at = |m, d| Local.with_ymd_and_hms(2025, m, d, 12, 0, 0).unwrap();
+        let points = [
+            (at(1, 1), 20.0),
+            (at(1, 31), 22.0),
+            (at(2, 10), 19.0),
+            (at(3, 5), 18.0),
+            (at(3, 7), 18.0),
+        ];
+        assert_eq!(
+            group(&points, Grouping::Monthly),
+            [
+                (at(1, 16), 21.0, 2.0_f64.sqrt()),
+                (at(2, 10), 19.0, 0.0),
+                (at(3, 6), 18.0, 0.0),
+            ]
+        );
+        assert_eq!(group(&points, Grouping::Sessions).len(), 5);
+        // Sunday 5 and Monday 6 January fall in different weeks
+        let weekend = [(at(1, 5), 20.0), (at(1, 6), 21.0)];
+        assert_eq!(group(&weekend, Grouping::Weekly).len(), 2);
+
+        let style = Style {
+            grouping: Grouping::from_label("Monthly average"),
+            ..Style::default()
+        };
+        let chart = goal_chart(&points, None, style).unwrap();
+        assert_eq!(chart.commands.matches(['M', 'L']).count(), 3);
+        assert_eq!(chart.error_bars.matches('M').count(), 1);
+        assert!((chart.high - (21.0 + 2.0_f64.sqrt() + 1.0)).abs() < 1e-9);
+        assert_eq!(trend_chart(&points).unwrap().error_bars, "");
+    }
+
+    #[test]
+    fn test_sparkline_spreads_values_evenly() {
+        assert_eq!(sparkline(&[12.0]), "");
+        assert_eq!(
+            sparkline(&[7.0, 7.5, 72.0]),
+            "M 0.00 100.00 L 50.00 99.23 L 100.00 0.00"
+        );
+        assert_eq!(sparkline(&[9.0, 9.0]), "M 0.00 50.00 L 100.00 50.00");
+    }
+
+    #[test]
+    fn test_range_start() {
+        let now = Local.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        assert_eq!(range_start("All time", now), None);
+        assert_eq!(
+            range_start("Last month", now),
+            Some(now - Duration::days(31))
+        );
+    }
+}
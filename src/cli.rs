@@ -0,0 +1,302 @@
+use body_fat_core::{
+    ALL_SITES, AUTOMATIC_PROTOCOL, Formula, Measurements, Subject, parse_age, site_label,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+
+/// A skinfold calculation requested from the command line or stdin.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Request {
+    /// "male" or "female".
+    pub sex: String,
+    pub age: u32,
+    /// Protocol picker label, "Automatic" picks from the measured sites.
+    #[serde(default = "automatic")]
+    pub protocol: String,
+    /// Sites that could not be measured.
+    #[serde(default)]
+    pub excluded: Vec<String>,
+    /// Skinfold readings in mm by site key.
+    pub sites: BTreeMap<String, f64>,
+}
+
+fn automatic() -> String {
+    AUTOMATIC_PROTOCOL.to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Response {
+    pub formula: String,
+    /// Sum of the formula's skinfolds in mm.
+    pub sum: f64,
+    pub body_fat: f64,
+    /// Expected spread of the result from reading error, in percentage points.
+    pub uncertainty: f64,
+    pub category: String,
+    /// Problems that did not stop the calculation, such as an age outside
+    /// the equations' validation range.
+    pub warnings: Vec<String>,
+}
+
+/// Build a request from `--sex=`, `--age=`, `--protocol=`, `--exclude=` and
+/// one `--<site>=<mm>` flag per reading.
+pub fn parse_args(args: &[String]) -> Result<Request, Vec<String>> {
+    let mut errors = Vec::new();
+    let mut request = Request {
+        sex: "male".to_string(),
+        age: 0,
+        protocol: automatic(),
+        excluded: Vec::new(),
+        sites: BTreeMap::new(),
+    };
+    for arg in args {
+        let Some((flag, value)) = arg.strip_prefix("--").and_then(|arg| arg.split_once('=')) else {
+            continue;
+        };
+        match flag {
+            "sex" => request.sex = value.to_string(),
+            "age" => match parse_age(value) {
+                Ok(age) => request.age = age,
+                Err(e) => errors.push(e.to_string()),
+            },
+            "protocol" => request.protocol = value.to_string(),
+            "exclude" => request.excluded.push(value.to_string()),
+            site if ALL_SITES.contains(&site) => match value.parse() {
+                Ok(mm) => {
+                    request.sites.insert(site.to_string(), mm);
+                }
+                Err(_) => errors.push(format!(
+                    "{} measurement must be a valid number",
+                    site_label(site)
+                )),
+            },
+            _ => {}
+        }
+    }
+    if errors.is_empty() {
+        Ok(request)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Calculate body fat the same way as the skinfold tab of the window.
+pub fn compute(request: &Request) -> Result<Response, Vec<String>> {
+    let mut errors = Vec::new();
+    let is_male = match request.sex.to_lowercase().as_str() {
+        "male" => true,
+        "female" => false,
+        _ => {
+            errors.push("Sex must be male or female".to_string());
+            true
+        }
+    };
+    for site in request.sites.keys().chain(&request.excluded) {
+        if !ALL_SITES.contains(&site.as_str()) {
+            errors.push(format!("Unknown site: {}", site));
+        }
+    }
+
+    let mut measurements = Measurements::new();
+    for (site, mm) in &request.sites {
+        measurements.set_measurement(site, *mm);
+    }
+    let excluded: Vec<&str> = request.excluded.iter().map(String::as_str).collect();
+    let subject = Subject {
+        age: request.age,
+        is_male,
+        protocol: &request.protocol,
+        excluded: &excluded,
+        ranges: &[],
+    };
+    match body_fat_core::compute(&measurements, &subject) {
+        Ok(composition) if errors.is_empty() => Ok(Response {
+            formula: composition.formula.name().to_string(),
+            sum: composition.sum,
+            body_fat: composition.body_fat,
+            uncertainty: composition.uncertainty,
+            category: composition.classification.label(),
+            warnings: composition
+                .warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect(),
+        }),
+        Ok(_) => Err(errors),
+        Err(calculation_errors) => {
+            errors.extend(calculation_errors.iter().map(|e| e.to_string()));
+            Err(errors)
+        }
+    }
+}
+
+/// Run a calculation without opening the window, returning the exit code.
+/// Reads a JSON request from stdin with `--stdin`, otherwise from flags, and
+/// prints JSON with `--format=json`.
+pub fn run(args: &[String]) -> i32 {
+    let request = if args.iter().any(|arg| arg == "--stdin") {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| vec![format!("Could not read stdin: {}", e)])
+            .and_then(|_| {
+                serde_json::from_str(&input).map_err(|e| vec![format!("Invalid request: {}", e)])
+            })
+    } else {
+        parse_args(args)
+    };
+
+    match request.and_then(|request| compute(&request)) {
+        Ok(response) if args.iter().any(|arg| arg == "--format=json") => {
+            println!("{}", serde_json::to_string_pretty(&response).unwrap());
+            0
+        }
+        Ok(response) => {
+            println!("Formula: {}", response.formula);
+            println!(
+                "Body fat: {:.2}% (±{:.1})",
+                response.body_fat, response.uncertainty
+            );
+            println!("Category: {}", response.category);
+            for warning in &response.warnings {
+                println!("Warning: {}", warning);
+            }
+            0
+        }
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            2
+        }
+    }
+}
+
+/// Ages and mean per-site skinfolds (mm) tabulated by `--compare-formulas`.
+const COMPARISON_AGES: [u32; 5] = [20, 30, 40, 50, 60];
+const COMPARISON_MEAN_SKINFOLDS: [f64; 6] = [5.0, 10.0, 15.0, 20.0, 25.0, 30.0];
+
+/// Markdown tables of every formula's estimate over a grid of ages and skinfolds.
+///
+/// Formulas sum different numbers of sites, so rows are keyed by the mean
+/// skinfold per site rather than the raw sum.
+pub fn formula_comparison_table(is_male: bool) -> String {
+    let mut table = format!(
+        "# Formula comparison ({})\n",
+        if is_male { "male" } else { "female" }
+    );
+    for age in COMPARISON_AGES {
+        table.push_str(&format!("\n## Age {}\n\n| Mean skinfold (mm) |", age));
+        for formula in Formula::ALL {
+            table.push_str(&format!(" {} |", formula.name()));
+        }
+        table.push_str(&format!("\n|---|{}\n", "---:|".repeat(Formula::ALL.len())));
+        for mean in COMPARISON_MEAN_SKINFOLDS {
+            table.push_str(&format!("| {:.0} |", mean));
+            for formula in Formula::ALL {
+                let total = mean * formula.sites(is_male).len() as f64;
+                table.push_str(&format!(" {:.1}% |", formula.body_fat(total, age, is_male)));
+            }
+            table.push('\n');
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_flags_and_json_give_the_same_result() {
+        let from_flags = parse_args(&args(&[
+            "--cli",
+            "--sex=male",
+            "--age=30",
+            "--protocol=3-site",
+            "--chest=10",
+            "--abdominal=20",
+            "--thigh=15",
+        ]))
+        .unwrap();
+        let from_json: Request = serde_json::from_str(
+            r#"{"sex": "male", "age": 30, "protocol": "3-site",
+                "sites": {"chest": 10, "abdominal": 20, "thigh": 15}}"#,
+        )
+        .unwrap();
+        assert_eq!(from_flags, from_json);
+
+        let response = compute(&from_flags).unwrap();
+        assert_eq!(response.formula, "Jackson & Pollock 3-site");
+        assert_eq!(response.sum, 45.0);
+    }
+
+    #[test]
+    fn test_compute_reports_every_problem() {
+        let request: Request =
+            serde_json::from_str(r#"{"sex": "other", "age": 0, "sites": {"elbow": 3}}"#).unwrap();
+        let errors = compute(&request).unwrap_err();
+        assert!(errors.contains(&"Sex must be male or female".to_string()));
+        assert!(errors.contains(&"Unknown site: elbow".to_string()));
+        assert!(errors.contains(&"Chest measurement is required".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_protocol_exits_with_usage_status() {
+        let request = args(&["--cli", "--age=30", "--protocol=3 site", "--chest=10"]);
+        let errors = compute(&parse_args(&request).unwrap()).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|error| error.starts_with("Unknown protocol"))
+        );
+        assert_eq!(run(&request), 2);
+    }
+
+    #[test]
+    fn test_age_flag_uses_the_shared_age_check() {
+        let errors = parse_args(&args(&["--age=abc"])).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![body_fat_core::CalculationError::NonNumericAge.to_string()]
+        );
+        let errors = parse_args(&args(&["--age=0"])).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![body_fat_core::CalculationError::InvalidAge.to_string()]
+        );
+    }
+
+    #[test]
+    fn test_formula_comparison_table() {
+        for is_male in [true, false] {
+            let table = formula_comparison_table(is_male);
+            assert_eq!(table.matches("## Age").count(), COMPARISON_AGES.len());
+            assert_eq!(
+                table.lines().filter(|line| line.ends_with("% |")).count(),
+                COMPARISON_AGES.len() * COMPARISON_MEAN_SKINFOLDS.len()
+            );
+            for formula in Formula::ALL {
+                assert!(table.contains(formula.name()));
+            }
+        }
+
+        // Estimates rise with skinfold thickness and stay within a plausible range
+        for is_male in [true, false] {
+            for formula in Formula::ALL {
+                let sites = formula.sites(is_male).len() as f64;
+                let estimates: Vec<f64> = COMPARISON_MEAN_SKINFOLDS
+                    .iter()
+                    .map(|mean| formula.body_fat(mean * sites, 40, is_male))
+                    .collect();
+                assert!(estimates.windows(2).all(|pair| pair[0] < pair[1]));
+                assert!(estimates.iter().all(|bf| (0.0..60.0).contains(bf)));
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::report;
+use body_fat_core::SiteRange;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Preferences kept between launches. Missing fields take their defaults so
+/// files written by older versions still load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// "Metric" or "Imperial".
+    pub unit_system: String,
+    /// Skinfold protocol picked by default, e.g. "Automatic" or "7-site".
+    pub protocol: String,
+    /// Decimal places shown for body fat results.
+    pub decimal_places: usize,
+    /// Profile selected when the app was closed, `None` for no profile.
+    pub last_profile_id: Option<i64>,
+    /// How sessions are compared: "Values", "Change" or "Percent change".
+    pub comparison: String,
+    /// Export file name with `{profile}`, `{date}` and `{method}` filled in.
+    pub export_name_template: String,
+    /// Folder for exports, the documents folder when empty.
+    pub export_folder: String,
+    /// Write the profile's progress page after every saved session.
+    pub auto_export: bool,
+    /// Folder the latest result is written to as watch complication JSON
+    /// after every save, off when empty.
+    pub watch_sync_folder: String,
+    /// Speak each result aloud, in builds with the read-aloud feature.
+    pub read_aloud: bool,
+    /// "System", "Light" or "Dark".
+    pub theme: String,
+    /// Text size picker label, e.g. "Normal" or "Large".
+    pub text_size: String,
+    /// Font family name, or "System Default".
+    pub font_family: String,
+    pub reduced_motion: bool,
+    /// "Fast", "Normal" or "Slow".
+    pub animation_speed: String,
+    /// How close a new session may be to a saved one before asking, e.g.
+    /// "2 hours".
+    pub duplicate_window: String,
+    /// Activity level used for the energy estimate, e.g. "Sedentary".
+    pub activity_level: String,
+    /// Extra CSV column names for skinfold sites, e.g. `"Tummy": "abdominal"`.
+    /// Only set by editing the settings file.
+    pub site_aliases: BTreeMap<String, String>,
+    /// Saved CSV column mappings by name, each from header to "date",
+    /// "ignore" or a site key.
+    pub import_presets: BTreeMap<String, BTreeMap<String, String>>,
+    /// Acceptable skinfold readings per site and age group, replacing the
+    /// built-in 1–80 mm for a gym's population. Later entries win. Only set
+    /// by editing the settings file.
+    pub site_ranges: Vec<SiteRangeSetting>,
+}
+
+/// A skinfold range in the settings file, e.g. `{"site": "thigh",
+/// "min_age": 12, "max_age": 17, "min_mm": 2, "max_mm": 40}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SiteRangeSetting {
+    /// Site key, or "*" for every site.
+    pub site: String,
+    #[serde(default)]
+    pub min_age: Option<u32>,
+    #[serde(default)]
+    pub max_age: Option<u32>,
+    pub min_mm: f64,
+    pub max_mm: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            unit_system: "Metric".to_string(),
+            protocol: "Automatic".to_string(),
+            decimal_places: 2,
+            last_profile_id: None,
+            comparison: "Values".to_string(),
+            export_name_template: report::DEFAULT_NAME_TEMPLATE.to_string(),
+            export_folder: String::new(),
+            auto_export: false,
+            watch_sync_folder: String::new(),
+            read_aloud: false,
+            theme: "System".to_string(),
+            text_size: "Normal".to_string(),
+            font_family: "System Default".to_string(),
+            reduced_motion: false,
+            animation_speed: "Normal".to_string(),
+            duplicate_window: "2 hours".to_string(),
+            activity_level: "Sedentary".to_string(),
+            site_aliases: BTreeMap::new(),
+            import_presets: BTreeMap::new(),
+            site_ranges: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// `settings.json` in the platform's per-user config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("com", "gregorycarnegie", "body_fat_calculator")
+            .map(|dirs| dirs.config_dir().join("settings.json"))
+    }
+
+    /// Preferences from `path`, or the defaults when the file is missing or
+    /// can't be read.
+    pub fn load(path: &Path) -> Config {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return Config::default();
+        };
+        serde_json::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("Ignoring settings in {}: {}", path.display(), e);
+            Config::default()
+        })
+    }
+
+    /// The configured site ranges for `Subject::ranges`.
+    pub fn site_ranges(&self) -> Vec<SiteRange> {
+        self.site_ranges
+            .iter()
+            .map(|setting| SiteRange {
+                site: setting.site.clone(),
+                ages: match (setting.min_age, setting.max_age) {
+                    (None, None) => None,
+                    (min, max) => Some(min.unwrap_or(0)..=max.unwrap_or(u32::MAX)),
+                },
+                range_mm: setting.min_mm..=setting.max_mm,
+            })
+            .collect()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trip_and_defaults() {
+        let dir = std::env::temp_dir().join(format!("bfc-config-{}", std::process::id()));
+        let path = dir.join("nested").join("settings.json");
+        assert_eq!(Config::load(&path), Config::default());
+
+        let config = Config {
+            unit_system: "Imperial".to_string(),
+            decimal_places: 1,
+            last_profile_id: Some(3),
+            theme: "Dark".to_string(),
+            font_family: "Noto Sans".to_string(),
+            reduced_motion: true,
+            ..Config::default()
+        };
+        config.save(&path).unwrap();
+        assert_eq!(Config::load(&path), config);
+
+        std::fs::write(&path, r#"{"protocol": "3-site"}"#).unwrap();
+        assert_eq!(Config::load(&path).protocol, "3-site");
+        assert_eq!(Config::load(&path).decimal_places, 2);
+
+        std::fs::write(
+            &path,
+            r#"{"site_ranges": [{"site": "*", "min_mm": 1, "max_mm": 120},
+                {"site": "thigh", "max_age": 17, "min_mm": 2, "max_mm": 40}]}"#,
+        )
+        .unwrap();
+        let ranges = Config::load(&path).site_ranges();
+        assert_eq!(ranges[0].ages, None);
+        assert_eq!(ranges[0].range_mm, 1.0..=120.0);
+        assert_eq!(ranges[1].ages, Some(0..=17));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(Config::load(&path), Config::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+use crate::storage::Session;
+use body_fat_core::{Formula, Measurements, NAVY_FORMULA, calculate_body_fat_navy};
+
+/// Fixes for readings saved in the wrong unit, with the factor that takes a
+/// stored value to the unit it should have been in.
+pub const UNIT_CORRECTIONS: [(&str, f64); 3] = [
+    ("Skinfolds entered in cm", 10.0),
+    ("Skinfolds entered in inches", 25.4),
+    ("Tape entered in inches", 2.54),
+];
+
+pub fn correction_factor(label: &str) -> Option<f64> {
+    UNIT_CORRECTIONS
+        .iter()
+        .find(|(name, _)| *name == label)
+        .map(|(_, factor)| *factor)
+}
+
+/// The session with every reading scaled by `factor` and body fat worked
+/// out again with the same formula.
+pub fn correct_units(session: &Session, factor: f64) -> Result<Session, String> {
+    let scale = |values: &[(String, f64)]| {
+        values
+            .iter()
+            .map(|(site, value)| (site.clone(), value * factor))
+            .collect::<Vec<_>>()
+    };
+    let readings = scale(&session.readings);
+    let reading = |site: &str| {
+        readings
+            .iter()
+            .find(|(name, _)| name == site)
+            .map_or(0.0, |(_, value)| *value)
+    };
+
+    let body_fat = if session.formula == NAVY_FORMULA {
+        calculate_body_fat_navy(
+            reading("neck"),
+            reading("waist"),
+            reading("hip"),
+            reading("height"),
+            session.is_male,
+        )
+        .map_err(|e| e.to_string())?
+    } else {
+        let formula = Formula::ALL
+            .into_iter()
+            .find(|formula| formula.name() == session.formula)
+            .ok_or_else(|| format!("Can't recalculate the {} formula", session.formula))?;
+        let mut measurements = Measurements::new();
+        for (site, value) in &readings {
+            measurements.set_measurement(site, *value);
+        }
+        formula.body_fat(
+            formula.sum(&measurements, session.is_male),
+            session.age,
+            session.is_male,
+        )
+    };
+    Ok(Session {
+        body_fat,
+        trials: scale(&session.trials),
+        readings,
+        ..session.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_correct_units_recalculates_body_fat() {
+        let formula = Formula::JacksonPollock3;
+        let session = Session {
+            profile_id: None,
+            recorded_at: Local.with_ymd_and_hms(2025, 3, 1, 9, 30, 0).unwrap(),
+            formula: formula.name().to_string(),
+            age: 34,
+            is_male: true,
+            body_fat: formula.body_fat(4.5, 34, true),
+            readings: vec![
+                ("chest".to_string(), 1.0),
+                ("abdominal".to_string(), 2.0),
+                ("thigh".to_string(), 1.5),
+            ],
+            trials: vec![("chest".to_string(), 1.1)],
+            caliper: None,
+            tag: None,
+            excluded_sites: Vec::new(),
+        };
+        let corrected = correct_units(
+            &session,
+            correction_factor("Skinfolds entered in cm").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(corrected.readings[1], ("abdominal".to_string(), 20.0));
+        assert_eq!(corrected.trials, vec![("chest".to_string(), 11.0)]);
+        assert_eq!(corrected.body_fat, formula.body_fat(45.0, 34, true));
+        assert_eq!(corrected.recorded_at, session.recorded_at);
+
+        let relabelled = Session {
+            formula: "Clinic caliper".to_string(),
+            ..session
+        };
+        assert!(correct_units(&relabelled, 10.0).is_err());
+    }
+}
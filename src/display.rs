@@ -0,0 +1,58 @@
+/// How body fat is shown wherever one session is compared with another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// The reading itself, e.g. "17.6%".
+    Values,
+    /// Percentage points from the baseline, e.g. "-0.6 points".
+    Change,
+    /// Change relative to the baseline, e.g. "-3.3% change".
+    PercentChange,
+}
+
+impl Comparison {
+    /// The comparison for a settings label, values when unrecognised.
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "Change" => Comparison::Change,
+            "Percent change" => Comparison::PercentChange,
+            _ => Comparison::Values,
+        }
+    }
+
+    /// `value` compared with `baseline`, falling back to the value itself
+    /// when there is nothing to compare against.
+    pub fn format(self, value: f64, baseline: Option<f64>, decimals: usize) -> String {
+        match (self, baseline) {
+            (Comparison::Change, Some(baseline)) => {
+                format!("{:+.decimals$} points", value - baseline)
+            }
+            (Comparison::PercentChange, Some(baseline)) if baseline != 0.0 => {
+                format!("{:+.1}% change", (value - baseline) / baseline * 100.0)
+            }
+            _ => format!("{:.decimals$}%", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparison_formats() {
+        assert_eq!(Comparison::Values.format(17.6, Some(18.2), 2), "17.60%");
+        assert_eq!(
+            Comparison::Change.format(17.6, Some(18.2), 1),
+            "-0.6 points"
+        );
+        assert_eq!(
+            Comparison::PercentChange.format(19.0, Some(20.0), 1),
+            "-5.0% change"
+        );
+        assert_eq!(Comparison::Change.format(17.6, None, 1), "17.6%");
+        for label in ["Values", "Change", "Percent change"] {
+            assert_eq!(Comparison::from_label(label).format(1.0, None, 0), "1%");
+        }
+        assert_eq!(Comparison::from_label("Change"), Comparison::Change);
+    }
+}
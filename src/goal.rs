@@ -0,0 +1,130 @@
+use body_fat_core::series::{LinearTrend, Sample, linear_trend};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Target body fat percentages a goal can be set to.
+pub const BODY_FAT_RANGE: RangeInclusive<f64> = 1.0..=99.0;
+
+/// Body fat a profile is aiming for, and the date they want to reach it by.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Goal {
+    pub body_fat: f64,
+    pub target_date: NaiveDate,
+}
+
+impl Goal {
+    /// Midday on the target date, for placing it on the chart's time axis.
+    pub fn target_time(&self) -> DateTime<Local> {
+        let noon = self.target_date.and_hms_opt(12, 0, 0).unwrap();
+        Local
+            .from_local_datetime(&noon)
+            .earliest()
+            .unwrap_or_else(|| Local.from_utc_datetime(&noon))
+    }
+}
+
+fn days_between(from: DateTime<Local>, to: DateTime<Local>) -> f64 {
+    (to - from).num_seconds() as f64 / 86_400.0
+}
+
+/// Least-squares line through a body fat series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fit {
+    origin: DateTime<Local>,
+    pub trend: LinearTrend,
+}
+
+impl Fit {
+    pub fn value_at(&self, at: DateTime<Local>) -> f64 {
+        self.trend.value_at(days_between(self.origin, at))
+    }
+}
+
+/// A dated body fat series in the core library's form, counting days from
+/// `origin`.
+fn samples(series: &[(DateTime<Local>, f64)], origin: DateTime<Local>) -> Vec<Sample> {
+    series
+        .iter()
+        .map(|(at, body_fat)| Sample {
+            day: days_between(origin, *at),
+            body_fat: *body_fat,
+            weight_kg: None,
+        })
+        .collect()
+}
+
+/// Linear regression of body fat against time. Needs sessions at two or
+/// more different times.
+pub fn fit(series: &[(DateTime<Local>, f64)]) -> Option<Fit> {
+    let origin = series.first()?.0;
+    let trend = linear_trend(&samples(series, origin))?;
+    Some(Fit { origin, trend })
+}
+
+/// How far a profile is from their goal and whether their trend gets there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoalProgress {
+    /// Percentage points still to lose from the latest session, negative
+    /// when the goal is above it.
+    pub remaining: f64,
+    /// Points per week needed to reach the goal on time, `None` once the
+    /// target date has passed.
+    pub weekly_rate: Option<f64>,
+    /// Body fat the current trend reaches on the target date.
+    pub projected: Option<f64>,
+}
+
+/// Progress towards `goal` from a series of sessions, oldest first.
+pub fn progress(
+    goal: &Goal,
+    series: &[(DateTime<Local>, f64)],
+    now: DateTime<Local>,
+) -> Option<GoalProgress> {
+    let latest = series.last()?.1;
+    let remaining = latest - goal.body_fat;
+    let weeks = days_between(now, goal.target_time()) / 7.0;
+    Some(GoalProgress {
+        remaining,
+        weekly_rate: (weeks > 0.0).then(|| remaining / weeks),
+        projected: fit(series).map(|fit| fit.value_at(goal.target_time())),
+    })
+}
+
+/// Fat mass in kg to lose to reach `target` percent body fat while keeping
+/// the current lean mass.
+pub fn fat_mass_to_lose(weight_kg: f64, body_fat: f64, target: f64) -> f64 {
+    let lean_mass = weight_kg * (1.0 - body_fat / 100.0);
+    weight_kg - lean_mass / (1.0 - target / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 1, d, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_fit_follows_a_straight_line() {
+        let fit = fit(&[(day(1), 20.0), (day(8), 19.3), (day(15), 18.6)]).unwrap();
+        assert!((fit.trend.slope_per_day + 0.1).abs() < 1e-9);
+        assert!((fit.value_at(day(29)) - 17.2).abs() < 1e-9);
+        assert_eq!(super::fit(&[(day(1), 20.0), (day(1), 19.0)]), None);
+    }
+
+    #[test]
+    fn test_goal_progress() {
+        let goal = Goal {
+            body_fat: 16.0,
+            target_date: NaiveDate::from_ymd_opt(2025, 1, 29).unwrap(),
+        };
+        let progress = progress(&goal, &[(day(1), 20.0), (day(15), 18.6)], day(15)).unwrap();
+        assert!((progress.remaining - 2.6).abs() < 1e-9);
+        assert!((progress.weekly_rate.unwrap() - 1.3).abs() < 1e-9);
+        assert!((progress.projected.unwrap() - 17.2).abs() < 1e-9);
+
+        assert!((fat_mass_to_lose(80.0, 20.0, 15.0) - 4.705_882_352_941_18).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,490 @@
+use crate::achievements::Badge;
+use crate::display::Comparison;
+use crate::quality::Suggestion;
+use crate::{BodyFatCalculator, Strings};
+use body_fat_core::{
+    AUTOMATIC_PROTOCOL, CalculationError, CalculationWarning, Classification, Formula,
+};
+use slint::ComponentHandle;
+use std::fmt::Display;
+use std::num::ParseFloatError;
+
+/// Parse a number typed with either a decimal point or a decimal comma, so
+/// "12,5" and "12.5" both read as 12.5. Measurements never need thousands
+/// separators, so a single comma is always taken as the decimal mark.
+pub fn parse_decimal(text: &str) -> Result<f64, ParseFloatError> {
+    let text = text.trim();
+    if !text.contains('.') && text.matches(',').count() == 1 {
+        text.replacen(',', ".", 1).parse()
+    } else {
+        text.parse()
+    }
+}
+
+/// Category, borderline neighbour and cohort note in the user's language,
+/// like `Classification::label` in English.
+pub fn classification_label(ui: &BodyFatCalculator, classification: &Classification) -> String {
+    let strings = ui.global::<Strings>();
+    let category = |name: Option<&str>| {
+        name.map_or_else(Default::default, |name| {
+            strings.invoke_category(name.into())
+        })
+    };
+    strings
+        .invoke_classification(
+            category(Some(classification.category)),
+            category(classification.borderline),
+            classification
+                .cohort_note
+                .map_or_else(Default::default, |note| {
+                    strings.invoke_cohort_note(note.into())
+                }),
+        )
+        .into()
+}
+
+/// "Category for age 30 (Male): Good" in the user's language.
+pub fn category_text(
+    ui: &BodyFatCalculator,
+    age: u32,
+    is_male: bool,
+    classification: &Classification,
+) -> String {
+    ui.global::<Strings>()
+        .invoke_category_for_age(
+            age as i32,
+            is_male,
+            classification_label(ui, classification).into(),
+        )
+        .into()
+}
+
+/// A calculation problem in the user's language. `Display` on the error
+/// stays in English for the command line and logs.
+pub fn error_text(ui: &BodyFatCalculator, error: &CalculationError) -> String {
+    let field = |key: &str| field(ui, key);
+    let fields =
+        |keys: &mut dyn Iterator<Item = &str>| keys.map(field).collect::<Vec<_>>().join(", ");
+    let (kind, args) = match error {
+        CalculationError::MissedSite(site) => ("missed-site", vec![field(site)]),
+        CalculationError::InvalidNumber(site) => ("invalid-number", vec![field(site)]),
+        CalculationError::OutOfRange {
+            site,
+            value,
+            min,
+            max,
+        } => (
+            "out-of-range",
+            vec![
+                field(site),
+                reading(ui, *value),
+                reading(ui, *min),
+                reading(ui, *max),
+            ],
+        ),
+        CalculationError::InvalidAge => ("invalid-age", Vec::new()),
+        CalculationError::NonNumericAge => ("non-numeric-age", Vec::new()),
+        CalculationError::ExcludedSites { formula, sites } => (
+            "excluded-sites",
+            vec![
+                formula.name().to_string(),
+                fields(&mut sites.iter().copied()),
+            ],
+        ),
+        CalculationError::NoFormula { excluded } => (
+            "no-formula",
+            vec![fields(&mut excluded.iter().map(String::as_str))],
+        ),
+        CalculationError::UnknownProtocol(protocol) => (
+            "unknown-protocol",
+            vec![
+                protocol.clone(),
+                AUTOMATIC_PROTOCOL.to_string(),
+                Formula::ALL
+                    .iter()
+                    .map(|formula| formula.protocol_label())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ],
+        ),
+        CalculationError::InvalidCircumference { field: key, unit } => {
+            ("invalid-circumference", vec![field(key), unit.to_string()])
+        }
+        CalculationError::WaistNotAboveNeck { is_male: true } => {
+            ("waist-not-above-neck", Vec::new())
+        }
+        CalculationError::WaistNotAboveNeck { is_male: false } => {
+            ("waist-hip-not-above-neck", Vec::new())
+        }
+    };
+    translated_error(ui, kind, args)
+}
+
+/// The energy panel's complaint about the weight input.
+pub fn weight_error(ui: &BodyFatCalculator, unit: &str) -> String {
+    translated_error(ui, "weight", vec![unit.to_string()])
+}
+
+/// A calculation warning in the user's language.
+pub fn warning_text(ui: &BodyFatCalculator, warning: &CalculationWarning) -> String {
+    match warning {
+        CalculationWarning::AgeOutsideValidation(age) => translated_error(
+            ui,
+            "age-outside-validation",
+            vec![
+                body_fat_core::VALIDATED_AGES.start().to_string(),
+                body_fat_core::VALIDATED_AGES.end().to_string(),
+                age.to_string(),
+            ],
+        ),
+    }
+}
+
+fn translated_error(ui: &BodyFatCalculator, kind: &str, args: Vec<String>) -> String {
+    let args: Vec<slint::SharedString> = args.into_iter().map(Into::into).collect();
+    ui.global::<Strings>()
+        .invoke_error(
+            kind.into(),
+            slint::ModelRc::new(slint::VecModel::from(args)),
+        )
+        .into()
+}
+
+/// Status message or result text `key` from `Strings.text` in the user's
+/// language, with `args` filling its placeholders in order. Numbers among
+/// them should already be formatted with `number`.
+pub fn text(ui: &BodyFatCalculator, key: &str, args: &[&dyn Display]) -> String {
+    let args: Vec<slint::SharedString> = args.iter().map(|arg| arg.to_string().into()).collect();
+    ui.global::<Strings>()
+        .invoke_text(key.into(), slint::ModelRc::new(slint::VecModel::from(args)))
+        .into()
+}
+
+/// `value` to `decimals` places with the language's decimal mark.
+pub fn number(ui: &BodyFatCalculator, value: f64, decimals: usize) -> String {
+    with_decimal_mark(ui, format!("{:.*}", decimals, value))
+}
+
+/// Like `number`, but always with a sign, for changes.
+pub fn signed_number(ui: &BodyFatCalculator, value: f64, decimals: usize) -> String {
+    with_decimal_mark(ui, format!("{:+.*}", decimals, value))
+}
+
+/// A reading the way it would be typed, e.g. "12.5" or "12,5", without
+/// padding it to a fixed number of places.
+pub fn reading(ui: &BodyFatCalculator, value: f64) -> String {
+    with_decimal_mark(ui, value.to_string())
+}
+
+/// A percentage to `decimals` places, e.g. "17.6%".
+pub fn percent(ui: &BodyFatCalculator, value: f64, decimals: usize) -> String {
+    text(ui, "percent", &[&number(ui, value, decimals)])
+}
+
+fn with_decimal_mark(ui: &BodyFatCalculator, number: String) -> String {
+    let mark = ui.global::<Strings>().invoke_decimal_mark();
+    if mark == "." {
+        number
+    } else {
+        number.replace('.', &mark)
+    }
+}
+
+/// Label for a skinfold site or tape measurement key.
+pub fn field(ui: &BodyFatCalculator, key: &str) -> String {
+    ui.global::<Strings>().invoke_field(key.into()).into()
+}
+
+/// Labels for several site or measurement keys, separated by commas.
+pub fn fields<'a>(ui: &BodyFatCalculator, keys: impl IntoIterator<Item = &'a str>) -> String {
+    keys.into_iter()
+        .map(|key| field(ui, key))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Explanation of a result for its info button.
+pub fn glossary(ui: &BodyFatCalculator, metric: &str) -> String {
+    ui.global::<Strings>().invoke_glossary(metric.into()).into()
+}
+
+/// "1 week", "3 weeks" and so on.
+pub fn weeks(ui: &BodyFatCalculator, count: u32) -> String {
+    ui.global::<Strings>().invoke_weeks(count as i32).into()
+}
+
+/// An FFMI rating from `classify_ffmi`.
+pub fn ffmi_class(ui: &BodyFatCalculator, name: &str) -> String {
+    ui.global::<Strings>().invoke_ffmi_class(name.into()).into()
+}
+
+/// Name of an earned badge.
+pub fn badge_title(ui: &BodyFatCalculator, badge: &Badge) -> String {
+    ui.global::<Strings>()
+        .invoke_badge_title(badge.id.into())
+        .into()
+}
+
+/// What earned the badge.
+pub fn badge_description(ui: &BodyFatCalculator, badge: &Badge) -> String {
+    ui.global::<Strings>()
+        .invoke_badge_description(badge.id.into())
+        .into()
+}
+
+/// How a caliper model reads, empty for an unknown name.
+pub fn caliper_note(ui: &BodyFatCalculator, name: &str) -> String {
+    ui.global::<Strings>()
+        .invoke_caliper_note(name.into())
+        .into()
+}
+
+/// A data quality suggestion from `quality::assess`.
+pub fn suggestion_text(ui: &BodyFatCalculator, suggestion: &Suggestion) -> String {
+    match suggestion {
+        Suggestion::RegularSchedule => text(ui, "suggest-regular-schedule", &[]),
+        Suggestion::RepeatReadings => text(ui, "suggest-repeat-readings", &[]),
+        Suggestion::ConsistentPinch(spread) => text(
+            ui,
+            "suggest-consistent-pinch",
+            &[&number(ui, spread * 100.0, 0)],
+        ),
+        Suggestion::OneFormula => text(ui, "suggest-one-formula", &[]),
+    }
+}
+
+/// Body fat compared with an earlier session like `Comparison::format`,
+/// in the user's language.
+pub fn comparison_text(
+    ui: &BodyFatCalculator,
+    comparison: Comparison,
+    value: f64,
+    baseline: Option<f64>,
+    decimals: usize,
+) -> String {
+    match (comparison, baseline) {
+        (Comparison::Change, Some(baseline)) => text(
+            ui,
+            "change-points",
+            &[&signed_number(ui, value - baseline, decimals)],
+        ),
+        (Comparison::PercentChange, Some(baseline)) if baseline != 0.0 => text(
+            ui,
+            "percent-change",
+            &[&signed_number(ui, (value - baseline) / baseline * 100.0, 1)],
+        ),
+        _ => percent(ui, value, decimals),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::achievements::BADGES;
+    use body_fat_core::classification::BELOW_ESSENTIAL;
+    use body_fat_core::{ALL_SITES, CALIPERS, CATEGORIES, FEMALE_TABLE, MALE_TABLE, site_label};
+
+    const UI: &str = include_str!("../ui/main.slint");
+    const MAIN: &str = include_str!("main.rs");
+
+    /// Calls that print to the terminal or log, whose text stays in English.
+    const UNTRANSLATED_CALLS: [&str; 6] = [
+        "eprintln!",
+        "println!",
+        "print!",
+        "debug!",
+        "info_span!",
+        "expect",
+    ];
+
+    /// Keys passed to calls of `function` in `source`: the literals in
+    /// each call's arguments that look like catalogue keys.
+    fn catalogue_keys<'a>(source: &'a str, function: &str) -> Vec<&'a str> {
+        let call = format!("{}(", function);
+        let mut keys = Vec::new();
+        for (at, _) in source.match_indices(&call) {
+            if source[..at].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                continue;
+            }
+            let args = &source[at + call.len()..];
+            let mut depth = 1;
+            let end = args
+                .find(|c| {
+                    depth += match c {
+                        '(' => 1,
+                        ')' => -1,
+                        _ => 0,
+                    };
+                    depth == 0
+                })
+                .unwrap_or(args.len());
+            keys.extend(args[..end].split('"').skip(1).step_by(2).filter(|literal| {
+                !literal.is_empty()
+                    && literal
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c == '-' || c == '_')
+            }));
+        }
+        keys
+    }
+
+    /// String literals in `source` that read as English prose, other than
+    /// those in comments, passed to `UNTRANSLATED_CALLS` or also quoted in
+    /// the UI as a model value rather than as translatable text.
+    fn prose_literals(source: &str) -> Vec<String> {
+        let mut found = Vec::new();
+        // Name in front of each open parenthesis, innermost last
+        let mut calls: Vec<String> = Vec::new();
+        let mut word = String::new();
+        let mut chars = source.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '/' if chars.peek() == Some(&'/') => {
+                    chars.by_ref().find(|&c| c == '\n');
+                    word.clear();
+                }
+                // A char literal, or a lifetime whose name is read as a word
+                '\'' => {
+                    if chars.next() == Some('\\') {
+                        chars.next();
+                    }
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                    }
+                    word.clear();
+                }
+                '"' => {
+                    let mut literal = String::new();
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '\\' => {
+                                chars.next();
+                                literal.push(' ');
+                            }
+                            '"' => break,
+                            c => literal.push(c),
+                        }
+                    }
+                    let untranslated = calls
+                        .iter()
+                        .any(|call| UNTRANSLATED_CALLS.iter().any(|name| call.ends_with(name)));
+                    if !untranslated && reads_as_prose(&literal) && !ui_value(&literal) {
+                        found.push(literal);
+                    }
+                    word.clear();
+                }
+                '(' => calls.push(std::mem::take(&mut word)),
+                ')' => {
+                    calls.pop();
+                    word.clear();
+                }
+                c if c.is_alphanumeric() || "_!:".contains(c) => word.push(c),
+                _ => word.clear(),
+            }
+        }
+        found
+    }
+
+    /// Two or more words, or one starting with a capital letter, where a
+    /// word has at least two letters in a row.
+    fn reads_as_prose(text: &str) -> bool {
+        let words = text
+            .split_whitespace()
+            .filter(|word| {
+                word.chars()
+                    .zip(word.chars().skip(1))
+                    .any(|(a, b)| a.is_alphabetic() && b.is_alphabetic())
+            })
+            .count();
+        words > 1 || (words == 1 && text.starts_with(char::is_uppercase))
+    }
+
+    /// Quoted in the UI other than inside `@tr`, e.g. a combo box choice.
+    fn ui_value(text: &str) -> bool {
+        let quoted = format!("\"{}\"", text);
+        UI.match_indices(&quoted)
+            .any(|(at, _)| !UI[..at].ends_with("@tr("))
+    }
+
+    /// Every string the core can hand to `Strings` has a translatable
+    /// counterpart in the UI.
+    #[test]
+    fn test_strings_cover_core_text() {
+        let translatable = |text: &str| UI.contains(&format!("@tr(\"{}\")", text));
+        for category in CATEGORIES.iter().chain(&[BELOW_ESSENTIAL, "Unclassified"]) {
+            assert!(translatable(category), "{}", category);
+        }
+        for note in MALE_TABLE
+            .cohorts
+            .iter()
+            .chain(FEMALE_TABLE.cohorts)
+            .filter_map(|cohort| cohort.note)
+        {
+            assert!(translatable(note), "{}", note);
+        }
+        for site in ALL_SITES {
+            assert!(translatable(&site_label(site)), "{}", site);
+        }
+        for field in ["height", "neck", "waist", "hip"] {
+            assert!(translatable(body_fat_core::tape_label(field)), "{}", field);
+        }
+        for caliper in CALIPERS {
+            assert!(translatable(caliper.note), "{}", caliper.name);
+        }
+        for badge in BADGES {
+            assert!(translatable(badge.title), "{}", badge.id);
+            assert!(translatable(badge.description), "{}", badge.id);
+        }
+        for class in ["Below average", "Average", "Above average", "Exceptional"] {
+            assert!(translatable(class), "{}", class);
+        }
+
+        // Every key the app asks for has an entry, and nothing it shows
+        // is left as an English literal
+        let main = &MAIN[..MAIN.find("#[cfg(test)]").unwrap_or(MAIN.len())];
+        for function in ["text", "status", "glossary"] {
+            for key in catalogue_keys(main, function) {
+                assert!(
+                    UI.contains(&format!("== \"{}\" {{ return @tr(", key)),
+                    "no {} entry for {}",
+                    function,
+                    key
+                );
+            }
+        }
+        let i18n = include_str!("i18n.rs");
+        let i18n = &i18n[..i18n.find("#[cfg(test)]").unwrap_or(i18n.len())];
+        for key in catalogue_keys(i18n, "text") {
+            assert!(UI.contains(&format!("key == \"{}\"", key)), "{}", key);
+        }
+        assert_eq!(prose_literals(main), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_prose_literals_skip_logging_and_ui_values() {
+        let source = r#"
+            // "Not this"
+            let unit = if is_male { 'm' } else { '"' };
+            eprintln!("Could not load {}: {}", path, e);
+            let _span = tracing::info_span!("calculate").entered();
+            push_message(ui, format!("Saved to history"));
+            ui.set_selected_gender("Female".into());
+            let key = "no-export-folder";
+        "#;
+        assert_eq!(prose_literals(source), ["Saved to history"]);
+        assert!(reads_as_prose("Male"));
+        assert!(!reads_as_prose("%Y-%m-%d %H:%M"));
+        assert!(!reads_as_prose("mm"));
+        assert!(!reads_as_prose("remote-entry"));
+    }
+
+    #[test]
+    fn test_parse_decimal_accepts_either_separator() {
+        assert_eq!(parse_decimal("12,5"), Ok(12.5));
+        assert_eq!(parse_decimal(" 12.5 "), Ok(12.5));
+        assert_eq!(parse_decimal("12"), Ok(12.0));
+        assert!(parse_decimal("1,2,5").is_err());
+        assert!(parse_decimal("1.2,5").is_err());
+        assert!(parse_decimal("").is_err());
+    }
+}
@@ -0,0 +1,513 @@
+use crate::i18n::parse_decimal;
+use crate::profile::Profile;
+use crate::storage::Session;
+use body_fat_core::{ALL_SITES, Measurements, Subject, compute, site_label};
+use chrono::{Local, NaiveDate, TimeZone};
+use std::collections::BTreeMap;
+
+/// Other names for each site seen in exported spreadsheets, including German
+/// and Spanish ones. Matched after `normalize`, so case, units and accents
+/// don't matter.
+pub const DEFAULT_SITE_ALIASES: [(&str, &str); 29] = [
+    ("pec", "chest"),
+    ("pectoral", "chest"),
+    ("brust", "chest"),
+    ("pecho", "chest"),
+    ("abs", "abdominal"),
+    ("abdomen", "abdominal"),
+    ("belly", "abdominal"),
+    ("bauch", "abdominal"),
+    ("abdominal", "abdominal"),
+    ("quad", "thigh"),
+    ("oberschenkel", "thigh"),
+    ("muslo", "thigh"),
+    ("tricep", "triceps"),
+    ("trizeps", "triceps"),
+    ("triceps", "triceps"),
+    ("subscap", "subscapular"),
+    ("subskapular", "subscapular"),
+    ("subescapular", "subscapular"),
+    ("suprailiaca", "suprailiac"),
+    ("suprailiakal", "suprailiac"),
+    ("iliac crest", "suprailiac"),
+    ("axilla", "midaxillary"),
+    ("mid axillary", "midaxillary"),
+    ("mittelaxillar", "midaxillary"),
+    ("axilar media", "midaxillary"),
+    ("medio axilar", "midaxillary"),
+    ("bicep", "biceps"),
+    ("bizeps", "biceps"),
+    ("biceps", "biceps"),
+];
+
+/// Headers taken as the session date.
+const DATE_HEADERS: [&str; 5] = ["date", "recorded at", "datum", "fecha", "day"];
+
+/// What a CSV column holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Date,
+    Site(&'static str),
+    Ignored,
+}
+
+impl Column {
+    /// Name stored in import presets: "date", "ignore" or a site key.
+    pub fn key(self) -> &'static str {
+        match self {
+            Column::Date => "date",
+            Column::Site(site) => site,
+            Column::Ignored => "ignore",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Column {
+        match key {
+            "date" => Column::Date,
+            key => ALL_SITES
+                .into_iter()
+                .find(|site| *site == key)
+                .map_or(Column::Ignored, Column::Site),
+        }
+    }
+
+    /// Name shown in the column mapping screen.
+    pub fn label(self) -> String {
+        match self {
+            Column::Date => "Date".to_string(),
+            Column::Site(site) => site_label(site),
+            Column::Ignored => "Ignore".to_string(),
+        }
+    }
+
+    pub fn from_label(label: &str) -> Column {
+        Column::choices()
+            .into_iter()
+            .find(|column| column.label() == label)
+            .unwrap_or(Column::Ignored)
+    }
+
+    /// Every target a column can be mapped to, in menu order.
+    pub fn choices() -> Vec<Column> {
+        [Column::Ignored, Column::Date]
+            .into_iter()
+            .chain(ALL_SITES.into_iter().map(Column::Site))
+            .collect()
+    }
+}
+
+/// Lowercase with accents, units and separators removed, e.g. "Tríceps (mm)"
+/// becomes "triceps".
+fn normalize(header: &str) -> String {
+    let header = header.to_lowercase();
+    let header = header
+        .trim()
+        .trim_end_matches("(mm)")
+        .trim_end_matches(" mm");
+    header
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            '_' | '-' => ' ',
+            c => c,
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The column a header names. `custom` maps extra names to site keys and is
+/// checked before the built-in aliases, so users can override them.
+pub fn column_for(header: &str, custom: &BTreeMap<String, String>) -> Column {
+    let header = normalize(header);
+    if DATE_HEADERS.contains(&header.as_str()) {
+        return Column::Date;
+    }
+    let site = custom
+        .iter()
+        .find(|(alias, _)| normalize(alias) == header)
+        .map(|(_, site)| site.as_str())
+        .or_else(|| {
+            DEFAULT_SITE_ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == header)
+                .map(|(_, site)| *site)
+        })
+        .unwrap_or(header.as_str());
+    match ALL_SITES.iter().find(|known| **known == site) {
+        Some(site) => Column::Site(site),
+        None => Column::Ignored,
+    }
+}
+
+/// The cells of a CSV file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// The first few non-empty values in a column, for showing what it holds.
+    pub fn samples(&self, column: usize) -> String {
+        self.rows
+            .iter()
+            .filter_map(|row| row.get(column))
+            .filter(|cell| !cell.is_empty())
+            .take(3)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Header and rows of a CSV file. Semicolons or tabs are taken as the
+/// delimiter when the header has no commas, as spreadsheets using a decimal
+/// comma write them. Quotes around fields are removed.
+pub fn read_table(text: &str) -> Result<Table, String> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next().ok_or("The file is empty")?;
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .find(|delimiter| header.contains(*delimiter))
+        .unwrap_or(',');
+    let split = |line: &str| -> Vec<String> {
+        line.split(delimiter)
+            .map(|field| field.trim().trim_matches('"').trim().to_string())
+            .collect()
+    };
+    Ok(Table {
+        headers: split(header),
+        rows: lines.map(split).collect(),
+    })
+}
+
+/// Columns recognised from each header, in order.
+pub fn auto_mapping(headers: &[String], custom: &BTreeMap<String, String>) -> Vec<Column> {
+    headers
+        .iter()
+        .map(|header| column_for(header, custom))
+        .collect()
+}
+
+/// Whether the user should check `mapping` before importing: some column
+/// wasn't recognised, or there is no date or site to import.
+pub fn needs_review(mapping: &[Column]) -> bool {
+    mapping.contains(&Column::Ignored)
+        || !mapping.contains(&Column::Date)
+        || !mapping
+            .iter()
+            .any(|column| matches!(column, Column::Site(_)))
+}
+
+/// A saved mapping from header to column key, as kept in the settings.
+pub type Preset = BTreeMap<String, String>;
+
+pub fn preset_from(headers: &[String], mapping: &[Column]) -> Preset {
+    headers
+        .iter()
+        .zip(mapping)
+        .map(|(header, column)| (header.clone(), column.key().to_string()))
+        .collect()
+}
+
+/// The preset's mapping for these headers, when it names every one of them.
+pub fn preset_mapping(headers: &[String], preset: &Preset) -> Option<Vec<Column>> {
+    headers
+        .iter()
+        .map(|header| preset.get(header).map(|key| Column::from_key(key)))
+        .collect()
+}
+
+/// One imported measurement day.
+#[derive(Debug, Clone)]
+pub struct ImportedRow {
+    pub date: NaiveDate,
+    pub measurements: Measurements,
+}
+
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%d/%m/%Y", "%d.%m.%Y"]
+        .into_iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+}
+
+/// Read the rows using `mapping`, one column per header. Empty cells are
+/// treated as sites that weren't measured.
+pub fn rows_from(rows: &[Vec<String>], mapping: &[Column]) -> Result<Vec<ImportedRow>, String> {
+    if !mapping.contains(&Column::Date) {
+        return Err("No date column".to_string());
+    }
+    if !mapping
+        .iter()
+        .any(|column| matches!(column, Column::Site(_)))
+    {
+        return Err("No skinfold site columns".to_string());
+    }
+    let mut imported = Vec::new();
+    for (line, row) in rows.iter().enumerate() {
+        // Line 1 is the header
+        let line = line + 2;
+        let mut date = None;
+        let mut measurements = Measurements::new();
+        for (column, cell) in mapping.iter().zip(row) {
+            match column {
+                Column::Date => {
+                    date = Some(
+                        parse_date(cell)
+                            .ok_or_else(|| format!("Line {}: unrecognised date {}", line, cell))?,
+                    )
+                }
+                Column::Site(site) if !cell.is_empty() => {
+                    let value = parse_decimal(cell)
+                        .map_err(|_| format!("Line {}: {} is not a number", line, cell))?;
+                    measurements.set_measurement(site, value);
+                }
+                _ => {}
+            }
+        }
+        let date = date.ok_or_else(|| format!("Line {}: missing date", line))?;
+        imported.push(ImportedRow { date, measurements });
+    }
+    Ok(imported)
+}
+
+/// Sessions for `profile` calculated with `protocol`, stamped at midday as
+/// CSV files rarely record a time. Rows that can't be calculated are
+/// reported by date instead.
+pub fn to_sessions(
+    rows: &[ImportedRow],
+    profile: &Profile,
+    protocol: &str,
+) -> (Vec<Session>, Vec<String>) {
+    let mut sessions = Vec::new();
+    let mut errors = Vec::new();
+    for row in rows {
+        // Empty cells weren't measured, so the formula is picked around them
+        let excluded: Vec<&str> = ALL_SITES
+            .into_iter()
+            .filter(|site| row.measurements.get(site) == 0.0)
+            .collect();
+        let subject = Subject {
+            age: profile.age_on(row.date),
+            is_male: profile.is_male,
+            protocol,
+            excluded: &excluded,
+            ranges: &[],
+        };
+        let composition = match compute(&row.measurements, &subject) {
+            Ok(composition) => composition,
+            Err(row_errors) => {
+                for e in row_errors {
+                    errors.push(format!("{}: {}", row.date, e));
+                }
+                continue;
+            }
+        };
+        let Some(recorded_at) = row
+            .date
+            .and_hms_opt(12, 0, 0)
+            .and_then(|at| Local.from_local_datetime(&at).earliest())
+        else {
+            continue;
+        };
+        sessions.push(Session {
+            profile_id: profile.id,
+            recorded_at,
+            formula: composition.formula.name().to_string(),
+            age: subject.age,
+            is_male: profile.is_male,
+            body_fat: composition.body_fat,
+            readings: ALL_SITES
+                .into_iter()
+                .filter(|site| !excluded.contains(site))
+                .map(|site| (site.to_string(), row.measurements.get(site)))
+                .collect(),
+            trials: Vec::new(),
+            caliper: None,
+            tag: None,
+            excluded_sites: Vec::new(),
+        });
+    }
+    (sessions, errors)
+}
+
+/// Quote a CSV field when it holds a comma, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Sessions as CSV, one row each with a column per site any of them
+/// measured. The date and site columns read back in with `read_table`;
+/// time, formula, body fat, tag and excluded sites are ignored on import.
+pub fn to_csv(sessions: &[Session]) -> String {
+    let mut sites: Vec<&str> = Vec::new();
+    for (site, _) in sessions.iter().flat_map(|session| &session.readings) {
+        if !sites.contains(&site.as_str()) {
+            sites.push(site);
+        }
+    }
+    // Skinfolds in the usual order, tape measurements after them
+    sites.sort_by_key(|site| ALL_SITES.iter().position(|known| known == site));
+    sites.sort_by_key(|site| !ALL_SITES.contains(site));
+
+    let mut csv = String::from("Date,Time,Formula,Body fat,Tag,Excluded");
+    for site in &sites {
+        csv.push(',');
+        csv.push_str(site);
+    }
+    csv.push('\n');
+    for session in sessions {
+        csv.push_str(&format!(
+            "{},{},{},{:.2},{},{}",
+            session.recorded_at.format("%Y-%m-%d"),
+            session.recorded_at.format("%H:%M"),
+            csv_field(&session.formula),
+            session.body_fat,
+            csv_field(session.tag.as_deref().unwrap_or_default()),
+            session.excluded_sites.join(" "),
+        ));
+        for site in &sites {
+            csv.push(',');
+            if let Some((_, value)) = session.readings.iter().find(|(key, _)| key == site) {
+                csv.push_str(&value.to_string());
+            }
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localized_headers_map_to_sites() {
+        let custom = BTreeMap::from([("Tummy".to_string(), "abdominal".to_string())]);
+        let text = "Fecha;Pecho (mm);Bauch;Oberschenkel;Tummy;Notes\n\
+                    2025-03-01;12,5;20;15;;felt good\n\
+                    02/03/2025;12;;14,5;19;\n";
+        let Table { headers, rows } = read_table(text).unwrap();
+        let mapping = auto_mapping(&headers, &custom);
+        assert_eq!(
+            mapping,
+            [
+                Column::Date,
+                Column::Site("chest"),
+                Column::Site("abdominal"),
+                Column::Site("thigh"),
+                Column::Site("abdominal"),
+                Column::Ignored,
+            ]
+        );
+        assert_eq!(column_for("Tríceps", &custom), Column::Site("triceps"));
+        assert_eq!(
+            column_for("mid-axillary mm", &custom),
+            Column::Site("midaxillary")
+        );
+
+        let imported = rows_from(&rows, &mapping).unwrap();
+        assert_eq!(imported[0].measurements.chest, 12.5);
+        assert_eq!(imported[0].measurements.abdominal, 20.0);
+        assert_eq!(
+            imported[1].date,
+            NaiveDate::from_ymd_opt(2025, 3, 2).unwrap()
+        );
+        assert_eq!(imported[1].measurements.abdominal, 19.0);
+
+        let profile = Profile {
+            id: Some(1),
+            name: "Sam".to_string(),
+            birth_date: NaiveDate::from_ymd_opt(1990, 1, 1).unwrap(),
+            is_male: true,
+            preferred_formula: "Automatic".to_string(),
+        };
+        let (sessions, errors) = to_sessions(&imported, &profile, "Automatic");
+        assert_eq!(sessions.len(), 2, "{:?}", errors);
+        assert_eq!(sessions[0].formula, "Jackson & Pollock 3-site");
+        assert_eq!(sessions[0].readings.len(), 3);
+
+        assert!(rows_from(&rows, &[Column::Date]).is_err());
+        let bad = vec![vec!["March".to_string(), "12".to_string()]];
+        assert_eq!(
+            rows_from(&bad, &[Column::Date, Column::Site("chest")]).unwrap_err(),
+            "Line 2: unrecognised date March"
+        );
+    }
+
+    #[test]
+    fn test_presets_cover_unrecognised_columns() {
+        let table =
+            read_table("When,Left pinch,Notes\n2025-03-01,12,x\n2025-03-08,,y\n2025-03-15,13,\n")
+                .unwrap();
+        let mapping = auto_mapping(&table.headers, &BTreeMap::new());
+        assert!(needs_review(&mapping));
+        assert_eq!(table.samples(1), "12, 13");
+
+        let chosen = vec![
+            Column::from_label("Date"),
+            Column::from_label("Triceps"),
+            Column::from_label("Ignore"),
+        ];
+        assert!(!needs_review(&chosen[..2]));
+        let preset = preset_from(&table.headers, &chosen);
+        assert_eq!(preset["Left pinch"], "triceps");
+        assert_eq!(preset_mapping(&table.headers, &preset), Some(chosen));
+        assert_eq!(preset_mapping(&["Other".to_string()], &preset), None);
+        assert_eq!(Column::choices().len(), 2 + ALL_SITES.len());
+    }
+
+    #[test]
+    fn test_exported_sessions_import_again() {
+        let session = |day, readings: &[(&str, f64)], tag: Option<&str>| Session {
+            profile_id: None,
+            recorded_at: Local.with_ymd_and_hms(2025, 3, day, 9, 30, 0).unwrap(),
+            formula: "Jackson & Pollock 3-site".to_string(),
+            age: 34,
+            is_male: true,
+            body_fat: 18.25,
+            readings: readings
+                .iter()
+                .map(|(site, value)| (site.to_string(), *value))
+                .collect(),
+            trials: Vec::new(),
+            caliper: None,
+            tag: tag.map(str::to_string),
+            excluded_sites: if tag.is_some() {
+                vec!["abdominal".to_string(), "triceps".to_string()]
+            } else {
+                Vec::new()
+            },
+        };
+        let csv = to_csv(&[
+            session(1, &[("thigh", 15.0), ("chest", 12.5)], Some("cut")),
+            session(8, &[("chest", 12.0), ("waist", 84.0)], None),
+        ]);
+        assert_eq!(
+            csv,
+            "Date,Time,Formula,Body fat,Tag,Excluded,chest,thigh,waist\n\
+             2025-03-01,09:30,Jackson & Pollock 3-site,18.25,cut,abdominal triceps,12.5,15,\n\
+             2025-03-08,09:30,Jackson & Pollock 3-site,18.25,,,12,,84\n"
+        );
+
+        let table = read_table(&csv).unwrap();
+        let mapping = auto_mapping(&table.headers, &BTreeMap::new());
+        assert_eq!(mapping[0], Column::Date);
+        assert_eq!(mapping[6], Column::Site("chest"));
+        let rows = rows_from(&table.rows, &mapping).unwrap();
+        assert_eq!(rows[1].date, NaiveDate::from_ymd_opt(2025, 3, 8).unwrap());
+        assert_eq!(rows[0].measurements.get("chest"), 12.5);
+        assert_eq!(rows[1].measurements.get("thigh"), 0.0);
+        assert_eq!(csv_field("cut, \"week 1\""), "\"cut, \"\"week 1\"\"\"");
+    }
+}
@@ -1,383 +1,3087 @@
 slint::include_modules!();
 
-use std::cell::RefCell;
+mod achievements;
+mod backup;
+mod chart;
+mod cli;
+mod config;
+mod correction;
+mod display;
+mod goal;
+mod i18n;
+mod import;
+#[cfg(feature = "pdf-reports")]
+mod manifest;
+#[cfg(feature = "pdf-reports")]
+mod pdf;
+mod profile;
+mod quality;
+mod recovery;
+#[cfg(feature = "remote-entry")]
+mod remote_entry;
+mod report;
+#[cfg(feature = "read-aloud")]
+mod speech;
+mod storage;
+mod streak;
+mod synthetic;
+#[cfg(feature = "watch-sync")]
+mod watch;
+
+use backup::Backup;
+use body_fat_core::{
+    ALL_SITES, CalculationError, Formula, MONTE_CARLO_RUNS, Measurements, NAVY_FORMULA,
+    READING_SPREAD_TOLERANCE, Subject, calculate_body_fat_navy, caliper, check_site_consistency,
+    classify_body_fat_with_margin, classify_ffmi, combine_readings, compute, ffmi,
+    harpenden_equivalent, katch_mcardle_bmr, lean_body_mass, normalized_ffmi, parse_age,
+    readings_disagree, select_formula, sensitivity_report, simulate_body_fat_uncertainty, tdee,
+    units::{self, UnitSystem},
+};
+use config::Config;
+use display::Comparison;
+use goal::Goal;
+use profile::Profile;
+#[cfg(feature = "remote-entry")]
+use remote_entry::RemoteEntryServer;
+use slint::Model;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use storage::{BulkEdit, Session, Storage};
+
+/// Number of past sessions listed in the history panel.
+const HISTORY_LIMIT: usize = 50;
+
+/// Number of past readings shown when a site's label is clicked.
+const SITE_HISTORY_LIMIT: usize = 5;
+
+/// Name of the CSV written when exporting sessions ticked in the history.
+const SESSIONS_NAME_TEMPLATE: &str = "sessions-{date}";
+
+/// Choices for how close two sessions can be before saving asks to update
+/// the earlier one instead, in minutes.
+const DUPLICATE_WINDOWS: [(&str, Option<i64>); 5] = [
+    ("Off", None),
+    ("30 minutes", Some(30)),
+    ("1 hour", Some(60)),
+    ("2 hours", Some(120)),
+    ("6 hours", Some(360)),
+];
+
+/// How a message in the results panel is styled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+    Info,
+    Success,
+}
+
+impl Severity {
+    /// Name used by the `Message` struct in the UI.
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Success => "success",
+        }
+    }
+}
+
+/// Where the calculate button is in its cycle, shown by the UI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CalculatorState {
+    /// A required field is empty or not a number; the button is disabled.
+    Incomplete,
+    /// Inputs are valid and any result shown matches them.
+    Ready,
+    Calculating,
+    /// The last calculation failed; its problems are listed in the results.
+    Error,
+    /// Inputs changed after the result shown was calculated.
+    Stale,
+}
+
+impl CalculatorState {
+    /// Name used by the `calculator-state` property in the UI.
+    fn label(self) -> &'static str {
+        match self {
+            CalculatorState::Incomplete => "incomplete",
+            CalculatorState::Ready => "ready",
+            CalculatorState::Calculating => "calculating",
+            CalculatorState::Error => "error",
+            CalculatorState::Stale => "stale",
+        }
+    }
+
+    /// State after an input is edited.
+    fn after_edit(self, complete: bool, showing_result: bool) -> Self {
+        match self {
+            CalculatorState::Calculating => self,
+            _ if !complete => CalculatorState::Incomplete,
+            _ if showing_result => CalculatorState::Stale,
+            _ => CalculatorState::Ready,
+        }
+    }
+
+    /// State once a calculation has finished.
+    fn after_calculation(succeeded: bool) -> Self {
+        if succeeded {
+            CalculatorState::Ready
+        } else {
+            CalculatorState::Error
+        }
+    }
+}
+
+fn message(severity: Severity, text: impl Into<slint::SharedString>) -> Message {
+    Message {
+        severity: severity.label().into(),
+        text: text.into(),
+    }
+}
+
+/// Replace the messages in the results panel.
+fn set_messages(ui: &BodyFatCalculator, messages: Vec<Message>) {
+    ui.set_messages(slint::ModelRc::new(slint::VecModel::from(messages)));
+}
+
+/// Add a message below the ones already shown.
+fn push_message(ui: &BodyFatCalculator, severity: Severity, text: String) {
+    let mut messages: Vec<Message> = ui.get_messages().iter().collect();
+    messages.push(message(severity, text));
+    set_messages(ui, messages);
+}
+
+/// Clear the results panel and list input errors in it, already in the
+/// user's language.
+fn show_errors(ui: &BodyFatCalculator, errors: &[String]) {
+    ui.set_result_text("".into());
+    ui.set_category_text("".into());
+    ui.set_sensitivity_text("".into());
+    ui.set_uncertainty_text("".into());
+    ui.set_result_help("".into());
+    ui.set_category_help("".into());
+    ui.set_show_results(false);
+    set_messages(
+        ui,
+        errors
+            .iter()
+            .map(|error| message(Severity::Error, error.clone()))
+            .collect(),
+    );
+}
+
+/// Show each input problem next to its field, clearing the others.
+fn show_field_errors(ui: &BodyFatCalculator, errors: &[CalculationError]) {
+    let message = |field: &str| -> slint::SharedString {
+        errors
+            .iter()
+            .find(|e| e.field() == field)
+            .map_or_else(Default::default, |e| i18n::error_text(ui, e).into())
+    };
+    ui.set_chest_error(message("chest"));
+    ui.set_abdominal_error(message("abdominal"));
+    ui.set_thigh_error(message("thigh"));
+    ui.set_triceps_error(message("triceps"));
+    ui.set_subscapular_error(message("subscapular"));
+    ui.set_suprailiac_error(message("suprailiac"));
+    ui.set_midaxillary_error(message("midaxillary"));
+    ui.set_biceps_error(message("biceps"));
+    ui.set_age_error(message("age"));
+    ui.set_protocol_error(message("protocol"));
+    ui.set_height_error(message("height"));
+    ui.set_neck_error(message("neck"));
+    ui.set_waist_error(message("waist"));
+    ui.set_hip_error(message("hip"));
+}
+
+/// Show lean mass, BMR and TDEE for the last body fat result and the weight
+/// entered, or clear them when either is missing.
+fn refresh_energy(ui: &BodyFatCalculator, body_fat: Option<f64>) {
+    let units = UnitSystem::from_label(&ui.get_unit_system()).unwrap_or(UnitSystem::Metric);
+    let weight = ui.get_weight_input();
+    let text = match (body_fat, weight.trim()) {
+        (None, _) | (_, "") => String::new(),
+        (Some(body_fat), weight) => match i18n::parse_decimal(weight) {
+            Ok(weight) if weight > 0.0 => {
+                // Lean mass scales with weight, so it can be shown in the entered unit
+                let lean_kg = lean_body_mass(units::weight_to_kg(weight, units), body_fat);
+                let bmr = katch_mcardle_bmr(lean_kg);
+                let mut text = i18n::text(
+                    ui,
+                    "energy",
+                    &[
+                        &i18n::number(ui, lean_body_mass(weight, body_fat), 1),
+                        &units.weight_unit(),
+                        &i18n::number(ui, bmr, 0),
+                        &i18n::number(ui, tdee(bmr, &ui.get_activity_level()).unwrap_or(bmr), 0),
+                    ],
+                );
+                // FFMI needs a height as well
+                if let Ok(height) = i18n::parse_decimal(&ui.get_height_input())
+                    && height > 0.0
+                {
+                    let height_m = units::length_to_cm(height, units) / 100.0;
+                    let index = ffmi(lean_kg, height_m);
+                    let normalized = normalized_ffmi(index, height_m);
+                    let class = classify_ffmi(normalized, ui.get_selected_gender() == "Male");
+                    text.push('\n');
+                    text.push_str(&i18n::text(
+                        ui,
+                        "ffmi",
+                        &[
+                            &i18n::number(ui, index, 1),
+                            &i18n::number(ui, normalized, 1),
+                            &i18n::ffmi_class(ui, class),
+                        ],
+                    ));
+                }
+                text
+            }
+            _ => i18n::weight_error(ui, units.weight_unit()),
+        },
+    };
+    ui.set_energy_text(text.into());
+    ui.set_energy_help(i18n::glossary(ui, "energy").into());
+}
+
+/// Estimate body fat from the US Navy tape measurements, returning the
+/// session to record when it succeeds.
+fn calculate_navy_body_fat(ui: &BodyFatCalculator) -> Option<Session> {
+    let is_male = ui.get_selected_gender() == "Male";
+    let units = UnitSystem::from_label(&ui.get_unit_system()).unwrap_or(UnitSystem::Metric);
+    let mut errors = Vec::new();
+    let mut circumference =
+        |field: &'static str, text: slint::SharedString| match i18n::parse_decimal(&text) {
+            Ok(value) if value > 0.0 => units::length_to_cm(value, units),
+            _ => {
+                errors.push(CalculationError::InvalidCircumference {
+                    field,
+                    unit: units.length_unit(),
+                });
+                0.0
+            }
+        };
+    let height = circumference("height", ui.get_height_input());
+    let neck = circumference("neck", ui.get_neck_input());
+    let waist = circumference("waist", ui.get_waist_input());
+    let hip = if is_male {
+        0.0
+    } else {
+        circumference("hip", ui.get_hip_input())
+    };
+    let age = parse_age(&ui.get_age_input()).unwrap_or_else(|e| {
+        errors.push(e);
+        0
+    });
+    let body_fat_percentage = if errors.is_empty() {
+        calculate_body_fat_navy(neck, waist, hip, height, is_male)
+            .map_err(|e| errors.push(e))
+            .ok()
+    } else {
+        None
+    };
+    let Some(body_fat_percentage) = body_fat_percentage else {
+        show_field_errors(ui, &errors);
+        show_errors(
+            ui,
+            &errors
+                .iter()
+                .map(|e| i18n::error_text(ui, e))
+                .collect::<Vec<_>>(),
+        );
+        return None;
+    };
+    let classification = classify_body_fat_with_margin(age, body_fat_percentage, is_male, 0.0);
+
+    ui.set_result_text(result_text(ui, body_fat_percentage).into());
+    ui.set_category_text(i18n::category_text(ui, age, is_male, &classification).into());
+    let inputs = if is_male {
+        i18n::text(
+            ui,
+            "navy-inputs-male",
+            &[
+                &i18n::number(ui, height, 1),
+                &i18n::number(ui, neck, 1),
+                &i18n::number(ui, waist, 1),
+            ],
+        )
+    } else {
+        i18n::text(
+            ui,
+            "navy-inputs-female",
+            &[
+                &i18n::number(ui, height, 1),
+                &i18n::number(ui, neck, 1),
+                &i18n::number(ui, waist, 1),
+                &i18n::number(ui, hip, 1),
+            ],
+        )
+    };
+    ui.set_result_help(format!("{}\n\n{}", i18n::glossary(ui, "navy_body_fat"), inputs).into());
+    ui.set_category_help(category_help(ui, body_fat_percentage, age, is_male).into());
+    ui.set_sensitivity_text("".into());
+    ui.set_uncertainty_text("".into());
+    set_messages(ui, Vec::new());
+    ui.set_show_results(true);
+
+    let mut readings = vec![
+        ("height".to_string(), height),
+        ("neck".to_string(), neck),
+        ("waist".to_string(), waist),
+    ];
+    if !is_male {
+        readings.push(("hip".to_string(), hip));
+    }
+    Some(Session {
+        profile_id: None,
+        recorded_at: chrono::Local::now(),
+        formula: NAVY_FORMULA.to_string(),
+        age,
+        is_male,
+        body_fat: body_fat_percentage,
+        readings,
+        trials: Vec::new(),
+        caliper: None,
+        tag: None,
+        excluded_sites: Vec::new(),
+    })
+}
+
+/// History database from `--db=PATH`, or the default location.
+fn history_db_path() -> Option<std::path::PathBuf> {
+    std::env::args()
+        .find_map(|arg| arg.strip_prefix("--db=").map(std::path::PathBuf::from))
+        .or_else(Storage::default_path)
+}
+
+/// Synthetic profiles and sessions per profile requested with
+/// `--generate-history=N`, plus `--generate-profiles=P` for more than one
+/// client.
+fn generate_history_request() -> Option<(usize, usize)> {
+    let count = |prefix: &str| {
+        std::env::args().find_map(|arg| {
+            arg.strip_prefix(prefix)
+                .and_then(|count| count.parse().ok())
+        })
+    };
+    let sessions = count("--generate-history=")?;
+    Some((count("--generate-profiles=").unwrap_or(1), sessions))
+}
+
+/// What was done at startup about a damaged history database.
+struct Recovered {
+    /// Where the damaged file was moved.
+    damaged: std::path::PathBuf,
+    /// File holding the rows that could still be read, with how many
+    /// sessions were lost.
+    salvaged: Option<(std::path::PathBuf, usize)>,
+    /// Backup the fresh database was filled from, `None` when it was filled
+    /// from the salvaged rows.
+    restored: Option<std::path::PathBuf>,
+}
+
+/// Open the history database, creating it on first run. A damaged one is
+/// set aside and replaced rather than stopping the app from starting.
+fn open_storage() -> (Option<Rc<RefCell<Storage>>>, Option<Recovered>) {
+    let Some(path) = history_db_path() else {
+        return (None, None);
+    };
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+        && let Err(e) = std::fs::create_dir_all(dir)
+    {
+        eprintln!("Could not create data directory {}: {}", dir.display(), e);
+        return (None, None);
+    }
+    let damaged = match Storage::open(&path) {
+        Ok(storage) => match storage.integrity_problems() {
+            Ok(problems) if problems.is_empty() => {
+                return (Some(Rc::new(RefCell::new(storage))), None);
+            }
+            Ok(problems) => {
+                eprintln!(
+                    "History at {} failed its integrity check: {}",
+                    path.display(),
+                    problems.join("; ")
+                );
+                true
+            }
+            Err(e) => storage::is_corruption(&e),
+        },
+        Err(e) => {
+            eprintln!("Could not open history at {}: {}", path.display(), e);
+            storage::is_corruption(&e)
+        }
+    };
+    if !damaged {
+        return (None, None);
+    }
+    match recover_storage(&path) {
+        Ok((storage, recovered)) => (Some(Rc::new(RefCell::new(storage))), Some(recovered)),
+        Err(e) => {
+            eprintln!("Could not recover history at {}: {}", path.display(), e);
+            (None, None)
+        }
+    }
+}
+
+/// Export what can still be read from the damaged database at `path`, move
+/// it aside and start a fresh one from the newest good backup, or from the
+/// salvaged rows when there is none.
+fn recover_storage(path: &std::path::Path) -> Result<(Storage, Recovered), String> {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let salvage = Storage::open_damaged(path)
+        .ok()
+        .map(|storage| Backup::salvage(&storage));
+    let salvaged = match &salvage {
+        Some((backup, lost)) if !backup.profiles.is_empty() || !backup.sessions.is_empty() => {
+            let salvaged = path.with_file_name(format!("history-salvaged-{}.json", stamp));
+            match std::fs::write(&salvaged, backup.to_json()) {
+                Ok(()) => Some((salvaged, *lost)),
+                Err(e) => {
+                    eprintln!("Could not write {}: {}", salvaged.display(), e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // The write-ahead log and its index belong with the damaged file
+    let damaged = path.with_extension(format!("sqlite3.damaged-{}", stamp));
+    std::fs::rename(path, &damaged).map_err(|e| e.to_string())?;
+    for suffix in ["-wal", "-shm"] {
+        let mut from = path.as_os_str().to_owned();
+        from.push(suffix);
+        let mut to = damaged.as_os_str().to_owned();
+        to.push(suffix);
+        let _ = std::fs::rename(from, to);
+    }
+
+    let mut storage = Storage::open(path).map_err(|e| e.to_string())?;
+    let backup = documents_dir().and_then(|dir| backup::newest_good(&dir));
+    let restored = match backup {
+        Some((backup_path, backup)) => {
+            backup.restore(&mut storage).map_err(|e| e.to_string())?;
+            Some(backup_path)
+        }
+        None => {
+            if let Some((backup, _)) = &salvage {
+                backup.restore(&mut storage).map_err(|e| e.to_string())?;
+            }
+            None
+        }
+    };
+    Ok((
+        storage,
+        Recovered {
+            damaged,
+            salvaged,
+            restored,
+        },
+    ))
+}
+
+/// Explain at startup how a damaged history database was replaced.
+fn show_recovery(ui: &BodyFatCalculator, recovered: &Recovered) {
+    let mut lines = vec![i18n::text(
+        ui,
+        "history-damaged",
+        &[&recovered.damaged.display()],
+    )];
+    lines.push(match &recovered.restored {
+        Some(path) => i18n::text(ui, "history-restored-backup", &[&path.display()]),
+        None if recovered.salvaged.is_some() => i18n::text(ui, "history-restored-salvage", &[]),
+        None => i18n::text(ui, "history-started-empty", &[]),
+    });
+    if let Some((path, lost)) = &recovered.salvaged {
+        lines.push(i18n::text(ui, "history-salvaged", &[&path.display(), lost]));
+    }
+    ui.set_recovery_text(lines.join("\n\n").into());
+}
+
+/// Fill the history database with synthetic profiles and their sessions for
+/// stress-testing.
+fn generate_history(profiles: usize, sessions: usize) {
+    let (Some(storage), _) = open_storage() else {
+        return;
+    };
+    let mut storage = storage.borrow_mut();
+    let clients = synthetic::generate_clients(profiles, sessions, getrandom::u64().unwrap_or(0));
+    for (profile, mut sessions) in clients {
+        let saved = storage.save_profile(&profile).and_then(|id| {
+            for session in &mut sessions {
+                session.profile_id = Some(id);
+            }
+            storage.save_all(&sessions)
+        });
+        match saved {
+            Ok(()) => println!(
+                "Added {} with {} synthetic sessions",
+                profile.name,
+                sessions.len()
+            ),
+            Err(e) => {
+                eprintln!("Could not save synthetic sessions: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Decimal places picked in the settings for body fat results.
+fn decimal_places(ui: &BodyFatCalculator) -> usize {
+    ui.get_decimal_places().parse().unwrap_or(2)
+}
+
+/// "Body Fat Percentage: 17.6%" to the places picked in the settings.
+fn result_text(ui: &BodyFatCalculator, body_fat: f64) -> String {
+    i18n::text(
+        ui,
+        "body-fat-result",
+        &[&i18n::number(ui, body_fat, decimal_places(ui))],
+    )
+}
+
+/// The category's explanation with the inputs it was looked up from.
+fn category_help(ui: &BodyFatCalculator, body_fat: f64, age: u32, is_male: bool) -> String {
+    let inputs = i18n::text(
+        ui,
+        if is_male {
+            "category-inputs-male"
+        } else {
+            "category-inputs-female"
+        },
+        &[&i18n::number(ui, body_fat, 2), &age],
+    );
+    format!("{}\n\n{}", i18n::glossary(ui, "category"), inputs)
+}
+
+/// Speak the result and its category when read-aloud is switched on.
+#[cfg(feature = "read-aloud")]
+fn read_result_aloud(
+    ui: &BodyFatCalculator,
+    speaker: &speech::Speaker,
+    body_fat: f64,
+    classification: &body_fat_core::Classification,
+) {
+    if !ui.get_read_aloud() {
+        return;
+    }
+    let text = i18n::text(
+        ui,
+        "announcement",
+        &[
+            &i18n::number(ui, body_fat, decimal_places(ui)),
+            &i18n::classification_label(ui, classification),
+        ],
+    );
+    if let Err(e) = speaker.say(&text) {
+        eprintln!("Could not read the result aloud: {}", e);
+    }
+}
+
+/// Restore saved preferences; the UI's change handlers refresh what depends
+/// on them once the event loop starts.
+fn apply_config(ui: &BodyFatCalculator, config: &Config, profiles: &[Profile]) {
+    ui.set_unit_system(config.unit_system.as_str().into());
+    ui.set_selected_protocol(config.protocol.as_str().into());
+    ui.set_decimal_places(config.decimal_places.to_string().into());
+    ui.set_comparison_display(config.comparison.as_str().into());
+    ui.set_export_name_template(config.export_name_template.as_str().into());
+    ui.set_export_folder(config.export_folder.as_str().into());
+    ui.set_auto_export(config.auto_export);
+    ui.set_watch_sync_folder(config.watch_sync_folder.as_str().into());
+    ui.set_read_aloud(config.read_aloud);
+    ui.set_theme(config.theme.as_str().into());
+    ui.set_text_size(config.text_size.as_str().into());
+    ui.set_font_family(config.font_family.as_str().into());
+    ui.global::<Motion>()
+        .set_reduced_motion(config.reduced_motion);
+    ui.set_animation_speed(config.animation_speed.as_str().into());
+    ui.set_duplicate_window(config.duplicate_window.as_str().into());
+    ui.set_activity_level(config.activity_level.as_str().into());
+    ui.set_import_presets(import_preset_names(config));
+    if let Some(index) = profiles
+        .iter()
+        .position(|profile| profile.id.is_some() && profile.id == config.last_profile_id)
+    {
+        ui.set_selected_profile(index as i32 + 1);
+    }
+}
+
+/// Current preferences, to be saved when the app closes. Settings with no
+/// control in the window are kept from `loaded`.
+fn current_config(ui: &BodyFatCalculator, profiles: &[Profile], loaded: &Config) -> Config {
+    Config {
+        unit_system: ui.get_unit_system().into(),
+        protocol: ui.get_selected_protocol().into(),
+        decimal_places: decimal_places(ui),
+        last_profile_id: selected_profile_id(ui, profiles),
+        comparison: ui.get_comparison_display().into(),
+        export_name_template: ui.get_export_name_template().into(),
+        export_folder: ui.get_export_folder().into(),
+        auto_export: ui.get_auto_export(),
+        watch_sync_folder: ui.get_watch_sync_folder().trim().into(),
+        read_aloud: ui.get_read_aloud(),
+        theme: ui.get_theme().into(),
+        text_size: ui.get_text_size().into(),
+        font_family: ui.get_font_family().into(),
+        reduced_motion: ui.global::<Motion>().get_reduced_motion(),
+        animation_speed: ui.get_animation_speed().into(),
+        duplicate_window: ui.get_duplicate_window().into(),
+        activity_level: ui.get_activity_level().into(),
+        ..loaded.clone()
+    }
+}
+
+/// Database id of the profile picked in the selector, `None` for "No profile".
+fn selected_profile_id(ui: &BodyFatCalculator, profiles: &[Profile]) -> Option<i64> {
+    (ui.get_selected_profile() as usize)
+        .checked_sub(1)
+        .and_then(|index| profiles.get(index))
+        .and_then(|profile| profile.id)
+}
+
+/// Reload saved profiles into the selector.
+fn refresh_profiles(ui: &BodyFatCalculator, storage: &Storage, profiles: &RefCell<Vec<Profile>>) {
+    match storage.profiles() {
+        Ok(loaded) => *profiles.borrow_mut() = loaded,
+        Err(e) => eprintln!("Could not load profiles: {}", e),
+    }
+    let names: Vec<slint::SharedString> = std::iter::once(i18n::text(ui, "no-profile", &[]).into())
+        .chain(
+            profiles
+                .borrow()
+                .iter()
+                .map(|profile| profile.name.clone().into()),
+        )
+        .collect();
+    ui.set_profile_names(slint::ModelRc::new(slint::VecModel::from(names)));
+}
+
+/// Validate the new profile inputs against the current gender and protocol.
+fn new_profile(ui: &BodyFatCalculator) -> Result<Profile, Vec<String>> {
+    let mut errors = Vec::new();
+    let name = ui.get_new_profile_name().trim().to_string();
+    if name.is_empty() {
+        errors.push(i18n::text(ui, "profile-name-required", &[]));
+    }
+    let birth_date = ui
+        .get_new_profile_birth_date()
+        .trim()
+        .parse::<chrono::NaiveDate>();
+    match birth_date {
+        Ok(birth_date) if errors.is_empty() => Ok(Profile {
+            id: None,
+            name,
+            birth_date,
+            is_male: ui.get_selected_gender() == "Male",
+            preferred_formula: ui.get_selected_protocol().to_string(),
+        }),
+        Ok(_) => Err(errors),
+        Err(_) => {
+            errors.push(i18n::text(ui, "birth-date-format", &[]));
+            Err(errors)
+        }
+    }
+}
+
+/// List the profile's most recent sessions in the history panel.
+fn refresh_history(ui: &BodyFatCalculator, storage: &Storage, profile_id: Option<i64>) {
+    let sessions = match storage.history(profile_id, HISTORY_LIMIT) {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            eprintln!("Could not load history: {}", e);
+            return;
+        }
+    };
+    let comparison = Comparison::from_label(&ui.get_comparison_display());
+    let rows: Vec<HistoryEntry> = sessions
+        .iter()
+        .enumerate()
+        .map(|(i, (id, session))| HistoryEntry {
+            id: *id as i32,
+            selected: false,
+            date: session
+                .recorded_at
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+                .into(),
+            // Show which sites a fallback formula was picked around
+            formula: match session.excluded_sites.as_slice() {
+                [] => session.formula.clone(),
+                excluded => i18n::text(
+                    ui,
+                    "formula-without",
+                    &[
+                        &session.formula,
+                        &i18n::fields(ui, excluded.iter().map(String::as_str)),
+                    ],
+                ),
+            }
+            .into(),
+            // Newest first, so each session compares with the one after it
+            body_fat: i18n::comparison_text(
+                ui,
+                comparison,
+                session.body_fat,
+                sessions.get(i + 1).map(|(_, older)| older.body_fat),
+                decimal_places(ui),
+            )
+            .into(),
+            details: match (&session.caliper, session.is_male) {
+                (Some(caliper), true) => {
+                    i18n::text(ui, "session-details-caliper-male", &[&session.age, caliper])
+                }
+                (Some(caliper), false) => i18n::text(
+                    ui,
+                    "session-details-caliper-female",
+                    &[&session.age, caliper],
+                ),
+                (None, true) => i18n::text(ui, "session-details-male", &[&session.age]),
+                (None, false) => i18n::text(ui, "session-details-female", &[&session.age]),
+            }
+            .into(),
+            tag: session.tag.clone().unwrap_or_default().into(),
+        })
+        .collect();
+    ui.set_history(slint::ModelRc::new(slint::VecModel::from(rows)));
+
+    let sessions: Vec<Session> = sessions.into_iter().map(|(_, session)| session).collect();
+    match quality::assess(&sessions) {
+        Some(quality) => {
+            ui.set_data_quality_text(i18n::text(ui, "data-quality", &[&quality.score]).into());
+            ui.set_data_quality_help(if quality.suggestions.is_empty() {
+                i18n::text(ui, "data-quality-good", &[]).into()
+            } else {
+                let suggestions: Vec<String> = quality
+                    .suggestions
+                    .iter()
+                    .map(|suggestion| i18n::suggestion_text(ui, suggestion))
+                    .collect();
+                format!(
+                    "{}\n• {}",
+                    i18n::text(ui, "data-quality-improve", &[]),
+                    suggestions.join("\n• ")
+                )
+                .into()
+            });
+        }
+        None => {
+            ui.set_data_quality_text("".into());
+            ui.set_data_quality_help("".into());
+        }
+    }
 
-#[derive(Debug, Clone)]
-struct Measurements {
-    chest: f64,
-    abdominal: f64,
-    thigh: f64,
-    triceps: f64,
-    subscapular: f64,
-    suprailiac: f64,
-    midaxillary: f64,
-}
-
-impl Measurements {
-    fn new() -> Self {
-        Self {
-            chest: 0.0,
-            abdominal: 0.0,
-            thigh: 0.0,
-            triceps: 0.0,
-            subscapular: 0.0,
-            suprailiac: 0.0,
-            midaxillary: 0.0,
-        }
-    }
-
-    fn total(&self) -> f64 {
-        self.chest
-            + self.abdominal
-            + self.thigh
-            + self.triceps
-            + self.subscapular
-            + self.suprailiac
-            + self.midaxillary
-    }
-
-    fn set_measurement(&mut self, site: &str, value: f64) {
-        match site {
-            "chest" => self.chest = value,
-            "abdominal" => self.abdominal = value,
-            "thigh" => self.thigh = value,
-            "triceps" => self.triceps = value,
-            "subscapular" => self.subscapular = value,
-            "suprailiac" => self.suprailiac = value,
-            "midaxillary" => self.midaxillary = value,
-            _ => {}
-        }
-    }
-}
-
-fn calculate_body_fat(total_measurement: f64, age: u32, is_male: bool) -> f64 {
-    let body_density = if is_male {
-        // Male Jackson & Pollock 7-site equation
-        1.112 - 0.00043499 * total_measurement + 0.00000055 * total_measurement.powi(2)
-            - 0.00028826 * (age as f64)
+    // Streaks cover every session, not just the ones listed
+    let dates: Vec<chrono::NaiveDate> = match storage.body_fat_series(profile_id, None) {
+        Ok(series) => series.iter().map(|(at, _)| at.date_naive()).collect(),
+        Err(e) => {
+            eprintln!("Could not load sessions for streaks: {}", e);
+            Vec::new()
+        }
+    };
+    let streaks = streak::streaks(&dates, chrono::Local::now().date_naive());
+    let badges: Vec<String> = profile_badges(storage, profile_id)
+        .iter()
+        .map(|(badge, _)| i18n::badge_title(ui, badge))
+        .collect();
+    ui.set_badges_text(if badges.is_empty() {
+        "".into()
     } else {
-        // Female Jackson & Pollock 7-site equation
-        1.097 - 0.00046971 * total_measurement + 0.00000056 * total_measurement.powi(2)
-            - 0.00012828 * (age as f64)
+        i18n::text(ui, "badges", &[&badges.join(" · ")]).into()
+    });
+    ui.set_streak_text(if streaks.longest == 0 {
+        "".into()
+    } else {
+        i18n::text(
+            ui,
+            "streak",
+            &[
+                &i18n::weeks(ui, streaks.current),
+                &i18n::weeks(ui, streaks.longest),
+            ],
+        )
+        .into()
+    });
+}
+
+/// Ids of the sessions ticked in the history panel.
+fn selected_history_ids(ui: &BodyFatCalculator) -> Vec<i64> {
+    ui.get_history()
+        .iter()
+        .filter(|entry| entry.selected)
+        .map(|entry| entry.id as i64)
+        .collect()
+}
+
+/// Bulk edit for a history panel button, reading its inputs.
+fn bulk_edit_request(ui: &BodyFatCalculator, action: &str) -> Result<BulkEdit, String> {
+    match action {
+        "delete" => Ok(BulkEdit::Delete),
+        "shift" => match ui.get_bulk_shift_hours().trim().parse::<i64>() {
+            Ok(hours) if hours != 0 => Ok(BulkEdit::ShiftDates(chrono::Duration::hours(hours))),
+            _ => Err(i18n::text(ui, "shift-hours", &[])),
+        },
+        "relabel" => match ui.get_bulk_formula().trim() {
+            "" => Err(i18n::text(ui, "formula-label-required", &[])),
+            label => Ok(BulkEdit::Relabel(label.to_string())),
+        },
+        "retag" => match ui.get_bulk_tag().trim() {
+            "" => Ok(BulkEdit::Retag(None)),
+            tag => Ok(BulkEdit::Retag(Some(tag.to_string()))),
+        },
+        _ => Err(i18n::text(ui, "unknown-bulk-edit", &[&action])),
+    }
+}
+
+/// Write the sessions as CSV to the export folder, returning how it went.
+fn export_sessions(ui: &BodyFatCalculator, storage: &Storage, ids: &[i64]) -> (Severity, String) {
+    let Some(dir) = export_dir(ui) else {
+        return (Severity::Error, i18n::text(ui, "no-export-folder", &[]));
+    };
+    let mut sessions = Vec::new();
+    for &id in ids {
+        match storage.session(id) {
+            Ok(Some(session)) => sessions.push(session),
+            Ok(None) => {}
+            Err(e) => {
+                return (
+                    Severity::Error,
+                    i18n::text(ui, "load-sessions-failed", &[&e]),
+                );
+            }
+        }
+    }
+    // Oldest first, as the history panel lists them newest first
+    sessions.reverse();
+    let path = dir.join(report::file_name(
+        SESSIONS_NAME_TEMPLATE,
+        "",
+        "",
+        chrono::Local::now(),
+        "csv",
+    ));
+    match std::fs::create_dir_all(&dir)
+        .and_then(|()| std::fs::write(&path, import::to_csv(&sessions)))
+    {
+        Ok(()) => (
+            Severity::Success,
+            i18n::text(ui, "saved-file", &[&path.display()]),
+        ),
+        Err(e) => (
+            Severity::Error,
+            i18n::text(ui, "write-failed", &[&path.display(), &e]),
+        ),
+    }
+}
+
+/// Ticked sessions with their readings converted by the chosen unit
+/// correction and body fat recalculated, plus a line per session comparing
+/// old and new values.
+fn unit_corrections(
+    ui: &BodyFatCalculator,
+    storage: &Storage,
+    profile_id: Option<i64>,
+) -> Result<(Vec<(i64, Session)>, String), String> {
+    let factor = correction::correction_factor(&ui.get_unit_correction())
+        .ok_or_else(|| i18n::text(ui, "pick-unit-correction", &[]))?;
+    let ids = selected_history_ids(ui);
+    if ids.is_empty() {
+        return Err(i18n::text(ui, "tick-sessions-to-correct", &[]));
+    }
+    let history = storage
+        .history(profile_id, HISTORY_LIMIT)
+        .map_err(|e| i18n::text(ui, "load-sessions-failed", &[&e]))?;
+
+    let mut corrected = Vec::new();
+    let mut preview = Vec::new();
+    for (id, session) in history.iter().filter(|(id, _)| ids.contains(id)) {
+        let fixed = correction::correct_units(session, factor)?;
+        let readings = session
+            .readings
+            .iter()
+            .zip(&fixed.readings)
+            .map(|((site, old), (_, new))| {
+                format!(
+                    "{} {} → {}",
+                    i18n::field(ui, site),
+                    i18n::reading(ui, *old),
+                    i18n::number(ui, *new, 1)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        preview.push(i18n::text(
+            ui,
+            "correction-preview",
+            &[
+                &session.recorded_at.format("%Y-%m-%d %H:%M"),
+                &readings,
+                &i18n::number(ui, session.body_fat, 2),
+                &i18n::number(ui, fixed.body_fat, 2),
+            ],
+        ));
+        corrected.push((*id, fixed));
+    }
+    Ok((corrected, preview.join("\n")))
+}
+
+/// Redraw the profile's trend chart for the selected date range.
+fn refresh_chart(ui: &BodyFatCalculator, storage: &Storage, profile_id: Option<i64>) {
+    let since = chart::range_start(&ui.get_chart_range(), chrono::Local::now());
+    let series = match storage.body_fat_series(profile_id, since) {
+        Ok(series) => series,
+        Err(e) => {
+            eprintln!("Could not load trend: {}", e);
+            return;
+        }
+    };
+    let goal = match profile_id.map(|id| storage.goal(id)) {
+        Some(Ok(goal)) => goal,
+        Some(Err(e)) => {
+            eprintln!("Could not load goal: {}", e);
+            None
+        }
+        None => None,
+    };
+    ui.set_goal_text(
+        goal.map(|goal| goal_summary(ui, &goal, &series))
+            .unwrap_or_default()
+            .into(),
+    );
+    let style = chart::Style {
+        break_gaps: ui.get_chart_break_gaps(),
+        grouping: chart::Grouping::from_label(&ui.get_chart_grouping()),
+    };
+    match chart::goal_chart(&series, goal.as_ref(), style) {
+        Some(trend) => {
+            ui.set_trend_commands(trend.commands.into());
+            ui.set_trend_gaps(trend.gaps.into());
+            ui.set_trend_error_bars(trend.error_bars.into());
+            ui.set_trend_projection(trend.projection.into());
+            ui.set_trend_goal(trend.goal.into());
+            ui.set_trend_low(i18n::percent(ui, trend.low, 1).into());
+            ui.set_trend_high(i18n::percent(ui, trend.high, 1).into());
+            ui.set_trend_start(trend.start.format("%Y-%m-%d").to_string().into());
+            ui.set_trend_end(trend.end.format("%Y-%m-%d").to_string().into());
+        }
+        None => ui.set_trend_commands("".into()),
+    }
+}
+
+/// Distance to the goal, the pace needed and where the trend is heading.
+fn goal_summary(
+    ui: &BodyFatCalculator,
+    goal: &Goal,
+    series: &[(chrono::DateTime<chrono::Local>, f64)],
+) -> String {
+    let goal_text = i18n::text(
+        ui,
+        "goal",
+        &[&i18n::number(ui, goal.body_fat, 1), &goal.target_date],
+    );
+    let Some(progress) = goal::progress(goal, series, chrono::Local::now()) else {
+        return goal_text;
+    };
+    let mut summary = vec![goal_text];
+    if progress.remaining <= 0.0 {
+        summary.push(i18n::text(ui, "goal-reached", &[]));
+        return summary.join(" ");
+    }
+    let remaining = i18n::number(ui, progress.remaining, 1);
+    let units = UnitSystem::from_label(&ui.get_unit_system()).unwrap_or(UnitSystem::Metric);
+    summary.push(match i18n::parse_decimal(&ui.get_weight_input()) {
+        Ok(weight) if weight > 0.0 => {
+            // Fat mass scales with weight, so it can be shown in the entered unit
+            let latest = series.last().map_or(0.0, |(_, bf)| *bf);
+            let fat = goal::fat_mass_to_lose(weight, latest, goal.body_fat);
+            i18n::text(
+                ui,
+                "goal-remaining-fat",
+                &[&remaining, &i18n::number(ui, fat, 1), &units.weight_unit()],
+            )
+        }
+        _ => i18n::text(ui, "goal-remaining", &[&remaining]),
+    });
+    summary.push(match progress.weekly_rate {
+        Some(rate) => i18n::text(ui, "goal-weekly-rate", &[&i18n::number(ui, rate, 2)]),
+        None => i18n::text(ui, "goal-date-passed", &[]),
+    });
+    if let Some(projected) = progress.projected {
+        summary.push(i18n::text(
+            ui,
+            "goal-projection",
+            &[&i18n::number(ui, projected.max(0.0), 1)],
+        ));
+    }
+    summary.join(" ")
+}
+
+/// Window picked in the settings for spotting repeat sessions.
+fn duplicate_window(ui: &BodyFatCalculator) -> Option<chrono::Duration> {
+    DUPLICATE_WINDOWS
+        .iter()
+        .find(|(label, _)| *label == ui.get_duplicate_window())
+        .and_then(|(_, minutes)| *minutes)
+        .map(chrono::Duration::minutes)
+}
+
+/// Folder picked for exports in the settings, or the documents folder.
+fn export_dir(ui: &BodyFatCalculator) -> Option<std::path::PathBuf> {
+    match ui.get_export_folder().trim() {
+        "" => documents_dir(),
+        folder => Some(std::path::PathBuf::from(folder)),
+    }
+}
+
+/// Formula of the profile's latest session, for `{method}` in export names.
+fn latest_method(storage: &Storage, profile_id: Option<i64>) -> String {
+    match storage.history(profile_id, 1) {
+        Ok(history) => history
+            .into_iter()
+            .next()
+            .map(|(_, session)| session.formula)
+            .unwrap_or_default(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Write `profile`'s progress page since `since` into `dir`, named by the
+/// template in the settings.
+fn write_progress_page(
+    ui: &BodyFatCalculator,
+    storage: &Storage,
+    profile: &Profile,
+    since: Option<chrono::DateTime<chrono::Local>>,
+    dir: &std::path::Path,
+) -> Result<std::path::PathBuf, String> {
+    let now = chrono::Local::now();
+    let series = storage
+        .body_fat_series(profile.id, since)
+        .map_err(|e| i18n::text(ui, "load-sessions-failed", &[&e]))?;
+    let path = dir.join(report::file_name(
+        &ui.get_export_name_template(),
+        &profile.name,
+        &latest_method(storage, profile.id),
+        now,
+        "html",
+    ));
+    let comparison = Comparison::from_label(&ui.get_comparison_display());
+    std::fs::create_dir_all(dir)
+        .and_then(|()| {
+            std::fs::write(
+                &path,
+                report::progress_page(
+                    &profile.name,
+                    &series,
+                    now,
+                    comparison,
+                    &profile_badges(storage, profile.id),
+                ),
+            )
+        })
+        .map_err(|e| i18n::text(ui, "write-failed", &[&path.display(), &e]))?;
+    Ok(path)
+}
+
+/// Badges the profile has earned, oldest first, for showing or exporting.
+fn profile_badges(
+    storage: &Storage,
+    profile_id: Option<i64>,
+) -> Vec<(
+    &'static achievements::Badge,
+    chrono::DateTime<chrono::Local>,
+)> {
+    let Some(profile_id) = profile_id else {
+        return Vec::new();
+    };
+    match storage.badges(profile_id) {
+        Ok(badges) => badges
+            .into_iter()
+            .filter_map(|(id, earned_at)| Some((achievements::badge(&id)?, earned_at)))
+            .collect(),
+        Err(e) => {
+            eprintln!("Could not load badges: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// After a save, store any badges the profile's sessions now qualify for and
+/// congratulate the user on new ones.
+fn award_badges(ui: &BodyFatCalculator, storage: &RefCell<Storage>, profile_id: Option<i64>) {
+    let Some(profile_id) = profile_id else {
+        return;
+    };
+    let earned = match storage.borrow().body_fat_series(Some(profile_id), None) {
+        Ok(series) => achievements::earned(&series),
+        Err(e) => {
+            eprintln!("Could not load sessions for badges: {}", e);
+            return;
+        }
+    };
+    let awarded = storage
+        .borrow_mut()
+        .award_badges(profile_id, &earned, chrono::Local::now());
+    match awarded {
+        Ok(new) => {
+            for badge in new.iter().filter_map(|id| achievements::badge(id)) {
+                push_message(
+                    ui,
+                    Severity::Success,
+                    i18n::text(
+                        ui,
+                        "badge-earned",
+                        &[
+                            &i18n::badge_title(ui, badge),
+                            &i18n::badge_description(ui, badge),
+                        ],
+                    ),
+                );
+            }
+        }
+        Err(e) => eprintln!("Could not save badges: {}", e),
+    }
+}
+
+/// After a save, refresh the profile's full progress page in the export
+/// folder when auto-export is on.
+fn auto_export(ui: &BodyFatCalculator, storage: &Storage, profile_id: Option<i64>) {
+    if !ui.get_auto_export() {
+        return;
+    }
+    let Some(profile) = profile_id.and_then(|id| {
+        storage
+            .profiles()
+            .ok()?
+            .into_iter()
+            .find(|profile| profile.id == Some(id))
+    }) else {
+        return;
+    };
+    let Some(dir) = export_dir(ui) else {
+        push_message(ui, Severity::Error, i18n::text(ui, "no-export-folder", &[]));
+        return;
+    };
+    match write_progress_page(ui, storage, &profile, None, &dir) {
+        Ok(path) => push_message(
+            ui,
+            Severity::Info,
+            i18n::text(ui, "exported", &[&path.display()]),
+        ),
+        Err(e) => push_message(ui, Severity::Error, e),
+    }
+}
+
+/// After a save, write the profile's latest result as complication JSON to
+/// the watch sync folder when one is set.
+#[cfg(feature = "watch-sync")]
+fn export_watch_complication(ui: &BodyFatCalculator, storage: &Storage, profile_id: Option<i64>) {
+    let folder = ui.get_watch_sync_folder();
+    if folder.trim().is_empty() {
+        return;
+    }
+    let Some(profile) = profile_id.and_then(|id| {
+        storage
+            .profiles()
+            .ok()?
+            .into_iter()
+            .find(|profile| profile.id == Some(id))
+    }) else {
+        return;
+    };
+    let series = match storage.body_fat_series(profile.id, None) {
+        Ok(series) => series,
+        Err(e) => {
+            eprintln!("Could not load sessions for the watch: {}", e);
+            return;
+        }
+    };
+    let Some(complication) = watch::complication(&series) else {
+        return;
+    };
+    let dir = std::path::Path::new(folder.trim());
+    let path = dir.join(watch::file_name(&profile.name));
+    if let Err(e) = std::fs::create_dir_all(dir)
+        .and_then(|()| std::fs::write(&path, watch::to_json(&complication)))
+    {
+        push_message(
+            ui,
+            Severity::Error,
+            i18n::text(ui, "write-failed", &[&path.display(), &e]),
+        );
+    }
+}
+
+/// Write a progress page for the selected profile and range to the
+/// export folder, returning the status to show.
+fn export_progress_page(
+    ui: &BodyFatCalculator,
+    storage: &Storage,
+    profile: Option<&Profile>,
+) -> String {
+    let Some(profile) = profile else {
+        return i18n::text(ui, "pick-export-profile", &[]);
     };
+    let since = chart::range_start(&ui.get_chart_range(), chrono::Local::now());
+    let Some(dir) = export_dir(ui) else {
+        return i18n::text(ui, "no-export-folder", &[]);
+    };
+    match write_progress_page(ui, storage, profile, since, &dir) {
+        Ok(path) => i18n::text(ui, "saved-file", &[&path.display()]),
+        Err(e) => e,
+    }
+}
 
-    (495.0 / body_density) - 450.0
+/// Write the selected profile's share card to the export folder, returning
+/// the status to show.
+fn export_share_card(
+    ui: &BodyFatCalculator,
+    storage: &Storage,
+    profile: Option<&Profile>,
+) -> String {
+    let Some(profile) = profile else {
+        return i18n::text(ui, "pick-export-profile", &[]);
+    };
+    let Some(dir) = export_dir(ui) else {
+        return i18n::text(ui, "no-export-folder", &[]);
+    };
+    let series = match storage.body_fat_series(profile.id, None) {
+        Ok(series) => series,
+        Err(e) => return i18n::text(ui, "load-sessions-failed", &[&e]),
+    };
+    let path = dir.join(report::file_name(
+        report::SHARE_CARD_NAME_TEMPLATE,
+        &profile.name,
+        "",
+        chrono::Local::now(),
+        "svg",
+    ));
+    let card = report::share_card(&profile.name, &series, &profile_badges(storage, profile.id));
+    match std::fs::create_dir_all(&dir).and_then(|()| std::fs::write(&path, card)) {
+        Ok(()) => i18n::text(ui, "saved-file", &[&path.display()]),
+        Err(e) => i18n::text(ui, "write-failed", &[&path.display(), &e]),
+    }
 }
 
-fn classify_body_fat_male(age: u32, bf: f64) -> &'static str {
-    if bf < 5.0 {
-        return "Extremely Lean (Below Essential Fat)";
-    }
+/// Write a PDF progress report for every profile over the chart's range into
+/// a dated folder, on a background thread that reports progress in the export
+/// status. Sessions are loaded first since the database stays on this thread.
+#[cfg(feature = "pdf-reports")]
+fn export_all_progress_pages(ui: &BodyFatCalculator, storage: &Storage, profiles: &[Profile]) {
+    let now = chrono::Local::now();
+    let since = chart::range_start(&ui.get_chart_range(), now);
+    let template = ui.get_export_name_template().to_string();
+    let mut files = Vec::new();
+    let mut pages = Vec::new();
+    for profile in profiles {
+        match storage.body_fat_series(profile.id, since) {
+            Ok(series) => {
+                let method = latest_method(storage, profile.id);
+                files.push((
+                    report::file_name(&template, &profile.name, &method, now, "pdf"),
+                    profile.id.unwrap_or_default(),
+                ));
+                pages.push((
+                    profile.name.clone(),
+                    series,
+                    profile_badges(storage, profile.id),
+                ));
+            }
+            Err(e) => {
+                ui.set_export_status(i18n::text(ui, "load-sessions-failed", &[&e]).into());
+                return;
+            }
+        }
+    }
+    if pages.is_empty() {
+        ui.set_export_status(i18n::text(ui, "add-export-profile", &[]).into());
+        return;
+    }
+    let Some(dir) = export_dir(ui) else {
+        ui.set_export_status(i18n::text(ui, "no-export-folder", &[]).into());
+        return;
+    };
+    let dir = dir.join(format!("progress-reports-{}", now.format("%Y-%m-%d")));
+    let comparison = Comparison::from_label(&ui.get_comparison_display());
+    // Two clients can share a name, or differ only in punctuation
+    report::distinct_file_names(&mut files);
+
+    let ui_handle = ui.as_weak();
+    std::thread::spawn(move || {
+        // The status is worded on the UI thread, where the catalogue is
+        let status = |key: &'static str, args: Vec<String>| {
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                let args: Vec<&dyn std::fmt::Display> = args
+                    .iter()
+                    .map(|arg| arg as &dyn std::fmt::Display)
+                    .collect();
+                ui.set_export_status(i18n::text(&ui, key, &args).into())
+            });
+        };
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            status(
+                "create-folder-failed",
+                vec![dir.display().to_string(), e.to_string()],
+            );
+            return;
+        }
+        let mut failed = Vec::new();
+        let mut manifest = manifest::Manifest::new(storage::SCHEMA_VERSION, now);
+        for (i, ((name, series, badges), (file, _))) in pages.iter().zip(&files).enumerate() {
+            status(
+                "exporting",
+                vec![(i + 1).to_string(), pages.len().to_string()],
+            );
+            let path = dir.join(file);
+            let pdf = report::progress_pdf(name, series, now, comparison, badges);
+            match std::fs::write(&path, &pdf) {
+                Ok(()) => manifest.add(file, &pdf),
+                Err(e) => {
+                    eprintln!("Could not write {}: {}", path.display(), e);
+                    failed.push(name.as_str());
+                }
+            }
+        }
+        // Lists only the reports that were written, so a partial export
+        // can't pass as complete
+        let path = dir.join(manifest::FILE_NAME);
+        if let Err(e) = std::fs::write(&path, manifest.to_json()) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            failed.push(manifest::FILE_NAME);
+        }
+        if failed.is_empty() {
+            status(
+                "exported-reports",
+                vec![pages.len().to_string(), dir.display().to_string()],
+            );
+        } else {
+            status("reports-failed", vec![failed.join(", ")]);
+        }
+    });
+}
+
+/// Names of the saved column mappings for the import screen's picker.
+fn import_preset_names(config: &Config) -> slint::ModelRc<slint::SharedString> {
+    let names: Vec<slint::SharedString> = config
+        .import_presets
+        .keys()
+        .map(|name| name.into())
+        .collect();
+    slint::ModelRc::new(slint::VecModel::from(names))
+}
+
+/// Open the column mapping screen with each column's guessed target and a
+/// few of its values.
+fn show_import_mapping(ui: &BodyFatCalculator, table: &import::Table, mapping: &[import::Column]) {
+    let columns: Vec<ImportColumn> = table
+        .headers
+        .iter()
+        .zip(mapping)
+        .enumerate()
+        .map(|(index, (header, column))| ImportColumn {
+            header: header.into(),
+            samples: table.samples(index).into(),
+            target: column.label().into(),
+        })
+        .collect();
+    ui.set_import_columns(slint::ModelRc::new(slint::VecModel::from(columns)));
+    ui.set_import_preset_name("".into());
+    ui.set_import_mapping_open(true);
+}
+
+/// Calculate and save the imported rows for `profile`, reporting rows that
+/// couldn't be read or calculated.
+fn finish_import(
+    ui: &BodyFatCalculator,
+    storage: &RefCell<Storage>,
+    profile: &Profile,
+    table: &import::Table,
+    mapping: &[import::Column],
+) {
+    let rows = match import::rows_from(&table.rows, mapping) {
+        Ok(rows) => rows,
+        Err(e) => {
+            push_message(ui, Severity::Error, i18n::text(ui, "import-failed", &[&e]));
+            return;
+        }
+    };
+    let protocol = ui.get_selected_protocol();
+    let (sessions, errors) = import::to_sessions(&rows, profile, &protocol);
+    for e in errors {
+        push_message(ui, Severity::Warning, e);
+    }
+    let saved = storage.borrow_mut().save_all(&sessions);
+    match saved {
+        Ok(()) => {
+            push_message(
+                ui,
+                Severity::Success,
+                i18n::text(
+                    ui,
+                    "imported",
+                    &[&sessions.len(), &rows.len(), &profile.name],
+                ),
+            );
+            award_badges(ui, storage, profile.id);
+        }
+        Err(e) => push_message(
+            ui,
+            Severity::Error,
+            i18n::text(ui, "save-import-failed", &[&e]),
+        ),
+    }
+    refresh_history(ui, &storage.borrow(), profile.id);
+    refresh_chart(ui, &storage.borrow(), profile.id);
+}
+
+/// The user's documents folder, or their home folder without one.
+fn documents_dir() -> Option<std::path::PathBuf> {
+    directories::UserDirs::new()
+        .map(|dirs| dirs.document_dir().unwrap_or(dirs.home_dir()).to_path_buf())
+}
+
+/// Save a finished calculation and show it in the history panel. A session
+/// close to an existing one is held in `pending` until the user decides
+/// whether it replaces the earlier one.
+fn record_session(
+    ui: &BodyFatCalculator,
+    storage: Option<&RefCell<Storage>>,
+    pending: &RefCell<Option<(i64, Session)>>,
+    session: Session,
+) {
+    let Some(storage) = storage else {
+        return;
+    };
+    if let Some(window) = duplicate_window(ui) {
+        match storage
+            .borrow()
+            .find_near(session.profile_id, session.recorded_at, window)
+        {
+            Ok(Some((id, recorded_at))) => {
+                ui.set_duplicate_text(
+                    i18n::text(
+                        ui,
+                        "duplicate-session",
+                        &[&recorded_at.format("%Y-%m-%d %H:%M")],
+                    )
+                    .into(),
+                );
+                *pending.borrow_mut() = Some((id, session));
+                return;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Could not check for repeat sessions: {}", e),
+        }
+    }
+    let saved = storage.borrow_mut().save(&session).map(|_| ());
+    if saved.is_ok() {
+        award_badges(ui, storage, session.profile_id);
+        auto_export(ui, &storage.borrow(), session.profile_id);
+        #[cfg(feature = "watch-sync")]
+        export_watch_complication(ui, &storage.borrow(), session.profile_id);
+    }
+    report_save(ui, saved);
+    refresh_history(ui, &storage.borrow(), session.profile_id);
+    refresh_chart(ui, &storage.borrow(), session.profile_id);
+}
+
+/// Add a finished calculation to the top of this sitting's log.
+fn log_calculation(ui: &BodyFatCalculator, session: &Session) {
+    let unit = if session.formula == NAVY_FORMULA {
+        "cm"
+    } else {
+        "mm"
+    };
+    let readings = session
+        .readings
+        .iter()
+        .map(|(site, value)| format!("{} {}", i18n::field(ui, site), i18n::number(ui, *value, 1)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut entries: Vec<SittingEntry> = ui.get_sitting_log().iter().collect();
+    entries.insert(
+        0,
+        SittingEntry {
+            time: session.recorded_at.format("%H:%M:%S").to_string().into(),
+            formula: session.formula.clone().into(),
+            body_fat: i18n::percent(ui, session.body_fat, decimal_places(ui)).into(),
+            inputs: i18n::text(
+                ui,
+                if session.is_male {
+                    "log-inputs-male"
+                } else {
+                    "log-inputs-female"
+                },
+                &[&session.age, &readings, &unit],
+            )
+            .into(),
+        },
+    );
+    ui.set_sitting_log(slint::ModelRc::new(slint::VecModel::from(entries)));
+}
+
+/// Confirm a saved session in the results panel, or say why it failed.
+fn report_save(ui: &BodyFatCalculator, saved: rusqlite::Result<()>) {
+    match saved {
+        Ok(()) => push_message(
+            ui,
+            Severity::Success,
+            i18n::text(ui, "saved-to-history", &[]),
+        ),
+        Err(e) => push_message(
+            ui,
+            Severity::Error,
+            i18n::text(ui, "save-session-failed", &[&e]),
+        ),
+    }
+}
+
+/// Print span timings to stderr when started with `--trace`.
+fn init_tracing() {
+    if std::env::args().any(|arg| arg == "--trace") {
+        tracing_subscriber::fmt()
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .with_writer(std::io::stderr)
+            .init();
+    }
+}
+
+/// Sex requested with `--compare-formulas=male|female` (male when omitted).
+fn compare_formulas_request() -> Option<bool> {
+    std::env::args().find_map(|arg| match arg.as_str() {
+        "--compare-formulas" | "--compare-formulas=male" => Some(true),
+        "--compare-formulas=female" => Some(false),
+        _ => None,
+    })
+}
+
+fn set_measurement_input(ui: &BodyFatCalculator, site: &str, value: slint::SharedString) {
+    match site {
+        "chest" => ui.set_chest_measurement(value),
+        "abdominal" => ui.set_abdominal_measurement(value),
+        "thigh" => ui.set_thigh_measurement(value),
+        "triceps" => ui.set_triceps_measurement(value),
+        "subscapular" => ui.set_subscapular_measurement(value),
+        "suprailiac" => ui.set_suprailiac_measurement(value),
+        "midaxillary" => ui.set_midaxillary_measurement(value),
+        "biceps" => ui.set_biceps_measurement(value),
+        _ => {}
+    }
+}
+
+/// Show the readings from the last calculation as ghost text in the inputs.
+fn set_last_values(ui: &BodyFatCalculator, measurements: &Measurements) {
+    let format_reading = |value: f64| -> slint::SharedString {
+        if value > 0.0 {
+            i18n::reading(ui, value).into()
+        } else {
+            "".into()
+        }
+    };
+    ui.set_chest_last(format_reading(measurements.chest));
+    ui.set_abdominal_last(format_reading(measurements.abdominal));
+    ui.set_thigh_last(format_reading(measurements.thigh));
+    ui.set_triceps_last(format_reading(measurements.triceps));
+    ui.set_subscapular_last(format_reading(measurements.subscapular));
+    ui.set_suprailiac_last(format_reading(measurements.suprailiac));
+    ui.set_midaxillary_last(format_reading(measurements.midaxillary));
+    ui.set_biceps_last(format_reading(measurements.biceps));
+}
+
+/// Fill the popover under a site's label with its last few readings and a
+/// sparkline of them, so a reading out of line with the rest stands out.
+fn show_site_history(
+    ui: &BodyFatCalculator,
+    storage: &Storage,
+    profile_id: Option<i64>,
+    site: &str,
+) {
+    let readings = match storage.site_history(profile_id, site, SITE_HISTORY_LIMIT) {
+        Ok(readings) => readings,
+        Err(e) => {
+            eprintln!("Could not load {} history: {}", site, e);
+            Vec::new()
+        }
+    };
+    let values = if readings.is_empty() {
+        i18n::text(ui, "no-site-history", &[])
+    } else {
+        readings
+            .iter()
+            .map(|(recorded_at, value)| {
+                i18n::text(
+                    ui,
+                    "site-history-entry",
+                    &[&recorded_at.format("%Y-%m-%d"), &i18n::reading(ui, *value)],
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    // Oldest first, so the line reads left to right like the trend chart
+    let oldest_first: Vec<f64> = readings.iter().rev().map(|(_, value)| *value).collect();
+    ui.set_site_history_values(values.into());
+    ui.set_site_history_path(chart::sparkline(&oldest_first).into());
+}
+
+fn measurement_input(ui: &BodyFatCalculator, site: &str) -> slint::SharedString {
+    match site {
+        "chest" => ui.get_chest_measurement(),
+        "abdominal" => ui.get_abdominal_measurement(),
+        "thigh" => ui.get_thigh_measurement(),
+        "triceps" => ui.get_triceps_measurement(),
+        "subscapular" => ui.get_subscapular_measurement(),
+        "suprailiac" => ui.get_suprailiac_measurement(),
+        "midaxillary" => ui.get_midaxillary_measurement(),
+        "biceps" => ui.get_biceps_measurement(),
+        _ => Default::default(),
+    }
+}
+
+/// Skinfold sites with nothing typed and no reading received another way.
+fn empty_sites(ui: &BodyFatCalculator, measurements: &Measurements) -> Vec<&'static str> {
+    ALL_SITES
+        .into_iter()
+        .filter(|site| {
+            measurement_input(ui, site).trim().is_empty() && measurements.get(site) == 0.0
+        })
+        .collect()
+}
+
+/// Sites the formula must do without: those marked as impossible to
+/// measure, plus empty ones when the automatic protocol may fall back to a
+/// formula that doesn't need them.
+fn formula_exclusions(ui: &BodyFatCalculator, measurements: &Measurements) -> Vec<&'static str> {
+    let mut excluded = excluded_sites(ui);
+    if ui.get_empty_site_policy() == "Fall back"
+        && Formula::from_protocol_label(&ui.get_selected_protocol()).is_none()
+    {
+        for site in empty_sites(ui, measurements) {
+            if !excluded.contains(&site) {
+                excluded.push(site);
+            }
+        }
+    }
+    excluded
+}
+
+/// Whether every field the selected method and protocol need holds a number.
+/// Empty skinfold inputs count when a reading arrived another way, e.g. from
+/// a phone.
+fn inputs_complete(ui: &BodyFatCalculator, measurements: &Measurements) -> bool {
+    let number = |text: slint::SharedString| i18n::parse_decimal(&text).is_ok_and(|v| v > 0.0);
+    if parse_age(&ui.get_age_input()).is_err() {
+        return false;
+    }
+    let is_male = ui.get_selected_gender() == "Male";
+    if ui.get_selected_method() == "Tape measure" {
+        return number(ui.get_height_input())
+            && number(ui.get_neck_input())
+            && number(ui.get_waist_input())
+            && (is_male || number(ui.get_hip_input()));
+    }
+    let excluded = formula_exclusions(ui, measurements);
+    let Ok(formula) = select_formula(&ui.get_selected_protocol(), &excluded, is_male) else {
+        return false;
+    };
+    formula.sites(is_male).iter().all(|site| {
+        let text = measurement_input(ui, site);
+        if text.trim().is_empty() {
+            measurements.get(site) > 0.0
+        } else if ui.get_repeated_readings() {
+            text.split_whitespace()
+                .all(|reading| i18n::parse_decimal(reading).is_ok())
+        } else {
+            number(text)
+        }
+    })
+}
+
+/// Move the calculate button to `state`.
+fn set_calculator_state(
+    ui: &BodyFatCalculator,
+    cell: &Cell<CalculatorState>,
+    state: CalculatorState,
+) {
+    cell.set(state);
+    ui.set_calculator_state(state.label().into());
+}
+
+/// Sites ticked as "could not measure", for `Subject::excluded`.
+fn excluded_sites(ui: &BodyFatCalculator) -> Vec<&'static str> {
+    [
+        ("chest", ui.get_chest_excluded()),
+        ("abdominal", ui.get_abdominal_excluded()),
+        ("thigh", ui.get_thigh_excluded()),
+        ("triceps", ui.get_triceps_excluded()),
+        ("subscapular", ui.get_subscapular_excluded()),
+        ("suprailiac", ui.get_suprailiac_excluded()),
+        ("midaxillary", ui.get_midaxillary_excluded()),
+        ("biceps", ui.get_biceps_excluded()),
+    ]
+    .into_iter()
+    .filter(|(_, excluded)| *excluded)
+    .map(|(site, _)| site)
+    .collect()
+}
+
+/// Mark the sites the selected protocol does not use as optional.
+fn update_optional_sites(ui: &BodyFatCalculator) {
+    let is_male = ui.get_selected_gender() == "Male";
+    let required = select_formula(&ui.get_selected_protocol(), &excluded_sites(ui), is_male)
+        .map_or(&ALL_SITES[..], |formula| formula.sites(is_male));
+    ui.set_chest_optional(!required.contains(&"chest"));
+    ui.set_abdominal_optional(!required.contains(&"abdominal"));
+    ui.set_thigh_optional(!required.contains(&"thigh"));
+    ui.set_triceps_optional(!required.contains(&"triceps"));
+    ui.set_subscapular_optional(!required.contains(&"subscapular"));
+    ui.set_suprailiac_optional(!required.contains(&"suprailiac"));
+    ui.set_midaxillary_optional(!required.contains(&"midaxillary"));
+    ui.set_biceps_optional(!required.contains(&"biceps"));
+}
+
+/// Tell the UI which optional subsystems were compiled into this build.
+fn register_capabilities(ui: &BodyFatCalculator) {
+    let capabilities = ui.global::<Capabilities>();
+    capabilities.set_remote_entry(cfg!(feature = "remote-entry"));
+    capabilities.set_read_aloud(cfg!(feature = "read-aloud"));
+    capabilities.set_pdf_reports(cfg!(feature = "pdf-reports"));
+    capabilities.set_watch_sync(cfg!(feature = "watch-sync"));
+}
+
+/// Installed font family names for the font picker, with the system default first.
+fn system_font_families() -> Vec<slint::SharedString> {
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+
+    let mut families: Vec<String> = db
+        .faces()
+        .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+        .collect();
+    families.sort_by_key(|name| name.to_lowercase());
+    families.dedup();
+
+    std::iter::once("System Default".to_string())
+        .chain(families)
+        .map(Into::into)
+        .collect()
+}
+
+/// Render `text` as a black-on-white QR code with a quiet zone, scaled up so it stays crisp.
+#[cfg(feature = "remote-entry")]
+fn qr_image(text: &str) -> Option<slint::Image> {
+    const SCALE: usize = 6;
+    const QUIET_ZONE: usize = 4;
+
+    let code = qrcode::QrCode::new(text).ok()?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let size = (modules + 2 * QUIET_ZONE) * SCALE;
+
+    let mut buffer = slint::SharedPixelBuffer::<slint::Rgb8Pixel>::new(size as u32, size as u32);
+    for (i, pixel) in buffer.make_mut_slice().iter_mut().enumerate() {
+        let x = (i % size) / SCALE;
+        let y = (i / size) / SCALE;
+        let inside = (QUIET_ZONE..modules + QUIET_ZONE).contains(&x)
+            && (QUIET_ZONE..modules + QUIET_ZONE).contains(&y);
+        let dark =
+            inside && colors[(y - QUIET_ZONE) * modules + (x - QUIET_ZONE)] == qrcode::Color::Dark;
+        let value = if dark { 0 } else { 255 };
+        *pixel = slint::Rgb8Pixel {
+            r: value,
+            g: value,
+            b: value,
+        };
+    }
+
+    Some(slint::Image::from_rgb8(buffer))
+}
+
+/// Port for the LAN entry page, enabled with `--remote-entry` or `--remote-entry=PORT`.
+#[cfg(feature = "remote-entry")]
+fn remote_entry_port() -> Option<u16> {
+    std::env::args().find_map(|arg| match arg.as_str() {
+        "--remote-entry" => Some(remote_entry::DEFAULT_PORT),
+        _ => arg
+            .strip_prefix("--remote-entry=")
+            .and_then(|port| port.parse().ok()),
+    })
+}
+
+/// Start the LAN entry server when requested on the command line and wire its
+/// pairing code into the UI.
+#[cfg(feature = "remote-entry")]
+fn start_remote_entry(ui: &BodyFatCalculator) -> Option<Rc<RemoteEntryServer>> {
+    let ui_handle = ui.as_weak();
+    let remote_entry = remote_entry_port().and_then(|port| {
+        let ui_handle = ui_handle.clone();
+        let server = RemoteEntryServer::start(port, move |site, value| {
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                set_measurement_input(&ui, &site, value.clone().into());
+                ui.invoke_measurement_updated(site.into(), value.into());
+            });
+        });
+        match server {
+            Ok(server) => Some(Rc::new(server)),
+            Err(e) => {
+                eprintln!("Failed to start remote entry server: {}", e);
+                None
+            }
+        }
+    });
+    if let Some(server) = &remote_entry {
+        let url = server.pairing_url();
+        ui.set_remote_entry_qr(qr_image(&url).unwrap_or_default());
+        ui.set_remote_entry_url(url.into());
+    }
+
+    // Issue a fresh one-time pairing code for the next phone
+    ui.on_renew_pairing_code({
+        let ui_handle = ui_handle.clone();
+        let remote_entry = remote_entry.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            if let Some(server) = &remote_entry {
+                let url = server.renew_pairing();
+                ui.set_remote_entry_qr(qr_image(&url).unwrap_or_default());
+                ui.set_remote_entry_url(url.into());
+            }
+        }
+    });
+
+    remote_entry
+}
+
+fn main() -> Result<(), slint::PlatformError> {
+    init_tracing();
+
+    // Print the formula comparison instead of opening the window
+    if let Some(is_male) = compare_formulas_request() {
+        print!("{}", cli::formula_comparison_table(is_male));
+        return Ok(());
+    }
+
+    // Headless calculation for scripts and servers without a display
+    if std::env::args().any(|arg| arg == "--cli") {
+        std::process::exit(cli::run(&std::env::args().collect::<Vec<_>>()));
+    }
+
+    // Developer option: generate data instead of opening the window
+    if let Some((profiles, sessions)) = generate_history_request() {
+        generate_history(profiles, sessions);
+        return Ok(());
+    }
+
+    let ui = BodyFatCalculator::new()?;
+    let ui_handle = ui.as_weak();
+
+    // Store measurements in a shared state
+    let measurements = Rc::new(RefCell::new(Measurements::new()));
+
+    register_capabilities(&ui);
+    ui.set_font_families(slint::ModelRc::new(slint::VecModel::from(
+        system_font_families(),
+    )));
+
+    // Saved sessions and profiles
+    let (storage, recovered) = open_storage();
+    if let Some(recovered) = &recovered {
+        show_recovery(&ui, recovered);
+    }
+    let profiles = Rc::new(RefCell::new(Vec::new()));
+    let config_path = Config::default_path();
+    let config = Rc::new(RefCell::new(
+        config_path.as_deref().map(Config::load).unwrap_or_default(),
+    ));
+    if let Some(storage) = &storage {
+        refresh_profiles(&ui, &storage.borrow(), &profiles);
+        refresh_history(&ui, &storage.borrow(), None);
+        refresh_chart(&ui, &storage.borrow(), None);
+    }
+    // Redraw history when sessions should be compared or rounded differently
+    ui.on_history_display_changed({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            if let Some(storage) = &storage {
+                let profile_id = selected_profile_id(&ui, &profiles.borrow());
+                refresh_history(&ui, &storage.borrow(), profile_id);
+            }
+        }
+    });
+
+    ui.on_chart_range_changed({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            if let Some(storage) = &storage {
+                let profile_id = selected_profile_id(&ui, &profiles.borrow());
+                refresh_chart(&ui, &storage.borrow(), profile_id);
+            }
+        }
+    });
+
+    ui.on_site_history_requested({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move |site| {
+            let ui = ui_handle.upgrade().unwrap();
+            if let Some(storage) = &storage {
+                let profile_id = selected_profile_id(&ui, &profiles.borrow());
+                show_site_history(&ui, &storage.borrow(), profile_id, &site);
+            }
+        }
+    });
+
+    // A repeat session either replaces the earlier one or is saved alongside it
+    let pending = Rc::new(RefCell::new(None::<(i64, Session)>));
+    ui.on_resolve_duplicate({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let pending = pending.clone();
+        move |update| {
+            let ui = ui_handle.upgrade().unwrap();
+            ui.set_duplicate_text("".into());
+            let (Some(storage), Some((id, session))) = (&storage, pending.borrow_mut().take())
+            else {
+                return;
+            };
+            let saved = if update {
+                storage.borrow_mut().update(id, &session)
+            } else {
+                storage.borrow_mut().save(&session).map(|_| ())
+            };
+            if saved.is_ok() {
+                award_badges(&ui, storage, session.profile_id);
+                auto_export(&ui, &storage.borrow(), session.profile_id);
+                #[cfg(feature = "watch-sync")]
+                export_watch_complication(&ui, &storage.borrow(), session.profile_id);
+            }
+            report_save(&ui, saved);
+            refresh_history(&ui, &storage.borrow(), session.profile_id);
+            refresh_chart(&ui, &storage.borrow(), session.profile_id);
+        }
+    });
+
+    ui.on_dismiss_message({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let ui = ui_handle.upgrade().unwrap();
+            let mut messages: Vec<Message> = ui.get_messages().iter().collect();
+            if (index as usize) < messages.len() {
+                messages.remove(index as usize);
+                set_messages(&ui, messages);
+            }
+        }
+    });
+
+    // Bulk edits of ticked history rows, with a single level of undo
+    ui.on_history_selection_changed({
+        let ui_handle = ui_handle.clone();
+        move |index, selected| {
+            let ui = ui_handle.upgrade().unwrap();
+            let history = ui.get_history();
+            if let Some(mut entry) = history.row_data(index as usize) {
+                entry.selected = selected;
+                history.set_row_data(index as usize, entry);
+            }
+        }
+    });
+
+    let undo = Rc::new(RefCell::new(Vec::<(i64, Session)>::new()));
+    ui.on_bulk_edit({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        let undo = undo.clone();
+        move |action| {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                return;
+            };
+            let ids = selected_history_ids(&ui);
+            if ids.is_empty() {
+                return;
+            }
+            let edit = match bulk_edit_request(&ui, &action) {
+                Ok(edit) => edit,
+                Err(e) => {
+                    show_errors(&ui, &[e]);
+                    return;
+                }
+            };
+            match storage.borrow_mut().bulk_edit(&ids, &edit) {
+                Ok(before) => *undo.borrow_mut() = before,
+                Err(e) => eprintln!("Could not edit sessions: {}", e),
+            }
+            ui.set_can_undo_bulk_edit(!undo.borrow().is_empty());
+            let profile_id = selected_profile_id(&ui, &profiles.borrow());
+            refresh_history(&ui, &storage.borrow(), profile_id);
+            refresh_chart(&ui, &storage.borrow(), profile_id);
+        }
+    });
+
+    ui.on_undo_bulk_edit({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        let undo = undo.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                return;
+            };
+            let before = std::mem::take(&mut *undo.borrow_mut());
+            if let Err(e) = storage.borrow_mut().restore(&before) {
+                eprintln!("Could not undo edit: {}", e);
+            }
+            ui.set_can_undo_bulk_edit(false);
+            let profile_id = selected_profile_id(&ui, &profiles.borrow());
+            refresh_history(&ui, &storage.borrow(), profile_id);
+            refresh_chart(&ui, &storage.borrow(), profile_id);
+        }
+    });
+
+    ui.on_export_selected_sessions({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                return;
+            };
+            let ids = selected_history_ids(&ui);
+            if ids.is_empty() {
+                show_errors(&ui, &[i18n::text(&ui, "nothing-selected", &[])]);
+                return;
+            }
+            let (severity, message) = export_sessions(&ui, &storage.borrow(), &ids);
+            push_message(&ui, severity, message);
+        }
+    });
+
+    // Fix sessions saved in the wrong unit, previewing before anything changes
+    ui.on_preview_unit_correction({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                return;
+            };
+            let profile_id = selected_profile_id(&ui, &profiles.borrow());
+            let preview = match unit_corrections(&ui, &storage.borrow(), profile_id) {
+                Ok((_, preview)) => preview,
+                Err(e) => e,
+            };
+            ui.set_correction_preview(preview.into());
+        }
+    });
+
+    ui.on_apply_unit_correction({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        let undo = undo.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                return;
+            };
+            let profile_id = selected_profile_id(&ui, &profiles.borrow());
+            let corrected = match unit_corrections(&ui, &storage.borrow(), profile_id) {
+                Ok((corrected, _)) => corrected,
+                Err(e) => {
+                    ui.set_correction_preview(e.into());
+                    return;
+                }
+            };
+            match storage.borrow_mut().rewrite(&corrected) {
+                Ok(before) => {
+                    ui.set_correction_preview(
+                        i18n::text(&ui, "corrected-sessions", &[&before.len()]).into(),
+                    );
+                    *undo.borrow_mut() = before;
+                }
+                Err(e) => ui.set_correction_preview(
+                    i18n::text(&ui, "correct-sessions-failed", &[&e]).into(),
+                ),
+            }
+            ui.set_can_undo_bulk_edit(!undo.borrow().is_empty());
+            refresh_history(&ui, &storage.borrow(), profile_id);
+            refresh_chart(&ui, &storage.borrow(), profile_id);
+        }
+    });
+
+    ui.on_export_progress_page({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                return;
+            };
+            let profiles = profiles.borrow();
+            let profile_id = selected_profile_id(&ui, &profiles);
+            let profile = profiles
+                .iter()
+                .find(|p| p.id.is_some() && p.id == profile_id);
+            let status = export_progress_page(&ui, &storage.borrow(), profile);
+            ui.set_export_status(status.into());
+        }
+    });
+    ui.on_export_share_card({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                return;
+            };
+            let profiles = profiles.borrow();
+            let profile_id = selected_profile_id(&ui, &profiles);
+            let profile = profiles
+                .iter()
+                .find(|p| p.id.is_some() && p.id == profile_id);
+            let status = export_share_card(&ui, &storage.borrow(), profile);
+            ui.set_export_status(status.into());
+        }
+    });
+    #[cfg(feature = "pdf-reports")]
+    ui.on_export_all_progress_pages({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            if let Some(storage) = &storage {
+                export_all_progress_pages(&ui, &storage.borrow(), &profiles.borrow());
+            }
+        }
+    });
+
+    // Picking a profile fills in their details and shows their history
+    ui.on_profile_changed({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let profiles = profiles.borrow();
+            let profile_id = selected_profile_id(&ui, &profiles);
+            if let Some(profile) = profiles
+                .iter()
+                .find(|p| p.id.is_some() && p.id == profile_id)
+            {
+                let today = chrono::Local::now().date_naive();
+                ui.set_age_input(profile.age_on(today).to_string().into());
+                ui.set_selected_gender(if profile.is_male { "Male" } else { "Female" }.into());
+                ui.set_selected_protocol(profile.preferred_formula.clone().into());
+            }
+            let goal = storage
+                .as_ref()
+                .zip(profile_id)
+                .and_then(|(storage, id)| storage.borrow().goal(id).ok().flatten());
+            ui.set_goal_body_fat_input(
+                goal.map(|goal| goal.body_fat.to_string())
+                    .unwrap_or_default()
+                    .into(),
+            );
+            ui.set_goal_date_input(
+                goal.map(|goal| goal.target_date.to_string())
+                    .unwrap_or_default()
+                    .into(),
+            );
+            if let Some(storage) = &storage {
+                refresh_history(&ui, &storage.borrow(), profile_id);
+                refresh_chart(&ui, &storage.borrow(), profile_id);
+            }
+        }
+    });
 
-    let age_groups = [
-        (
-            20,
-            29,
-            [
-                (5.0, 13.8, "Excellent"),
-                (13.9, 17.4, "Good"),
-                (17.5, 20.4, "Average"),
-                (20.5, 24.1, "Below Average"),
-                (24.2, 100.0, "Poor"),
-            ],
-        ),
-        (
-            30,
-            39,
-            [
-                (5.0, 14.9, "Excellent"),
-                (15.0, 18.9, "Good"),
-                (19.0, 21.4, "Average"),
-                (21.5, 25.1, "Below Average"),
-                (25.2, 100.0, "Poor"),
-            ],
-        ),
-        (
-            40,
-            49,
-            [
-                (5.0, 16.9, "Excellent"),
-                (17.0, 19.9, "Good"),
-                (20.0, 22.4, "Average"),
-                (22.5, 26.1, "Below Average"),
-                (26.2, 100.0, "Poor"),
-            ],
-        ),
-        (
-            50,
-            59,
-            [
-                (5.0, 18.9, "Excellent"),
-                (19.0, 21.9, "Good"),
-                (22.0, 24.4, "Average"),
-                (24.5, 28.1, "Below Average"),
-                (28.2, 100.0, "Poor"),
-            ],
-        ),
-        (
-            60,
-            69,
-            [
-                (5.0, 20.9, "Excellent"),
-                (21.0, 23.9, "Good"),
-                (24.0, 26.4, "Average"),
-                (26.5, 30.1, "Below Average"),
-                (30.2, 100.0, "Poor"),
-            ],
-        ),
-    ];
+    ui.on_set_goal({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let (Some(storage), Some(profile_id)) =
+                (&storage, selected_profile_id(&ui, &profiles.borrow()))
+            else {
+                ui.set_goal_text(i18n::text(&ui, "pick-goal-profile", &[]).into());
+                return;
+            };
+            let body_fat = i18n::parse_decimal(&ui.get_goal_body_fat_input());
+            let target_date = ui.get_goal_date_input().trim().parse::<chrono::NaiveDate>();
+            let goal = match (body_fat, target_date) {
+                (Ok(body_fat), Ok(target_date)) if goal::BODY_FAT_RANGE.contains(&body_fat) => {
+                    Goal {
+                        body_fat,
+                        target_date,
+                    }
+                }
+                (Ok(_), Ok(_)) | (Err(_), _) => {
+                    let (min, max) = (goal::BODY_FAT_RANGE.start(), goal::BODY_FAT_RANGE.end());
+                    ui.set_goal_text(i18n::text(&ui, "goal-body-fat-range", &[min, max]).into());
+                    return;
+                }
+                (_, Err(_)) => {
+                    ui.set_goal_text(i18n::text(&ui, "goal-date-format", &[]).into());
+                    return;
+                }
+            };
+            if let Err(e) = storage.borrow_mut().save_goal(profile_id, &goal) {
+                ui.set_goal_text(i18n::text(&ui, "save-goal-failed", &[&e]).into());
+                return;
+            }
+            refresh_chart(&ui, &storage.borrow(), Some(profile_id));
+        }
+    });
 
-    for (lower_age, upper_age, ranges) in age_groups.iter() {
-        if age >= *lower_age && age <= *upper_age {
-            for (low, high, category) in ranges.iter() {
-                if bf >= *low && bf <= *high {
-                    return category;
+    // Back up or restore every profile, goal and session as one JSON file
+    ui.set_backup_path(
+        documents_dir()
+            .map(|dir| {
+                dir.join(format!("{}.json", backup::FILE_PREFIX))
+                    .display()
+                    .to_string()
+            })
+            .unwrap_or_default()
+            .into(),
+    );
+    ui.on_save_backup({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let measurements = measurements.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                show_errors(&ui, &[i18n::text(&ui, "backups-need-database", &[])]);
+                return;
+            };
+            let path = std::path::PathBuf::from(ui.get_backup_path().trim());
+            let saved = Backup::from_storage(&storage.borrow(), measurements.borrow().clone())
+                .map_err(|e| e.to_string())
+                .and_then(|backup| {
+                    std::fs::write(&path, backup.to_json()).map_err(|e| e.to_string())
+                });
+            match saved {
+                Ok(()) => push_message(
+                    &ui,
+                    Severity::Success,
+                    i18n::text(&ui, "saved-backup", &[&path.display()]),
+                ),
+                Err(e) => push_message(
+                    &ui,
+                    Severity::Error,
+                    i18n::text(&ui, "save-backup-failed", &[&e]),
+                ),
+            }
+        }
+    });
+    ui.on_open_backup({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let measurements = measurements.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                show_errors(&ui, &[i18n::text(&ui, "backups-need-database", &[])]);
+                return;
+            };
+            let path = std::path::PathBuf::from(ui.get_backup_path().trim());
+            let backup = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| Backup::from_json(&text))
+                .and_then(|backup| {
+                    backup
+                        .restore(&mut storage.borrow_mut())
+                        .map(|()| backup)
+                        .map_err(|e| e.to_string())
+                });
+            let backup = match backup {
+                Ok(backup) => backup,
+                Err(e) => {
+                    push_message(
+                        &ui,
+                        Severity::Error,
+                        i18n::text(&ui, "open-backup-failed", &[&e]),
+                    );
+                    return;
                 }
+            };
+
+            for site in ALL_SITES {
+                let value = backup.measurements.get(site);
+                let text = if value > 0.0 {
+                    i18n::reading(&ui, value)
+                } else {
+                    String::new()
+                };
+                set_measurement_input(&ui, site, text.into());
             }
+            *measurements.borrow_mut() = backup.measurements.clone();
+            refresh_profiles(&ui, &storage.borrow(), &profiles);
+            ui.set_selected_profile(0);
+            refresh_history(&ui, &storage.borrow(), None);
+            refresh_chart(&ui, &storage.borrow(), None);
+            push_message(
+                &ui,
+                Severity::Success,
+                i18n::text(
+                    &ui,
+                    "restored-backup",
+                    &[
+                        &backup.profiles.len(),
+                        &backup.sessions.len(),
+                        &path.display(),
+                    ],
+                ),
+            );
         }
-    }
+    });
 
-    "Unclassified"
-}
+    // Import sessions from a spreadsheet into the selected profile, asking
+    // how to read its columns when they aren't all recognised
+    let pending_import: Rc<RefCell<Option<(Profile, import::Table)>>> = Rc::new(RefCell::new(None));
+    let targets: Vec<slint::SharedString> = import::Column::choices()
+        .into_iter()
+        .map(|column| column.label().into())
+        .collect();
+    ui.set_import_targets(slint::ModelRc::new(slint::VecModel::from(targets)));
+    ui.on_import_csv({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        let config = config.clone();
+        let pending_import = pending_import.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                show_errors(&ui, &[i18n::text(&ui, "import-needs-database", &[])]);
+                return;
+            };
+            let Some(profile) = (ui.get_selected_profile() as usize)
+                .checked_sub(1)
+                .and_then(|index| profiles.borrow().get(index).cloned())
+            else {
+                show_errors(&ui, &[i18n::text(&ui, "pick-import-profile", &[])]);
+                return;
+            };
+            let path = std::path::PathBuf::from(ui.get_import_path().trim());
+            let table = match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| import::read_table(&text))
+            {
+                Ok(table) => table,
+                Err(e) => {
+                    push_message(
+                        &ui,
+                        Severity::Error,
+                        i18n::text(&ui, "import-file-failed", &[&path.display(), &e]),
+                    );
+                    return;
+                }
+            };
 
-fn classify_body_fat_female(age: u32, bf: f64) -> &'static str {
-    if bf < 10.0 {
-        return "Extremely Lean (Below Essential Fat)";
-    }
+            let config = config.borrow();
+            if let Some(mapping) = config
+                .import_presets
+                .values()
+                .find_map(|preset| import::preset_mapping(&table.headers, preset))
+            {
+                finish_import(&ui, storage, &profile, &table, &mapping);
+                return;
+            }
+            let mapping = import::auto_mapping(&table.headers, &config.site_aliases);
+            if !import::needs_review(&mapping) {
+                finish_import(&ui, storage, &profile, &table, &mapping);
+                return;
+            }
+            show_import_mapping(&ui, &table, &mapping);
+            *pending_import.borrow_mut() = Some((profile, table));
+        }
+    });
+    ui.on_import_column_changed({
+        let ui_handle = ui_handle.clone();
+        move |index, target| {
+            let ui = ui_handle.upgrade().unwrap();
+            let columns = ui.get_import_columns();
+            if let Some(mut column) = columns.row_data(index as usize) {
+                column.target = target;
+                columns.set_row_data(index as usize, column);
+            }
+        }
+    });
+    ui.on_apply_import_preset({
+        let ui_handle = ui_handle.clone();
+        let config = config.clone();
+        move |name| {
+            let ui = ui_handle.upgrade().unwrap();
+            let config = config.borrow();
+            let Some(preset) = config.import_presets.get(name.as_str()) else {
+                return;
+            };
+            let columns = ui.get_import_columns();
+            for (index, mut column) in columns.iter().enumerate() {
+                if let Some(key) = preset.get(column.header.as_str()) {
+                    column.target = import::Column::from_key(key).label().into();
+                    columns.set_row_data(index, column);
+                }
+            }
+            ui.set_import_preset_name(name);
+        }
+    });
+    ui.on_confirm_import({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let config = config.clone();
+        let pending_import = pending_import.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let (Some(storage), Some((profile, table))) = (&storage, pending_import.take()) else {
+                return;
+            };
+            let mapping: Vec<import::Column> = ui
+                .get_import_columns()
+                .iter()
+                .map(|column| import::Column::from_label(&column.target))
+                .collect();
+            let name = ui.get_import_preset_name().trim().to_string();
+            if !name.is_empty() {
+                let mut config = config.borrow_mut();
+                config
+                    .import_presets
+                    .insert(name, import::preset_from(&table.headers, &mapping));
+                ui.set_import_presets(import_preset_names(&config));
+            }
+            ui.set_import_mapping_open(false);
+            finish_import(&ui, storage, &profile, &table, &mapping);
+        }
+    });
+    ui.on_cancel_import({
+        let ui_handle = ui_handle.clone();
+        let pending_import = pending_import.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            pending_import.take();
+            ui.set_import_mapping_open(false);
+        }
+    });
 
-    let age_groups = [
-        (
-            20,
-            29,
-            [
-                (10.0, 18.0, "Excellent"),
-                (19.0, 23.0, "Good"),
-                (24.0, 29.0, "Average"),
-                (30.0, 35.0, "Below Average"),
-                (36.0, 100.0, "Poor"),
-            ],
-        ),
-        (
-            30,
-            39,
-            [
-                (11.0, 19.0, "Excellent"),
-                (20.0, 24.0, "Good"),
-                (25.0, 30.0, "Average"),
-                (31.0, 36.0, "Below Average"),
-                (37.0, 100.0, "Poor"),
-            ],
-        ),
-        (
-            40,
-            49,
-            [
-                (12.0, 20.0, "Excellent"),
-                (21.0, 25.0, "Good"),
-                (26.0, 31.0, "Average"),
-                (32.0, 37.0, "Below Average"),
-                (38.0, 100.0, "Poor"),
-            ],
-        ),
-        (
-            50,
-            59,
-            [
-                (13.0, 21.0, "Excellent"),
-                (22.0, 26.0, "Good"),
-                (27.0, 32.0, "Average"),
-                (33.0, 38.0, "Below Average"),
-                (39.0, 100.0, "Poor"),
-            ],
-        ),
-        (
-            60,
-            69,
-            [
-                (14.0, 22.0, "Excellent"),
-                (23.0, 27.0, "Good"),
-                (28.0, 33.0, "Average"),
-                (34.0, 39.0, "Below Average"),
-                (40.0, 100.0, "Poor"),
-            ],
-        ),
-    ];
+    // Rebuild a profile's lost history from one of its exported progress pages
+    ui.on_recover_from_report({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                show_errors(&ui, &[i18n::text(&ui, "recovery-needs-database", &[])]);
+                return;
+            };
+            let path = std::path::PathBuf::from(ui.get_report_path().trim());
+            let report = match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| recovery::parse_progress_page(&text))
+            {
+                Ok(report) => report,
+                Err(e) => {
+                    push_message(
+                        &ui,
+                        Severity::Error,
+                        i18n::text(&ui, "read-report-failed", &[&e]),
+                    );
+                    return;
+                }
+            };
+            let Some(index) = profiles
+                .borrow()
+                .iter()
+                .position(|profile| profile.name.eq_ignore_ascii_case(&report.name))
+            else {
+                push_message(
+                    &ui,
+                    Severity::Error,
+                    i18n::text(&ui, "no-profile-named", &[&report.name]),
+                );
+                return;
+            };
+            let profile = profiles.borrow()[index].clone();
+            match recovery::restore(storage, &report, &profile) {
+                Ok(sessions) => {
+                    award_badges(&ui, storage, profile.id);
+                    ui.set_selected_profile(index as i32 + 1);
+                    refresh_history(&ui, &storage.borrow(), profile.id);
+                    refresh_chart(&ui, &storage.borrow(), profile.id);
+                    push_message(
+                        &ui,
+                        Severity::Success,
+                        i18n::text(
+                            &ui,
+                            "recovered",
+                            &[&sessions.len(), &report.sessions.len(), &profile.name],
+                        ),
+                    );
+                }
+                Err(e) => push_message(
+                    &ui,
+                    Severity::Error,
+                    i18n::text(&ui, "save-recovered-failed", &[&e]),
+                ),
+            }
+        }
+    });
 
-    for (lower_age, upper_age, ranges) in age_groups.iter() {
-        if age >= *lower_age && age <= *upper_age {
-            for (low, high, category) in ranges.iter() {
-                if bf >= *low && bf <= *high {
-                    return category;
+    ui.on_add_profile({
+        let ui_handle = ui_handle.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let Some(storage) = &storage else {
+                show_errors(&ui, &[i18n::text(&ui, "profiles-need-database", &[])]);
+                return;
+            };
+            let profile = match new_profile(&ui) {
+                Ok(profile) => profile,
+                Err(errors) => {
+                    show_errors(&ui, &errors);
+                    return;
+                }
+            };
+            let id = match storage.borrow_mut().save_profile(&profile) {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("Could not save profile: {}", e);
+                    return;
                 }
+            };
+            refresh_profiles(&ui, &storage.borrow(), &profiles);
+            let index = profiles.borrow().iter().position(|p| p.id == Some(id));
+            ui.set_new_profile_name("".into());
+            ui.set_new_profile_birth_date("".into());
+            if let Some(index) = index {
+                ui.set_selected_profile(index as i32 + 1);
             }
         }
+    });
+
+    // Optional phone entry page served on the local network
+    #[cfg(feature = "remote-entry")]
+    let remote_entry = start_remote_entry(&ui);
+    #[cfg(not(feature = "remote-entry"))]
+    if std::env::args().any(|arg| arg.starts_with("--remote-entry")) {
+        eprintln!("This build was compiled without remote entry support");
     }
 
-    "Unclassified"
-}
+    // Refresh which sites are optional when the protocol, sex or exclusions change
+    // The calculate button is only enabled once the required fields are filled
+    let calculator_state = Rc::new(Cell::new(CalculatorState::Incomplete));
+    ui.on_inputs_changed({
+        let ui_handle = ui_handle.clone();
+        let measurements = measurements.clone();
+        let calculator_state = calculator_state.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let complete = inputs_complete(&ui, &measurements.borrow());
+            let state = calculator_state
+                .get()
+                .after_edit(complete, ui.get_show_results());
+            set_calculator_state(&ui, &calculator_state, state);
+        }
+    });
 
-fn main() -> Result<(), slint::PlatformError> {
-    let ui = BodyFatCalculator::new()?;
-    let ui_handle = ui.as_weak();
+    ui.on_protocol_changed({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            update_optional_sites(&ui);
+            ui.invoke_inputs_changed();
+        }
+    });
+    update_optional_sites(&ui);
+    ui.invoke_inputs_changed();
 
-    // Store measurements in a shared state
-    let measurements = Rc::new(RefCell::new(Measurements::new()));
+    ui.on_caliper_changed({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            let note = i18n::caliper_note(&ui, &ui.get_selected_caliper());
+            ui.set_caliper_note(note.into());
+            ui.invoke_inputs_changed();
+        }
+    });
 
     // Handle measurement updates
     ui.on_measurement_updated({
+        let ui_handle = ui_handle.clone();
         let measurements = measurements.clone();
+        #[cfg(feature = "remote-entry")]
+        let remote_entry = remote_entry.clone();
         move |site, value| {
-            if let Ok(parsed_value) = value.parse::<f64>() {
+            if let Ok(parsed_value) = i18n::parse_decimal(&value) {
                 measurements
                     .borrow_mut()
                     .set_measurement(&site, parsed_value);
-                println!("Updated {} measurement: {}", site, parsed_value);
+                tracing::debug!("Updated {} measurement: {}", site, parsed_value);
+            }
+            ui_handle.upgrade().unwrap().invoke_inputs_changed();
+            #[cfg(feature = "remote-entry")]
+            if let Some(server) = &remote_entry {
+                server.broadcast(&site, &value);
+            }
+        }
+    });
+
+    ui.on_clear_sitting_log({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            ui.set_sitting_log(slint::ModelRc::default());
+        }
+    });
+
+    // Energy estimates follow the last result as weight and activity change
+    let last_body_fat = Rc::new(Cell::new(None::<f64>));
+    ui.on_energy_inputs_changed({
+        let ui_handle = ui_handle.clone();
+        let last_body_fat = last_body_fat.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            refresh_energy(&ui, last_body_fat.get());
+        }
+    });
+
+    // Start a fresh measurement, keeping the previous readings as ghost text
+    ui.on_clear_measurements({
+        let ui_handle = ui_handle.clone();
+        let measurements = measurements.clone();
+        let last_body_fat = last_body_fat.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            *measurements.borrow_mut() = Measurements::new();
+            for site in ALL_SITES {
+                set_measurement_input(&ui, site, "".into());
+                ui.invoke_measurement_updated(site.into(), "".into());
             }
+            ui.set_show_results(false);
+            set_messages(&ui, Vec::new());
+            last_body_fat.set(None);
+            refresh_energy(&ui, None);
         }
     });
 
     // Handle body fat calculation
-    ui.on_calculate_body_fat({
+    #[cfg(feature = "read-aloud")]
+    let speaker = Rc::new(speech::Speaker::default());
+    let calculate = {
         let ui_handle = ui_handle.clone();
         let measurements = measurements.clone();
+        let storage = storage.clone();
+        let profiles = profiles.clone();
+        let pending = pending.clone();
+        let last_body_fat = last_body_fat.clone();
+        // Range overrides are only set by editing the settings file, so
+        // they can't change while the app is open
+        let site_ranges = config.borrow().site_ranges();
+        #[cfg(feature = "read-aloud")]
+        let speaker = speaker.clone();
         move || {
             let ui = ui_handle.upgrade().unwrap();
+            let _span = tracing::info_span!("calculate").entered();
+            let profile_id = selected_profile_id(&ui, &profiles.borrow());
+            *pending.borrow_mut() = None;
+            ui.set_duplicate_text("".into());
+            show_field_errors(&ui, &[]);
+            ui.set_age_warning("".into());
+            last_body_fat.set(None);
+            refresh_energy(&ui, None);
+
+            // Tape measurements have their own inputs and equation
+            if ui.get_selected_method() == "Tape measure" {
+                if let Some(session) = calculate_navy_body_fat(&ui) {
+                    last_body_fat.set(Some(session.body_fat));
+                    refresh_energy(&ui, Some(session.body_fat));
+                    #[cfg(feature = "read-aloud")]
+                    read_result_aloud(
+                        &ui,
+                        &speaker,
+                        session.body_fat,
+                        &classify_body_fat_with_margin(
+                            session.age,
+                            session.body_fat,
+                            session.is_male,
+                            0.0,
+                        ),
+                    );
+                    let session = Session {
+                        profile_id,
+                        ..session
+                    };
+                    log_calculation(&ui, &session);
+                    record_session(&ui, storage.as_deref(), &pending, session);
+                }
+                return true;
+            }
 
-            // Get current measurements from UI (as fallback) and stored state
+            // Readings typed in the UI take precedence over stored state
             let current_measurements = measurements.borrow().clone();
             let mut final_measurements = Measurements::new();
-            let mut parse_errors = Vec::new();
-
-            // Helper to get measurement from UI or stored state
-            let get_measurement = |ui_value: slint::SharedString,
-                                   stored_value: f64,
-                                   site: &str|
-             -> Result<f64, String> {
-                // Prefer UI value if present, otherwise use stored value
-                if !ui_value.is_empty() {
+            let mut unparsed = Vec::new();
+            let inputs = [
+                ("chest", ui.get_chest_measurement()),
+                ("abdominal", ui.get_abdominal_measurement()),
+                ("thigh", ui.get_thigh_measurement()),
+                ("triceps", ui.get_triceps_measurement()),
+                ("subscapular", ui.get_subscapular_measurement()),
+                ("suprailiac", ui.get_suprailiac_measurement()),
+                ("midaxillary", ui.get_midaxillary_measurement()),
+                ("biceps", ui.get_biceps_measurement()),
+            ];
+            let mut trials = Vec::new();
+            let mut disagreeing = Vec::new();
+            for (site, ui_value) in inputs {
+                if ui_value.is_empty() {
+                    final_measurements.set_measurement(site, current_measurements.get(site));
+                    continue;
+                }
+                // Repeated readings are separated by spaces and combined into one
+                let readings = if ui.get_repeated_readings() {
                     ui_value
-                        .parse::<f64>()
-                        .map_err(|_| format!("{} measurement must be a valid number", site))
-                } else if stored_value > 0.0 {
-                    Ok(stored_value)
+                        .split_whitespace()
+                        .map(i18n::parse_decimal)
+                        .collect()
                 } else {
-                    Err(format!("{} measurement is required", site))
+                    i18n::parse_decimal(&ui_value).map(|val| vec![val])
+                };
+                match readings.ok().and_then(|readings| {
+                    combine_readings(&readings).map(|combined| (readings, combined))
+                }) {
+                    Some((readings, combined)) => {
+                        final_measurements.set_measurement(site, combined);
+                        if readings.len() > 1 {
+                            trials.extend(readings.iter().map(|val| (site.to_string(), *val)));
+                        }
+                        if readings_disagree(&readings) {
+                            disagreeing.push((site, readings));
+                        }
+                    }
+                    None => unparsed.push(site),
+                }
+            }
+
+            // Readings from other calipers can be converted to Harpenden equivalents
+            let device = caliper(&ui.get_selected_caliper());
+            let corrected = match device {
+                Some(device) if ui.get_caliper_correction() => {
+                    for (_, value) in &mut trials {
+                        *value *= device.harpenden_factor;
+                    }
+                    Some(harpenden_equivalent(&final_measurements, device))
                 }
+                _ => None,
             };
+            let adjusted = corrected.as_ref().unwrap_or(&final_measurements);
 
-            // Get all measurements (UI takes precedence over stored state)
-            match get_measurement(
-                ui.get_chest_measurement(),
-                current_measurements.chest,
-                "Chest",
-            ) {
-                Ok(val) => final_measurements.chest = val,
-                Err(e) => parse_errors.push(e),
-            }
+            // The chosen protocol, or the sites the user can't measure, pick the formula
+            let is_male = ui.get_selected_gender() == "Male";
+            let protocol = ui.get_selected_protocol();
+            let automatic = Formula::from_protocol_label(&protocol).is_none();
+            let excluded = formula_exclusions(&ui, &current_measurements);
+            let age = parse_age(&ui.get_age_input());
+            let subject = Subject {
+                age: *age.as_ref().unwrap_or(&0),
+                is_male,
+                protocol: &protocol,
+                excluded: &excluded,
+                ranges: &site_ranges,
+            };
+            let composition = match compute(adjusted, &subject) {
+                Ok(composition) => composition,
+                Err(errors) => {
+                    // A required site holding unreadable text was mistyped, not missed
+                    let errors: Vec<CalculationError> = errors
+                        .into_iter()
+                        .map(|e| match e {
+                            CalculationError::MissedSite(site) if unparsed.contains(&site) => {
+                                CalculationError::InvalidNumber(site)
+                            }
+                            // Text that isn't a number at all gets its own message
+                            CalculationError::InvalidAge => {
+                                age.clone().err().unwrap_or(CalculationError::InvalidAge)
+                            }
+                            e => e,
+                        })
+                        .collect();
+                    show_field_errors(&ui, &errors);
+                    show_errors(
+                        &ui,
+                        &errors
+                            .iter()
+                            .map(|e| i18n::error_text(&ui, e))
+                            .collect::<Vec<_>>(),
+                    );
+                    return true;
+                }
+            };
+            let age = subject.age;
+            let formula = composition.formula;
+            let total_measurement = composition.sum;
+            let body_fat_percentage = composition.body_fat;
 
-            match get_measurement(
-                ui.get_abdominal_measurement(),
-                current_measurements.abdominal,
-                "Abdominal",
-            ) {
-                Ok(val) => final_measurements.abdominal = val,
-                Err(e) => parse_errors.push(e),
+            if let Some(warning) = composition.warnings.iter().find(|w| w.field() == "age") {
+                ui.set_age_warning(i18n::warning_text(&ui, warning).into());
             }
 
-            match get_measurement(
-                ui.get_thigh_measurement(),
-                current_measurements.thigh,
-                "Thigh",
-            ) {
-                Ok(val) => final_measurements.thigh = val,
-                Err(e) => parse_errors.push(e),
+            // Update UI
+            ui.set_result_text(result_text(&ui, body_fat_percentage).into());
+            // Category is flagged when within reading error of a boundary
+            ui.set_category_text(
+                i18n::category_text(&ui, age, is_male, &composition.classification).into(),
+            );
+            let sensitivity = sensitivity_report(formula, total_measurement, age, is_male)
+                .iter()
+                .map(|(site, impact)| {
+                    i18n::text(
+                        &ui,
+                        "site-sensitivity",
+                        &[&i18n::field(&ui, site), &i18n::number(&ui, *impact, 2)],
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("   ");
+            ui.set_sensitivity_text(i18n::text(&ui, "sensitivity", &[&sensitivity]).into());
+            // Thousands of runs take a noticeable moment, so they go to a worker
+            // and the calculation finishes when it reports back
+            let simulating = ui.get_monte_carlo_enabled();
+            if simulating {
+                ui.set_uncertainty_text(i18n::text(&ui, "simulating", &[]).into());
+                let seed = getrandom::u64().unwrap_or(0);
+                let adjusted = adjusted.clone();
+                let ui_handle = ui.as_weak();
+                std::thread::spawn(move || {
+                    let _span =
+                        tracing::info_span!("monte_carlo", runs = MONTE_CARLO_RUNS).entered();
+                    let summary = simulate_body_fat_uncertainty(
+                        formula,
+                        &adjusted,
+                        age,
+                        is_male,
+                        MONTE_CARLO_RUNS,
+                        seed,
+                    );
+                    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                        let text = i18n::text(
+                            &ui,
+                            "uncertainty",
+                            &[
+                                &MONTE_CARLO_RUNS,
+                                &i18n::number(&ui, summary.median, 2),
+                                &i18n::number(&ui, summary.lower, 2),
+                                &i18n::number(&ui, summary.upper, 2),
+                            ],
+                        );
+                        ui.invoke_monte_carlo_finished(text.into())
+                    });
+                });
+            } else {
+                ui.set_uncertainty_text("".into());
             }
-
-            match get_measurement(
-                ui.get_triceps_measurement(),
-                current_measurements.triceps,
-                "Triceps",
-            ) {
-                Ok(val) => final_measurements.triceps = val,
-                Err(e) => parse_errors.push(e),
+            let consistency = check_site_consistency(adjusted, body_fat_percentage, age, is_male);
+            let mut messages = Vec::new();
+            for (site, readings) in &disagreeing {
+                messages.push(message(
+                    Severity::Warning,
+                    i18n::text(
+                        &ui,
+                        "readings-disagree",
+                        &[
+                            &i18n::field(&ui, site),
+                            &readings
+                                .iter()
+                                .map(|val| i18n::reading(&ui, *val))
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            &i18n::number(&ui, READING_SPREAD_TOLERANCE * 100.0, 0),
+                        ],
+                    ),
+                ));
             }
-
-            match get_measurement(
-                ui.get_subscapular_measurement(),
-                current_measurements.subscapular,
-                "Subscapular",
-            ) {
-                Ok(val) => final_measurements.subscapular = val,
-                Err(e) => parse_errors.push(e),
+            if formula == Formula::JacksonPollock7 && !consistency.is_consistent() {
+                let extra_sites = if is_male {
+                    ["triceps", "subscapular", "suprailiac", "midaxillary"]
+                } else {
+                    ["chest", "abdominal", "subscapular", "midaxillary"]
+                };
+                messages.push(message(
+                    Severity::Warning,
+                    i18n::text(
+                        &ui,
+                        "three-site-check",
+                        &[
+                            &i18n::number(&ui, consistency.three_site, 2),
+                            &i18n::number(&ui, consistency.difference.abs(), 1),
+                            &i18n::fields(&ui, extra_sites),
+                        ],
+                    ),
+                ));
             }
 
-            match get_measurement(
-                ui.get_suprailiac_measurement(),
-                current_measurements.suprailiac,
-                "Suprailiac",
-            ) {
-                Ok(val) => final_measurements.suprailiac = val,
-                Err(e) => parse_errors.push(e),
+            let inputs = i18n::text(
+                &ui,
+                if is_male {
+                    "skinfold-inputs-male"
+                } else {
+                    "skinfold-inputs-female"
+                },
+                &[
+                    &formula.name(),
+                    &i18n::number(&ui, total_measurement, 1),
+                    &age,
+                ],
+            );
+            ui.set_result_help(format!("{}\n\n{}", i18n::glossary(&ui, "body_fat"), inputs).into());
+            ui.set_category_help(category_help(&ui, body_fat_percentage, age, is_male).into());
+            if automatic && formula != Formula::ALL[0] {
+                messages.push(message(
+                    Severity::Info,
+                    i18n::text(
+                        &ui,
+                        "formula-fallback",
+                        &[
+                            &i18n::fields(
+                                &ui,
+                                Formula::ALL[0]
+                                    .sites(is_male)
+                                    .iter()
+                                    .copied()
+                                    .filter(|site| excluded.contains(site)),
+                            ),
+                            &formula.name(),
+                            &i18n::fields(&ui, formula.sites(is_male).iter().copied()),
+                        ],
+                    ),
+                ));
             }
-
-            match get_measurement(
-                ui.get_midaxillary_measurement(),
-                current_measurements.midaxillary,
-                "Midaxillary",
-            ) {
-                Ok(val) => final_measurements.midaxillary = val,
-                Err(e) => parse_errors.push(e),
+            if let Some(device) = device.filter(|_| corrected.is_some())
+                && device.harpenden_factor != 1.0
+            {
+                messages.push(message(
+                    Severity::Info,
+                    i18n::text(
+                        &ui,
+                        "caliper-corrected",
+                        &[&device.name, &i18n::reading(&ui, device.harpenden_factor)],
+                    ),
+                ));
             }
+            set_messages(&ui, messages);
+            ui.set_uncertainty_help(i18n::glossary(&ui, "uncertainty").into());
+            ui.set_sensitivity_help(i18n::glossary(&ui, "sensitivity").into());
+            ui.set_show_results(true);
+            last_body_fat.set(Some(body_fat_percentage));
+            refresh_energy(&ui, Some(body_fat_percentage));
+            #[cfg(feature = "read-aloud")]
+            read_result_aloud(
+                &ui,
+                &speaker,
+                body_fat_percentage,
+                &composition.classification,
+            );
 
-            // Parse age
-            let age = match ui.get_age_input().parse::<u32>() {
-                Ok(age) if age > 0 && age < 120 => age,
-                _ => {
-                    parse_errors.push("Age must be a valid number between 1 and 119".to_string());
-                    0
-                }
+            // Save the session with every reading that was taken
+            let readings = ALL_SITES
+                .iter()
+                .map(|site| (site.to_string(), adjusted.get(site)))
+                .filter(|(_, value)| *value > 0.0)
+                .collect();
+            let session = Session {
+                profile_id,
+                recorded_at: chrono::Local::now(),
+                formula: formula.name().to_string(),
+                age,
+                is_male,
+                body_fat: body_fat_percentage,
+                readings,
+                trials,
+                caliper: device.map(|device| device.name.to_string()),
+                tag: None,
+                excluded_sites: excluded.iter().map(|site| site.to_string()).collect(),
             };
+            log_calculation(&ui, &session);
+            record_session(&ui, storage.as_deref(), &pending, session);
+
+            // Update stored measurements with final values
+            set_last_values(&ui, &final_measurements);
+            *measurements.borrow_mut() = final_measurements;
+            !simulating
+        }
+    };
 
-            // Check for errors
-            if !parse_errors.is_empty() {
-                ui.set_result_text(format!("Errors: {}", parse_errors.join(", ")).into());
-                ui.set_category_text("Please fix the errors above".into());
-                ui.set_show_results(true);
+    // Calculations only run from a state where the inputs are complete
+    ui.on_calculate_body_fat({
+        let ui_handle = ui_handle.clone();
+        let calculator_state = calculator_state.clone();
+        move || {
+            let ui = ui_handle.upgrade().unwrap();
+            if matches!(
+                calculator_state.get(),
+                CalculatorState::Incomplete | CalculatorState::Calculating
+            ) {
                 return;
             }
+            set_calculator_state(&ui, &calculator_state, CalculatorState::Calculating);
+            // `false` while the Monte Carlo simulation is still running
+            if calculate() {
+                set_calculator_state(
+                    &ui,
+                    &calculator_state,
+                    CalculatorState::after_calculation(ui.get_show_results()),
+                );
+            }
+        }
+    });
 
-            // Calculate body fat
-            let is_male = ui.get_selected_gender() == "Male";
-            let total_measurement = final_measurements.total();
-            let body_fat_percentage = calculate_body_fat(total_measurement, age, is_male);
-
-            // Classify result
-            let category = if is_male {
-                classify_body_fat_male(age, body_fat_percentage)
-            } else {
-                classify_body_fat_female(age, body_fat_percentage)
-            };
-
-            // Update UI
-            ui.set_result_text(format!("Body Fat Percentage: {:.2}%", body_fat_percentage).into());
-            ui.set_category_text(
-                format!(
-                    "Category for age {} ({}): {}",
-                    age,
-                    if is_male { "Male" } else { "Female" },
-                    category
-                )
-                .into(),
+    // The simulation's worker hands its summary back here, ending the calculation
+    ui.on_monte_carlo_finished({
+        let ui_handle = ui_handle.clone();
+        let calculator_state = calculator_state.clone();
+        move |text| {
+            let ui = ui_handle.upgrade().unwrap();
+            ui.set_uncertainty_text(text);
+            set_calculator_state(
+                &ui,
+                &calculator_state,
+                CalculatorState::after_calculation(ui.get_show_results()),
             );
-            ui.set_show_results(true);
-
-            // Update stored measurements with final values
-            *measurements.borrow_mut() = final_measurements;
         }
     });
 
-    ui.run()
+    // Preferences from the last run; saved again when the window closes
+    apply_config(&ui, &config.borrow(), &profiles.borrow());
+
+    let result = ui.run();
+    if let Some(path) = &config_path
+        && let Err(e) = current_config(&ui, &profiles.borrow(), &config.borrow()).save(path)
+    {
+        eprintln!("Could not save settings to {}: {}", path.display(), e);
+    }
+    result
 }
 
 #[cfg(test)]
@@ -385,22 +3089,15 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_calculate_body_fat_male() {
-        let bf = calculate_body_fat(100.0, 30, true);
-        assert!(bf > 0.0 && bf < 50.0); // Reasonable range
-    }
-
-    #[test]
-    fn test_calculate_body_fat_female() {
-        let bf = calculate_body_fat(100.0, 30, false);
-        assert!(bf > 0.0 && bf < 50.0); // Reasonable range
-    }
-
-    #[test]
-    fn test_measurements_total() {
-        let mut measurements = Measurements::new();
-        measurements.chest = 10.0;
-        measurements.abdominal = 15.0;
-        assert_eq!(measurements.total(), 25.0);
+    fn test_calculator_state_transitions() {
+        use CalculatorState::*;
+        assert_eq!(Incomplete.after_edit(true, false), Ready);
+        assert_eq!(Ready.after_edit(false, false), Incomplete);
+        assert_eq!(Ready.after_edit(true, true), Stale);
+        assert_eq!(Error.after_edit(true, false), Ready);
+        assert_eq!(Stale.after_edit(false, true), Incomplete);
+        assert_eq!(Calculating.after_edit(false, true), Calculating);
+        assert_eq!(CalculatorState::after_calculation(true), Ready);
+        assert_eq!(CalculatorState::after_calculation(false), Error);
     }
 }
@@ -0,0 +1,164 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+/// Name of the manifest written next to a batch export.
+pub const FILE_NAME: &str = "manifest.json";
+
+/// What a batch export holds, so whoever receives the folder can check
+/// nothing is missing or altered.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Manifest {
+    pub app_version: &'static str,
+    /// Database `user_version` the export was made from.
+    pub schema_version: usize,
+    pub generated: DateTime<Local>,
+    pub files: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub bytes: usize,
+    /// Lowercase hex, as `sha256sum` prints it.
+    pub sha256: String,
+}
+
+impl Manifest {
+    pub fn new(schema_version: usize, generated: DateTime<Local>) -> Self {
+        Self {
+            app_version: env!("CARGO_PKG_VERSION"),
+            schema_version,
+            generated,
+            files: Vec::new(),
+        }
+    }
+
+    /// List a file written to the export folder with its contents' checksum.
+    pub fn add(&mut self, name: &str, contents: &[u8]) {
+        self.files.push(Entry {
+            name: name.to_string(),
+            bytes: contents.len(),
+            sha256: sha256(contents)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        });
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// Round constants: the first 32 bits of the fractional parts of the cube
+/// roots of the first 64 primes.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 digest of `data` (FIPS 180-4). Written out here since exports
+/// are the only thing that needs it.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    // Pad with a one bit, zeros, then the length in bits, to whole blocks
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_manifest_lists_files_with_checksums() {
+        let generated = Local.with_ymd_and_hms(2025, 3, 1, 9, 30, 0).unwrap();
+        let mut manifest = Manifest::new(8, generated);
+        manifest.add("empty.pdf", b"");
+        manifest.add("abc.pdf", b"abc");
+        manifest.add(
+            "two-blocks.pdf",
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+        );
+        let hashes: Vec<&str> = manifest
+            .files
+            .iter()
+            .map(|entry| entry.sha256.as_str())
+            .collect();
+        // Test vectors from FIPS 180-2
+        assert_eq!(
+            hashes,
+            [
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ]
+        );
+        assert_eq!(manifest.files[2].bytes, 56);
+
+        let json: serde_json::Value = serde_json::from_str(&manifest.to_json()).unwrap();
+        assert_eq!(json["app_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["schema_version"], 8);
+        assert_eq!(json["files"][1]["name"], "abc.pdf");
+    }
+}
@@ -0,0 +1,181 @@
+//! Just enough of PDF 1.4 to lay out text and lines on A4 pages with the
+//! built-in Helvetica fonts, so reports print without any extra software.
+
+/// A4 in points.
+pub const PAGE_WIDTH: f64 = 595.0;
+pub const PAGE_HEIGHT: f64 = 842.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+/// Drawing commands for one page, with the origin at the bottom left.
+#[derive(Debug, Default, Clone)]
+pub struct Page {
+    content: String,
+}
+
+impl Page {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write one line of text with its baseline starting at `x`, `y`.
+    /// Characters Helvetica cannot show come out as `?`.
+    pub fn text(&mut self, x: f64, y: f64, font: Font, size: f64, text: &str) {
+        let font = match font {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        };
+        self.content.push_str(&format!(
+            "BT /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET\n",
+            font,
+            size,
+            x,
+            y,
+            escape(text)
+        ));
+    }
+
+    /// Stroke straight segments through `points` in an RGB colour with
+    /// components from 0 to 1.
+    pub fn polyline(&mut self, points: &[(f64, f64)], width: f64, colour: (f64, f64, f64)) {
+        let Some(((x, y), rest)) = points.split_first() else {
+            return;
+        };
+        self.content.push_str(&format!(
+            "{:.3} {:.3} {:.3} RG {:.2} w {:.2} {:.2} m",
+            colour.0, colour.1, colour.2, width, x, y
+        ));
+        for (x, y) in rest {
+            self.content.push_str(&format!(" {:.2} {:.2} l", x, y));
+        }
+        self.content.push_str(" S\n");
+    }
+}
+
+/// Escape a string for a PDF literal, encoding it as WinAnsi with octal
+/// escapes so the content stream stays ASCII.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        let byte = match c {
+            '…' => 0x85,
+            '–' => 0x96,
+            '—' => 0x97,
+            '‘' => 0x91,
+            '’' => 0x92,
+            c if (' '..='~').contains(&c) || ('\u{a0}'..='\u{ff}').contains(&c) => c as u8,
+            _ => b'?',
+        };
+        match byte {
+            b'(' | b')' | b'\\' => {
+                escaped.push('\\');
+                escaped.push(byte as char);
+            }
+            0x20..=0x7e => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    escaped
+}
+
+/// The bytes of a PDF file holding `pages` in order.
+pub fn document(pages: &[Page]) -> Vec<u8> {
+    let _span = tracing::info_span!("pdf_document", pages = pages.len()).entered();
+    // Objects 1-4 are the catalogue, page tree and two fonts; each page then
+    // takes a page object and a content stream
+    let page_id = |i: usize| 5 + 2 * i;
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", page_id(i)))
+        .collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            page_id(i) + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            page.content.len(),
+            page.content
+        ));
+    }
+
+    let mut file = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(file.len());
+        file.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+    }
+    let xref = file.len();
+    file.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        file.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    file.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(escape("Sam (coach)"), "Sam \\(coach\\)");
+        assert_eq!(escape("±1.0 a\\b"), "\\2611.0 a\\\\b");
+        assert_eq!(escape("Exporting…"), "Exporting\\205");
+        assert_eq!(escape("体脂"), "??");
+    }
+
+    #[test]
+    fn test_xref_offsets_point_at_objects() {
+        let mut page = Page::new();
+        page.text(56.0, 786.0, Font::Bold, 20.0, "Progress for Sam");
+        page.polyline(&[(0.0, 0.0), (10.0, 10.0)], 1.0, (0.2, 0.6, 0.86));
+        let file = document(&[page, Page::new()]);
+        let text = String::from_utf8(file).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("BT /F2 20.0 Tf 56.00 786.00 Td (Progress for Sam) Tj ET"));
+
+        let start: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|tail| tail.lines().next())
+            .and_then(|offset| offset.parse().ok())
+            .unwrap();
+        assert!(text[start..].starts_with("xref\n0 9\n"));
+        let entries = text[start..].lines().skip(3).take(8);
+        for (i, entry) in entries.enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(text[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// A person whose sessions are tracked separately, e.g. one of a trainer's clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Database id, `None` until saved.
+    pub id: Option<i64>,
+    pub name: String,
+    pub birth_date: NaiveDate,
+    pub is_male: bool,
+    /// Protocol picker label used by default for this profile.
+    pub preferred_formula: String,
+}
+
+impl Profile {
+    /// Age in whole years on `date`.
+    pub fn age_on(&self, date: NaiveDate) -> u32 {
+        let had_birthday =
+            (date.month(), date.day()) >= (self.birth_date.month(), self.birth_date.day());
+        let years = date.year() - self.birth_date.year() - if had_birthday { 0 } else { 1 };
+        years.max(0) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_counts_birthdays() {
+        let profile = Profile {
+            id: None,
+            name: "Sam".to_string(),
+            birth_date: NaiveDate::from_ymd_opt(1990, 6, 15).unwrap(),
+            is_male: false,
+            preferred_formula: "Automatic".to_string(),
+        };
+        let on = |m, d| profile.age_on(NaiveDate::from_ymd_opt(2025, m, d).unwrap());
+        assert_eq!(on(6, 14), 34);
+        assert_eq!(on(6, 15), 35);
+        assert_eq!(on(12, 31), 35);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::storage::Session;
+use body_fat_core::READING_SPREAD_TOLERANCE;
+
+/// How trustworthy a profile's tracking is, out of 100, with the changes that
+/// would raise it most.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataQuality {
+    pub score: u32,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// A change to how sessions are taken that would raise the score, worded
+/// for the user by `i18n::suggestion_text`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Suggestion {
+    /// Sessions are unevenly spaced.
+    RegularSchedule,
+    /// Most sessions have a single reading per site.
+    RepeatReadings,
+    /// Repeated readings differ by this fraction of their mean on average.
+    ConsistentPinch(f64),
+    /// Sessions use a mix of formulas.
+    OneFormula,
+}
+
+/// Score from 0 to 1 for how evenly spaced the sessions are: 1 when every
+/// gap is the same length, falling as gaps vary.
+fn regularity(sessions: &[&Session]) -> f64 {
+    let gaps: Vec<f64> = sessions
+        .windows(2)
+        .map(|pair| (pair[1].recorded_at - pair[0].recorded_at).num_hours() as f64)
+        .collect();
+    let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+    if mean <= 0.0 {
+        return 0.0;
+    }
+    let variance = gaps.iter().map(|gap| (gap - mean).powi(2)).sum::<f64>() / gaps.len() as f64;
+    1.0 / (1.0 + variance.sqrt() / mean)
+}
+
+/// Mean spread of repeated readings at a site, relative to their mean.
+fn trial_spread(sessions: &[&Session]) -> Option<f64> {
+    let mut spreads = Vec::new();
+    for session in sessions {
+        for (site, _) in &session.readings {
+            let trials: Vec<f64> = session
+                .trials
+                .iter()
+                .filter(|(trial_site, _)| trial_site == site)
+                .map(|(_, value)| *value)
+                .collect();
+            if trials.len() < 2 {
+                continue;
+            }
+            let mean = trials.iter().sum::<f64>() / trials.len() as f64;
+            if mean > 0.0 {
+                let max = trials.iter().cloned().fold(f64::MIN, f64::max);
+                let min = trials.iter().cloned().fold(f64::MAX, f64::min);
+                spreads.push((max - min) / mean);
+            }
+        }
+    }
+    (!spreads.is_empty()).then(|| spreads.iter().sum::<f64>() / spreads.len() as f64)
+}
+
+/// Rate a profile's sessions on regular timing, use of repeated readings,
+/// agreement between those readings and sticking to one formula. Needs at
+/// least three sessions to judge timing.
+pub fn assess(sessions: &[Session]) -> Option<DataQuality> {
+    let mut sessions: Vec<&Session> = sessions.iter().collect();
+    if sessions.len() < 3 {
+        return None;
+    }
+    sessions.sort_by_key(|session| session.recorded_at);
+    let count = sessions.len() as f64;
+    let mut suggestions = Vec::new();
+
+    let regularity = regularity(&sessions);
+    if regularity < 0.7 {
+        suggestions.push(Suggestion::RegularSchedule);
+    }
+
+    let repeated = sessions.iter().filter(|s| !s.trials.is_empty()).count() as f64 / count;
+    if repeated < 0.5 {
+        suggestions.push(Suggestion::RepeatReadings);
+    }
+
+    // Sessions without repeated readings give no evidence either way
+    let agreement = match trial_spread(&sessions) {
+        Some(spread) => {
+            if spread > READING_SPREAD_TOLERANCE / 2.0 {
+                suggestions.push(Suggestion::ConsistentPinch(spread));
+            }
+            (1.0 - spread / (2.0 * READING_SPREAD_TOLERANCE)).max(0.0)
+        }
+        None => 0.5,
+    };
+
+    let mut formulas: Vec<&str> = sessions.iter().map(|s| s.formula.as_str()).collect();
+    formulas.sort();
+    let most_common = formulas
+        .chunk_by(|a, b| a == b)
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    let consistency = most_common as f64 / count;
+    if consistency < 0.8 {
+        suggestions.push(Suggestion::OneFormula);
+    }
+
+    let score = 0.3 * regularity + 0.25 * repeated + 0.25 * agreement + 0.2 * consistency;
+    Some(DataQuality {
+        score: (score * 100.0).round() as u32,
+        suggestions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Local, TimeZone};
+
+    fn session(day: i64, formula: &str, trials: Vec<(String, f64)>) -> Session {
+        Session {
+            profile_id: None,
+            recorded_at: Local.with_ymd_and_hms(2025, 1, 1, 8, 0, 0).unwrap() + Duration::days(day),
+            formula: formula.to_string(),
+            age: 34,
+            is_male: true,
+            body_fat: 18.0,
+            readings: vec![("chest".to_string(), 12.0)],
+            trials,
+            caliper: None,
+            tag: None,
+            excluded_sites: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_data_quality_rewards_careful_tracking() {
+        let trials = || vec![("chest".to_string(), 11.8), ("chest".to_string(), 12.2)];
+        let careful: Vec<Session> = (0..4)
+            .map(|week| session(week * 7, "7-site", trials()))
+            .collect();
+        let quality = assess(&careful).unwrap();
+        assert!(quality.score >= 95, "score {}", quality.score);
+        assert!(quality.suggestions.is_empty());
+
+        let patchy = vec![
+            session(0, "7-site", Vec::new()),
+            session(1, "3-site", Vec::new()),
+            session(30, "7-site", Vec::new()),
+            session(31, "US Navy", Vec::new()),
+        ];
+        let quality = assess(&patchy).unwrap();
+        assert!(quality.score < 50, "score {}", quality.score);
+        assert_eq!(
+            quality.suggestions,
+            [
+                Suggestion::RegularSchedule,
+                Suggestion::RepeatReadings,
+                Suggestion::OneFormula
+            ]
+        );
+
+        assert_eq!(assess(&careful[..2]), None);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::profile::Profile;
+use crate::storage::{Session, Storage};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use std::cell::RefCell;
+
+/// Formula recorded for sessions rebuilt from a report, which only keeps the
+/// date and result.
+pub const RECOVERED_FORMULA: &str = "Recovered from report";
+
+/// What could be read back from an exported progress page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredReport {
+    /// Profile name from the page heading, empty when it was missing.
+    pub name: String,
+    /// Date and body fat of each session, oldest first.
+    pub sessions: Vec<(NaiveDate, f64)>,
+}
+
+/// A table cell as written under each comparison setting.
+enum Cell {
+    Value(f64),
+    Change(f64),
+    PercentChange(f64),
+}
+
+fn parse_cell(text: &str) -> Option<Cell> {
+    let text = text.trim();
+    if let Some(change) = text.strip_suffix("% change") {
+        change.trim().parse().ok().map(Cell::PercentChange)
+    } else if let Some(change) = text.strip_suffix("points") {
+        change.trim().parse().ok().map(Cell::Change)
+    } else {
+        text.strip_suffix('%')?.trim().parse().ok().map(Cell::Value)
+    }
+}
+
+/// Page text with every tag replaced by a space, one table row per line.
+fn text_content(page: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for (i, c) in page.char_indices() {
+        match c {
+            '<' => {
+                in_tag = true;
+                if page[i..].starts_with("</tr>") || page[i..].starts_with("</h1>") {
+                    text.push('\n');
+                }
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+/// Read the sessions back out of a progress page written by
+/// `report::progress_page`. Accepts the HTML file itself or the text copied
+/// from a printed or PDF copy of it, as long as each table row stays on one
+/// line. Rows shown as changes are rebuilt from the oldest row, so percent
+/// changes come back to within rounding.
+pub fn parse_progress_page(page: &str) -> Result<RecoveredReport, String> {
+    let text = if page.contains("<tr>") {
+        text_content(page)
+    } else {
+        page.to_string()
+    };
+
+    let mut name = String::new();
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Progress for ") {
+            name = rest.trim().to_string();
+            continue;
+        }
+        let Some((date, cell)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            continue;
+        };
+        if let Some(cell) = parse_cell(cell) {
+            rows.push((date, cell));
+        }
+    }
+    if rows.is_empty() {
+        return Err("No sessions found; expected a progress page exported by this app".to_string());
+    }
+
+    // The table is newest first and its oldest row always shows the value
+    rows.reverse();
+    let mut sessions: Vec<(NaiveDate, f64)> = Vec::new();
+    for (date, cell) in rows {
+        let previous = sessions.last().map(|(_, body_fat)| *body_fat);
+        let body_fat = match (cell, previous) {
+            (Cell::Value(value), _) => value,
+            (Cell::Change(change), Some(previous)) => previous + change,
+            (Cell::PercentChange(change), Some(previous)) => previous * (1.0 + change / 100.0),
+            (_, None) => return Err(format!("The row for {} has no earlier value", date)),
+        };
+        sessions.push((date, body_fat));
+    }
+    Ok(RecoveredReport { name, sessions })
+}
+
+/// Sessions for `profile` rebuilt from the report, skipping days that
+/// `existing` (the profile's stored series) already covers. Recovered
+/// sessions are stamped at midday as the report keeps no time of day.
+pub fn sessions_to_restore(
+    report: &RecoveredReport,
+    profile: &Profile,
+    existing: &[(DateTime<Local>, f64)],
+) -> Vec<Session> {
+    report
+        .sessions
+        .iter()
+        .filter(|(date, _)| !existing.iter().any(|(at, _)| at.date_naive() == *date))
+        .filter_map(|(date, body_fat)| {
+            let recorded_at = Local
+                .from_local_datetime(&date.and_hms_opt(12, 0, 0)?)
+                .earliest()?;
+            Some(Session {
+                profile_id: profile.id,
+                recorded_at,
+                formula: RECOVERED_FORMULA.to_string(),
+                age: profile.age_on(*date),
+                is_male: profile.is_male,
+                body_fat: (body_fat * 100.0).round() / 100.0,
+                readings: Vec::new(),
+                trials: Vec::new(),
+                caliper: None,
+                tag: None,
+                excluded_sites: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+/// Save the report's sessions for `profile` that aren't already stored,
+/// returning what was added. The stored series is read before the database
+/// is borrowed again for writing.
+pub fn restore(
+    storage: &RefCell<Storage>,
+    report: &RecoveredReport,
+    profile: &Profile,
+) -> rusqlite::Result<Vec<Session>> {
+    let existing = storage.borrow().body_fat_series(profile.id, None)?;
+    let sessions = sessions_to_restore(report, profile, &existing);
+    storage.borrow_mut().save_all(&sessions)?;
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Comparison;
+    use crate::report::progress_page;
+
+    #[test]
+    fn test_sessions_recovered_from_progress_page() {
+        let day = |d| Local.with_ymd_and_hms(2025, 1, d, 8, 0, 0).unwrap();
+        let series = [(day(1), 20.0), (day(3), 18.0), (day(5), 19.0)];
+        for comparison in [
+            Comparison::Values,
+            Comparison::Change,
+            Comparison::PercentChange,
+        ] {
+            let page = progress_page("Sam & Jo", &series, day(6), comparison, &[]);
+            let report = parse_progress_page(&page).unwrap();
+            assert_eq!(report.name, "Sam & Jo");
+            assert_eq!(report.sessions.len(), 3);
+            for ((date, body_fat), (at, expected)) in report.sessions.iter().zip(&series) {
+                assert_eq!(*date, at.date_naive());
+                assert!((body_fat - expected).abs() < 0.05, "{:?}", comparison);
+            }
+        }
+
+        let pdf_text = "Progress for Sam\nGenerated 2025-01-06\nDate Body fat\n\
+                        2025-01-03 18.0%\n2025-01-01 20.0%\n";
+        let report = parse_progress_page(pdf_text).unwrap();
+        assert_eq!(report.sessions[0].1, 20.0);
+
+        let profile = Profile {
+            id: Some(4),
+            name: "Sam".to_string(),
+            birth_date: NaiveDate::from_ymd_opt(1990, 1, 2).unwrap(),
+            is_male: false,
+            preferred_formula: "Automatic".to_string(),
+        };
+        let restored = sessions_to_restore(&report, &profile, &[(day(1), 20.0)]);
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].profile_id, Some(4));
+        assert_eq!(restored[0].age, 35);
+        assert_eq!(restored[0].formula, RECOVERED_FORMULA);
+
+        assert!(parse_progress_page("<p>No sessions recorded yet.</p>").is_err());
+        assert!(parse_progress_page("2025-01-03 +1.0 points").is_err());
+    }
+
+    #[test]
+    fn test_restore_saves_only_missing_days() {
+        let storage = RefCell::new(Storage::open_in_memory().unwrap());
+        let mut profile = Profile {
+            id: None,
+            name: "Sam".to_string(),
+            birth_date: NaiveDate::from_ymd_opt(1990, 1, 2).unwrap(),
+            is_male: true,
+            preferred_formula: "Automatic".to_string(),
+        };
+        profile.id = Some(storage.borrow_mut().save_profile(&profile).unwrap());
+        let report =
+            parse_progress_page("Progress for Sam\n2025-01-03 18.0%\n2025-01-01 20.0%\n").unwrap();
+
+        assert_eq!(restore(&storage, &report, &profile).unwrap().len(), 2);
+        assert!(restore(&storage, &report, &profile).unwrap().is_empty());
+        let series = storage.borrow().body_fat_series(profile.id, None).unwrap();
+        assert_eq!(
+            series
+                .iter()
+                .map(|(_, body_fat)| *body_fat)
+                .collect::<Vec<_>>(),
+            [20.0, 18.0]
+        );
+    }
+}
@@ -0,0 +1,661 @@
+use crate::i18n::parse_decimal;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 8787;
+
+/// Connections open at once, counting entry pages listening for updates;
+/// further ones are turned away with a 503.
+const MAX_CONNECTIONS: usize = 16;
+/// Upper bound on the request line plus headers, in bytes.
+const MAX_HEAD_BYTES: u64 = 8 * 1024;
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long an entry page may stall an update before it is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often listening pages are pinged when there are no updates, so
+/// closed ones are noticed and their connections freed.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+const SITES: [&str; 8] = [
+    "chest",
+    "abdominal",
+    "thigh",
+    "triceps",
+    "subscapular",
+    "suprailiac",
+    "midaxillary",
+    "biceps",
+];
+
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Body Fat Calculator - Remote Entry</title>
+<style>
+body { font-family: sans-serif; margin: 0; padding: 16px; background: #f5f5f5; color: #333; }
+h1 { font-size: 20px; color: #2c3e50; }
+label { display: block; margin: 12px 0 4px; font-weight: 600; }
+input { width: 100%; box-sizing: border-box; font-size: 20px; padding: 8px; border: 1px solid #ccc; border-radius: 4px; }
+#status { margin-top: 16px; font-size: 14px; color: #6c757d; }
+</style>
+</head>
+<body>
+<h1>Skinfold Measurements (mm)</h1>
+<form id="form"></form>
+<div id="status">Connecting...</div>
+<script>
+const sites = ["chest", "abdominal", "thigh", "triceps", "subscapular", "suprailiac", "midaxillary", "biceps"];
+const form = document.getElementById("form");
+const status = document.getElementById("status");
+for (const site of sites) {
+  const label = document.createElement("label");
+  label.textContent = site.charAt(0).toUpperCase() + site.slice(1);
+  const input = document.createElement("input");
+  input.id = site;
+  input.inputMode = "decimal";
+  input.placeholder = "Separate repeated readings with spaces";
+  input.addEventListener("input", () => {
+    fetch("/measurement", {
+      method: "POST",
+      body: new URLSearchParams({ site: site, value: input.value }),
+    }).then(r => { status.textContent = r.ok ? "Sent " + site : "Rejected " + site; })
+      .catch(() => { status.textContent = "Desktop app unreachable"; });
+  });
+  label.appendChild(input);
+  form.appendChild(label);
+}
+const events = new EventSource("/events");
+events.onopen = () => { status.textContent = "Connected"; };
+events.onerror = () => { status.textContent = "Reconnecting..."; };
+// Updates echo every page's own posts back, so the field being typed in
+// is left alone rather than overwritten by a late echo
+events.onmessage = (e) => {
+  const [site, value] = e.data.split("=");
+  const input = document.getElementById(site);
+  if (input && input !== document.activeElement && input.value !== value) input.value = value;
+};
+</script>
+</body>
+</html>
+"#;
+
+type UpdateHandler = dyn Fn(String, String) + Send + Sync;
+
+/// How long a pairing code stays valid before it has to be regenerated.
+const PAIRING_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How long a paired device stays authorized without sending anything.
+const SESSION_IDLE_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// One-time pairing token exchanged for a per-device session cookie.
+struct Pairing {
+    token: String,
+    expires_at: Instant,
+    used: bool,
+    /// Session cookie to the time it lapses unless the device is used again.
+    sessions: HashMap<String, Instant>,
+}
+
+impl Pairing {
+    fn new() -> Self {
+        Self {
+            token: random_token(),
+            expires_at: Instant::now() + PAIRING_TTL,
+            used: false,
+            sessions: HashMap::new(),
+        }
+    }
+
+    fn renew(&mut self) {
+        self.token = random_token();
+        self.expires_at = Instant::now() + PAIRING_TTL;
+        self.used = false;
+    }
+
+    fn redeem(&mut self, token: &str) -> Option<String> {
+        if self.used || Instant::now() > self.expires_at || !constant_time_eq(token, &self.token) {
+            return None;
+        }
+        self.used = true;
+        let session = random_token();
+        self.sessions
+            .insert(session.clone(), Instant::now() + SESSION_IDLE_TTL);
+        Some(session)
+    }
+
+    /// Whether `session` belongs to a paired device, extending its lifetime
+    /// if so. Lapsed sessions are forgotten.
+    fn is_authorized(&mut self, session: Option<&str>) -> bool {
+        let now = Instant::now();
+        self.sessions.retain(|_, expires_at| *expires_at > now);
+        let Some(session) = session else {
+            return false;
+        };
+        // Check every entry so the time taken does not hint at a match
+        let mut found = None;
+        for (known, expires_at) in &mut self.sessions {
+            if constant_time_eq(known, session) {
+                found = Some(expires_at);
+            }
+        }
+        match found {
+            Some(expires_at) => {
+                *expires_at = now + SESSION_IDLE_TTL;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+struct Shared {
+    subscribers: Mutex<Vec<TcpStream>>,
+    /// Requests being handled plus subscribers.
+    connections: AtomicUsize,
+    pairing: Mutex<Pairing>,
+    on_update: Box<UpdateHandler>,
+}
+
+/// Minimal LAN HTTP server that serves a phone-friendly entry page and
+/// forwards typed measurements to the desktop app.
+pub struct RemoteEntryServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    /// Events for the writer thread, so the UI thread never blocks on a
+    /// slow phone.
+    events: Sender<String>,
+}
+
+impl RemoteEntryServer {
+    pub fn start(
+        port: u16,
+        on_update: impl Fn(String, String) + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            subscribers: Mutex::new(Vec::new()),
+            connections: AtomicUsize::new(0),
+            pairing: Mutex::new(Pairing::new()),
+            on_update: Box::new(on_update),
+        });
+
+        thread::spawn({
+            let shared = shared.clone();
+            move || {
+                for mut stream in listener.incoming().flatten() {
+                    if shared.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                        shared.connections.fetch_sub(1, Ordering::SeqCst);
+                        let _ = write_response(
+                            &mut stream,
+                            "503 Service Unavailable",
+                            "text/plain",
+                            "Too many connections",
+                        );
+                        continue;
+                    }
+                    let shared = shared.clone();
+                    thread::spawn(move || {
+                        match handle_connection(stream, &shared) {
+                            // Still open, and counted until the writer drops it
+                            Ok(Handled::Subscribed) => return,
+                            Ok(Handled::Closed) => {}
+                            Err(e) => eprintln!("Remote entry connection error: {}", e),
+                        }
+                        shared.connections.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+            }
+        });
+
+        let (events, received) = mpsc::channel::<String>();
+        thread::spawn({
+            let shared = shared.clone();
+            move || {
+                loop {
+                    let event = match received.recv_timeout(HEARTBEAT_INTERVAL) {
+                        Ok(event) => event,
+                        // A comment line, which pages ignore
+                        Err(RecvTimeoutError::Timeout) => ": heartbeat\n\n".to_string(),
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+                    let mut subscribers = shared.subscribers.lock().unwrap();
+                    let before = subscribers.len();
+                    // Writes time out, so a stalled page is dropped rather than
+                    // holding up the others
+                    subscribers.retain_mut(|stream| stream.write_all(event.as_bytes()).is_ok());
+                    shared
+                        .connections
+                        .fetch_sub(before - subscribers.len(), Ordering::SeqCst);
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            shared,
+            events,
+        })
+    }
+
+    /// URL a phone on the same network can open, using the LAN address of this machine.
+    pub fn local_url(&self) -> String {
+        let ip = lan_ip().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        format!("http://{}:{}/", ip, self.addr.port())
+    }
+
+    /// Local URL carrying the current one-time pairing token, for the QR code.
+    pub fn pairing_url(&self) -> String {
+        let token = self.shared.pairing.lock().unwrap().token.clone();
+        format!("{}pair?token={}", self.local_url(), token)
+    }
+
+    /// Invalidate the current pairing token and issue a fresh one.
+    /// Devices that already paired stay authorized until they go idle.
+    pub fn renew_pairing(&self) -> String {
+        self.shared.pairing.lock().unwrap().renew();
+        self.pairing_url()
+    }
+
+    /// Queue a measurement change for every connected entry page.
+    pub fn broadcast(&self, site: &str, value: &str) {
+        let _ = self.events.send(format!("data: {}={}\n\n", site, value));
+    }
+}
+
+/// What became of a connection once its request was answered.
+#[derive(Debug, PartialEq)]
+enum Handled {
+    Closed,
+    /// Kept open in `Shared::subscribers` to stream updates.
+    Subscribed,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    session: Option<String>,
+    body: String,
+}
+
+fn handle_connection(stream: TcpStream, shared: &Shared) -> io::Result<Handled> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = read_request(&mut reader)?;
+    let mut stream = stream;
+
+    if request.method == "GET" && request.path == "/pair" {
+        let token = parse_form(&request.query)
+            .into_iter()
+            .find_map(|(key, value)| (key == "token").then_some(value))
+            .unwrap_or_default();
+        let session = shared.pairing.lock().unwrap().redeem(&token);
+        match session {
+            Some(session) => write!(
+                stream,
+                "HTTP/1.1 303 See Other\r\nLocation: /\r\nSet-Cookie: session={}; Path=/; HttpOnly; SameSite=Strict\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                session
+            )?,
+            None => write_response(
+                &mut stream,
+                "403 Forbidden",
+                "text/plain",
+                "This pairing code has expired or was already used. Generate a new code in the desktop app.",
+            )?,
+        }
+        return Ok(Handled::Closed);
+    }
+
+    let authorized = shared
+        .pairing
+        .lock()
+        .unwrap()
+        .is_authorized(request.session.as_deref());
+    if !authorized {
+        write_response(
+            &mut stream,
+            "403 Forbidden",
+            "text/plain",
+            "This device is not paired. Scan the QR code shown in the desktop app.",
+        )?;
+        return Ok(Handled::Closed);
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => write_response(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            INDEX_HTML,
+        ),
+        ("GET", "/events") => {
+            stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+            )?;
+            shared.subscribers.lock().unwrap().push(stream);
+            return Ok(Handled::Subscribed);
+        }
+        ("POST", "/measurement") => match parse_measurement(&request.body) {
+            Some((site, value)) => {
+                (shared.on_update)(site, value);
+                write_response(&mut stream, "204 No Content", "text/plain", "")
+            }
+            None => write_response(
+                &mut stream,
+                "400 Bad Request",
+                "text/plain",
+                "Invalid measurement",
+            ),
+        },
+        _ => write_response(&mut stream, "404 Not Found", "text/plain", "Not found"),
+    }?;
+    Ok(Handled::Closed)
+}
+
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let mut head = Read::take(&mut *reader, MAX_HEAD_BYTES);
+    let mut request_line = String::new();
+    read_head_line(&mut head, &mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut content_length = 0;
+    let mut session = None;
+    loop {
+        let mut header = String::new();
+        if read_head_line(&mut head, &mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse::<usize>().unwrap_or(0).min(1024);
+        } else if name.eq_ignore_ascii_case("cookie") {
+            session = value
+                .split(';')
+                .find_map(|cookie| cookie.trim().strip_prefix("session="))
+                .map(str::to_string);
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query: query.to_string(),
+        session,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+/// Read one line of the request head, failing once the head outgrows
+/// `MAX_HEAD_BYTES` instead of buffering an endless line.
+fn read_head_line(head: &mut io::Take<impl BufRead>, line: &mut String) -> io::Result<usize> {
+    let read = head.read_line(line)?;
+    if read > 0 && !line.ends_with('\n') && head.limit() == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request headers too large",
+        ));
+    }
+    Ok(read)
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Parse a `site=...&value=...` form body, accepting only known sites and
+/// values that are empty or finite numbers. Repeated readings are separated
+/// by spaces, as in the desktop inputs.
+fn parse_measurement(body: &str) -> Option<(String, String)> {
+    let mut site = None;
+    let mut value = None;
+    for (key, val) in parse_form(body) {
+        match key.as_str() {
+            "site" => site = Some(val),
+            "value" => value = Some(val),
+            _ => {}
+        }
+    }
+
+    let site = site.filter(|s| SITES.contains(&s.as_str()))?;
+    let readings: Vec<&str> = value.as_deref()?.split_whitespace().collect();
+    if !readings
+        .iter()
+        .all(|reading| parse_decimal(reading).is_ok_and(f64::is_finite))
+    {
+        return None;
+    }
+    Some((site, readings.join(" ")))
+}
+
+fn parse_form(body: &str) -> Vec<(String, String)> {
+    body.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Compare secrets without stopping at the first differing byte.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |difference, (x, y)| difference | (x ^ y))
+            == 0
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("system random source unavailable");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn lan_ip() -> Option<IpAddr> {
+    // Connecting a UDP socket sends no packets but picks the outbound interface.
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect(("8.8.8.8", 80)).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_measurement_accepts_known_site() {
+        assert_eq!(
+            parse_measurement("site=chest&value=12.5"),
+            Some(("chest".to_string(), "12.5".to_string()))
+        );
+        assert_eq!(
+            parse_measurement("site=thigh&value="),
+            Some(("thigh".to_string(), String::new()))
+        );
+        assert_eq!(
+            parse_measurement("site=chest&value=12.5+13%20%2012,5"),
+            Some(("chest".to_string(), "12.5 13 12,5".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_measurement_rejects_bad_input() {
+        assert_eq!(parse_measurement("site=elbow&value=3"), None);
+        assert_eq!(parse_measurement("site=chest&value=abc"), None);
+        assert_eq!(parse_measurement("site=chest"), None);
+        assert_eq!(parse_measurement("site=chest&value=NaN"), None);
+        assert_eq!(parse_measurement("site=chest&value=inf"), None);
+        assert_eq!(parse_measurement("site=chest&value=12+abc"), None);
+    }
+
+    #[test]
+    fn test_event_streams_count_toward_connection_limit() {
+        let server = RemoteEntryServer::start(0, |_, _| {}).unwrap();
+        let token = server.shared.pairing.lock().unwrap().token.clone();
+        let session = server
+            .shared
+            .pairing
+            .lock()
+            .unwrap()
+            .redeem(&token)
+            .unwrap();
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, server.addr.port()));
+        let status = |stream: &TcpStream| {
+            let mut status = String::new();
+            BufReader::new(stream).read_line(&mut status).unwrap();
+            status
+        };
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(
+                stream,
+                "GET {} HTTP/1.1\r\nCookie: session={}\r\n\r\n",
+                path, session
+            )
+            .unwrap();
+            let status = status(&stream);
+            (stream, status)
+        };
+        // Turned away before the request is read, so this one sends nothing
+        let turned_away = || status(&TcpStream::connect(addr).unwrap());
+
+        let pages: Vec<TcpStream> = (0..MAX_CONNECTIONS)
+            .map(|_| {
+                let (stream, status) = get("/events");
+                assert!(status.starts_with("HTTP/1.1 200"), "{}", status);
+                stream
+            })
+            .collect();
+        assert!(turned_away().starts_with("HTTP/1.1 503"));
+
+        // A closed page is dropped on the next write, freeing its slot
+        drop(pages);
+        for _ in 0..2 {
+            server.broadcast("chest", "12");
+        }
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.shared.connections.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(server.shared.connections.load(Ordering::SeqCst), 0);
+        assert!(get("/").1.starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn test_pairing_token_is_single_use() {
+        let mut pairing = Pairing::new();
+        let token = pairing.token.clone();
+        assert_eq!(pairing.redeem("wrong"), None);
+        let session = pairing.redeem(&token).expect("first use should pair");
+        assert!(pairing.is_authorized(Some(&session)));
+        assert_eq!(pairing.redeem(&token), None);
+        assert!(!pairing.is_authorized(None));
+    }
+
+    #[test]
+    fn test_expired_pairing_token_is_rejected() {
+        let mut pairing = Pairing::new();
+        pairing.expires_at = Instant::now() - Duration::from_secs(1);
+        let token = pairing.token.clone();
+        assert_eq!(pairing.redeem(&token), None);
+        pairing.renew();
+        let token = pairing.token.clone();
+        assert!(pairing.redeem(&token).is_some());
+    }
+
+    #[test]
+    fn test_read_request_limits_header_size() {
+        let request = "POST /measurement HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc";
+        let parsed = read_request(&mut io::Cursor::new(request)).unwrap();
+        assert_eq!(parsed.path, "/measurement");
+        assert_eq!(parsed.body, "abc");
+
+        let endless = format!("GET / HTTP/1.1\r\nX-Padding: {}", "a".repeat(16 * 1024));
+        let error = read_request(&mut io::Cursor::new(endless)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_idle_session_expires() {
+        let mut pairing = Pairing::new();
+        let token = pairing.token.clone();
+        let session = pairing.redeem(&token).unwrap();
+        assert!(pairing.is_authorized(Some(&session)));
+        pairing
+            .sessions
+            .insert(session.clone(), Instant::now() - Duration::from_secs(1));
+        assert!(!pairing.is_authorized(Some(&session)));
+        assert!(pairing.sessions.is_empty());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("abc", "abc"));
+        assert!(!constant_time_eq("abc", "abd"));
+        assert!(!constant_time_eq("abc", "ab"));
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("12%2E5"), "12.5");
+        assert_eq!(percent_decode("a+b"), "a b");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
@@ -0,0 +1,474 @@
+use crate::achievements::Badge;
+use crate::chart::{self, VIEWBOX};
+use crate::display::Comparison;
+#[cfg(feature = "pdf-reports")]
+use crate::pdf::{self, Font, PAGE_HEIGHT, PAGE_WIDTH, Page};
+use chrono::{DateTime, Local};
+
+/// Escape text for HTML element content and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Export file name used unless the user sets their own.
+pub const DEFAULT_NAME_TEMPLATE: &str = "progress-{profile}-{date}";
+
+/// Name of share card files, which don't follow the user's template since
+/// they sit next to the progress pages.
+pub const SHARE_CARD_NAME_TEMPLATE: &str = "share-{profile}-{date}";
+
+/// Lowercase letters and digits with single dashes between words.
+pub fn slug(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// File name for a client's progress page from a naming template, filling
+/// in `{profile}`, `{date}` and `{method}`, e.g. `progress-sam-2025-01-06.html`
+/// for the default template and the `html` extension.
+pub fn file_name(
+    template: &str,
+    name: &str,
+    method: &str,
+    generated: DateTime<Local>,
+    extension: &str,
+) -> String {
+    let template = match template.trim() {
+        "" => DEFAULT_NAME_TEMPLATE,
+        template => template,
+    };
+    let mut file = template
+        .replace("{profile}", &slug(name))
+        .replace("{date}", &generated.format("%Y-%m-%d").to_string())
+        .replace("{method}", &slug(method))
+        .replace(['/', '\\'], "-");
+    let extension = format!(".{}", extension);
+    if !file.ends_with(&extension) {
+        file.push_str(&extension);
+    }
+    file
+}
+
+/// Add `-<id>` before the extension of every file name shared by more than
+/// one client, so a batch export never overwrites one client with another.
+#[cfg(feature = "pdf-reports")]
+pub fn distinct_file_names(files: &mut [(String, i64)]) {
+    let shared: Vec<bool> = files
+        .iter()
+        .map(|(file, _)| files.iter().filter(|(other, _)| other == file).count() > 1)
+        .collect();
+    for ((file, id), shared) in files.iter_mut().zip(shared) {
+        if shared {
+            let stem_end = file.rfind('.').unwrap_or(file.len());
+            file.insert_str(stem_end, &format!("-{}", id));
+        }
+    }
+}
+
+/// Self-contained HTML page summarising a client's progress, with the trend
+/// drawn as inline SVG so it opens in any browser without network access.
+/// Each session is shown against the one before it as `comparison` asks,
+/// and earned badges are listed under the chart.
+pub fn progress_page(
+    name: &str,
+    series: &[(DateTime<Local>, f64)],
+    generated: DateTime<Local>,
+    comparison: Comparison,
+    badges: &[(&Badge, DateTime<Local>)],
+) -> String {
+    let _span = tracing::info_span!("progress_page", sessions = series.len()).entered();
+    let name = escape(name);
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Progress for {name}</title>\n<style>\n\
+         body {{ font-family: sans-serif; max-width: 720px; margin: 2em auto; color: #2c3e50; }}\n\
+         svg {{ width: 100%; height: 200px; border: 1px solid #ddd; }}\n\
+         table {{ border-collapse: collapse; width: 100%; }}\n\
+         td, th {{ padding: 4px 8px; border-bottom: 1px solid #eee; text-align: left; }}\n\
+         .axis {{ color: #777; font-size: small; display: flex; justify-content: space-between; }}\n\
+         </style>\n</head>\n<body>\n<h1>Progress for {name}</h1>\n<p>Generated {}</p>\n",
+        generated.format("%Y-%m-%d")
+    );
+
+    if let (Some(first), Some(last)) = (series.first(), series.last()) {
+        let since = match comparison {
+            Comparison::Values => format!("{:.1}% on", first.1),
+            _ => format!("{} since", comparison.format(last.1, Some(first.1), 1)),
+        };
+        page.push_str(&format!(
+            "<p>Latest body fat: <strong>{:.1}%</strong> on {} ({} {}).</p>\n",
+            last.1,
+            last.0.format("%Y-%m-%d"),
+            since,
+            first.0.format("%Y-%m-%d")
+        ));
+    } else {
+        page.push_str("<p>No sessions recorded yet.</p>\n");
+    }
+
+    if let Some(trend) = chart::trend_chart(series) {
+        page.push_str(&format!(
+            "<div class=\"axis\"><span>{:.1}%</span></div>\n\
+             <svg viewBox=\"0 0 {VIEWBOX} {VIEWBOX}\" preserveAspectRatio=\"none\">\
+             <path d=\"{}\" fill=\"none\" stroke=\"#3498db\" stroke-width=\"2\" \
+             vector-effect=\"non-scaling-stroke\"/></svg>\n\
+             <div class=\"axis\"><span>{:.1}%</span></div>\n\
+             <div class=\"axis\"><span>{}</span><span>{}</span></div>\n",
+            trend.high,
+            trend.commands,
+            trend.low,
+            trend.start.format("%Y-%m-%d"),
+            trend.end.format("%Y-%m-%d")
+        ));
+    }
+
+    if !badges.is_empty() {
+        page.push_str("<h2>Badges</h2>\n<ul>\n");
+        for (badge, earned_at) in badges {
+            page.push_str(&format!(
+                "<li><strong>{}</strong>: {} (earned {})</li>\n",
+                escape(badge.title),
+                escape(badge.description),
+                earned_at.format("%Y-%m-%d")
+            ));
+        }
+        page.push_str("</ul>\n");
+    }
+
+    page.push_str("<table>\n<tr><th>Date</th><th>Body fat</th></tr>\n");
+    for (i, (at, body_fat)) in series.iter().enumerate().rev() {
+        let previous = i.checked_sub(1).map(|i| series[i].1);
+        page.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            at.format("%Y-%m-%d"),
+            comparison.format(*body_fat, previous, 1)
+        ));
+    }
+    page.push_str("</table>\n</body>\n</html>\n");
+    page
+}
+
+/// Small SVG card for sharing progress outside the app: the latest body fat,
+/// the change since the first session, the trend line and earned badge
+/// titles. Only dates and percentages are included, never measurements.
+pub fn share_card(
+    name: &str,
+    series: &[(DateTime<Local>, f64)],
+    badges: &[(&Badge, DateTime<Local>)],
+) -> String {
+    let _span = tracing::info_span!("share_card").entered();
+    let mut card = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"600\" height=\"315\" \
+         viewBox=\"0 0 600 315\" font-family=\"sans-serif\">\n\
+         <rect width=\"600\" height=\"315\" rx=\"16\" fill=\"#2c3e50\"/>\n\
+         <text x=\"32\" y=\"52\" font-size=\"24\" fill=\"#ecf0f1\">{}</text>\n",
+        escape(name)
+    );
+    match (series.first(), series.last()) {
+        (Some(first), Some(last)) => {
+            card.push_str(&format!(
+                "<text x=\"32\" y=\"118\" font-size=\"56\" font-weight=\"bold\" \
+                 fill=\"#ffffff\">{:.1}%</text>\n\
+                 <text x=\"32\" y=\"150\" font-size=\"16\" fill=\"#bdc3c7\">\
+                 {} since {}</text>\n",
+                last.1,
+                Comparison::Change.format(last.1, Some(first.1), 1),
+                first.0.format("%Y-%m-%d")
+            ));
+        }
+        _ => card.push_str(
+            "<text x=\"32\" y=\"118\" font-size=\"24\" fill=\"#bdc3c7\">\
+             No sessions recorded yet</text>\n",
+        ),
+    }
+    if let Some(trend) = chart::trend_chart(series) {
+        // The trend path is drawn in a VIEWBOX square, squeezed into the
+        // right half of the card
+        card.push_str(&format!(
+            "<svg x=\"320\" y=\"72\" width=\"248\" height=\"96\" \
+             viewBox=\"0 0 {VIEWBOX} {VIEWBOX}\" preserveAspectRatio=\"none\">\
+             <path d=\"{}\" fill=\"none\" stroke=\"#3498db\" stroke-width=\"3\" \
+             vector-effect=\"non-scaling-stroke\"/></svg>\n",
+            trend.commands
+        ));
+    }
+    for (i, (badge, _)) in badges.iter().enumerate() {
+        card.push_str(&format!(
+            "<text x=\"32\" y=\"{}\" font-size=\"16\" fill=\"#f1c40f\">★ {}</text>\n",
+            206 + i * 26,
+            escape(badge.title)
+        ));
+    }
+    card.push_str("</svg>\n");
+    card
+}
+
+/// Margin around every side of a PDF page, in points.
+#[cfg(feature = "pdf-reports")]
+const MARGIN: f64 = 56.0;
+
+/// Pages being filled from the top down, starting a new one when a line
+/// would run into the bottom margin.
+#[cfg(feature = "pdf-reports")]
+struct Layout {
+    pages: Vec<Page>,
+    y: f64,
+}
+
+#[cfg(feature = "pdf-reports")]
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![Page::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// Move down by `height` and return the page to draw on with the new
+    /// baseline.
+    fn advance(&mut self, height: f64) -> (&mut Page, f64) {
+        if self.y - height < MARGIN {
+            self.pages.push(Page::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+        (self.pages.last_mut().unwrap(), self.y)
+    }
+
+    fn line(&mut self, font: Font, size: f64, text: &str) {
+        let (page, y) = self.advance(size * 1.5);
+        page.text(MARGIN, y, font, size, text);
+    }
+}
+
+/// The progress page as a printable A4 PDF: the same summary, trend, badges
+/// and session table, with each table row kept on one line so "Recover from
+/// report" can read the text back.
+#[cfg(feature = "pdf-reports")]
+pub fn progress_pdf(
+    name: &str,
+    series: &[(DateTime<Local>, f64)],
+    generated: DateTime<Local>,
+    comparison: Comparison,
+    badges: &[(&Badge, DateTime<Local>)],
+) -> Vec<u8> {
+    let _span = tracing::info_span!("progress_pdf", sessions = series.len()).entered();
+    let mut layout = Layout::new();
+    layout.line(Font::Bold, 20.0, &format!("Progress for {}", name));
+    layout.line(
+        Font::Regular,
+        11.0,
+        &format!("Generated {}", generated.format("%Y-%m-%d")),
+    );
+
+    if let (Some(first), Some(last)) = (series.first(), series.last()) {
+        let since = match comparison {
+            Comparison::Values => format!("{:.1}% on", first.1),
+            _ => format!("{} since", comparison.format(last.1, Some(first.1), 1)),
+        };
+        layout.line(
+            Font::Regular,
+            11.0,
+            &format!(
+                "Latest body fat: {:.1}% on {} ({} {}).",
+                last.1,
+                last.0.format("%Y-%m-%d"),
+                since,
+                first.0.format("%Y-%m-%d")
+            ),
+        );
+    } else {
+        layout.line(Font::Regular, 11.0, "No sessions recorded yet.");
+    }
+
+    if let Some(trend) = chart::trend_chart(series) {
+        const HEIGHT: f64 = 160.0;
+        let width = PAGE_WIDTH - 2.0 * MARGIN;
+        layout.line(Font::Regular, 9.0, &format!("{:.1}%", trend.high));
+        let (page, bottom) = layout.advance(HEIGHT + 4.0);
+        let (left, right, top) = (MARGIN, MARGIN + width, bottom + HEIGHT);
+        page.polyline(
+            &[
+                (left, bottom),
+                (right, bottom),
+                (right, top),
+                (left, top),
+                (left, bottom),
+            ],
+            0.5,
+            (0.87, 0.87, 0.87),
+        );
+        let span = (trend.end - trend.start).num_seconds().max(1) as f64;
+        let range = (trend.high - trend.low).max(f64::EPSILON);
+        let points: Vec<(f64, f64)> = series
+            .iter()
+            .map(|(at, body_fat)| {
+                (
+                    left + width * (*at - trend.start).num_seconds() as f64 / span,
+                    bottom + HEIGHT * (body_fat - trend.low) / range,
+                )
+            })
+            .collect();
+        page.polyline(&points, 2.0, (0.2, 0.6, 0.86));
+        layout.line(Font::Regular, 9.0, &format!("{:.1}%", trend.low));
+        let end = trend.end.format("%Y-%m-%d").to_string();
+        let (page, y) = layout.advance(13.5);
+        page.text(
+            MARGIN,
+            y,
+            Font::Regular,
+            9.0,
+            &trend.start.format("%Y-%m-%d").to_string(),
+        );
+        // Dates are ten digits wide; Helvetica digits are 0.556 em
+        page.text(
+            MARGIN + width - 10.0 * 0.556 * 9.0,
+            y,
+            Font::Regular,
+            9.0,
+            &end,
+        );
+    }
+
+    if !badges.is_empty() {
+        layout.line(Font::Bold, 14.0, "Badges");
+        for (badge, earned_at) in badges {
+            layout.line(
+                Font::Regular,
+                11.0,
+                &format!(
+                    "{}: {} (earned {})",
+                    badge.title,
+                    badge.description,
+                    earned_at.format("%Y-%m-%d")
+                ),
+            );
+        }
+    }
+
+    let (page, y) = layout.advance(21.0);
+    page.text(MARGIN, y, Font::Bold, 11.0, "Date");
+    page.text(MARGIN + 120.0, y, Font::Bold, 11.0, "Body fat");
+    for (i, (at, body_fat)) in series.iter().enumerate().rev() {
+        let previous = i.checked_sub(1).map(|i| series[i].1);
+        let (page, y) = layout.advance(16.5);
+        page.text(
+            MARGIN,
+            y,
+            Font::Regular,
+            11.0,
+            &at.format("%Y-%m-%d").to_string(),
+        );
+        page.text(
+            MARGIN + 120.0,
+            y,
+            Font::Regular,
+            11.0,
+            &comparison.format(*body_fat, previous, 1),
+        );
+    }
+    pdf::document(&layout.pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_progress_page_is_self_contained() {
+        let day = |d| Local.with_ymd_and_hms(2025, 1, d, 8, 0, 0).unwrap();
+        let page = progress_page(
+            "Sam <Coach's client>",
+            &[(day(1), 20.0), (day(3), 18.0), (day(5), 19.0)],
+            day(6),
+            Comparison::Change,
+            &[(&crate::achievements::BADGES[1], day(3))],
+        );
+        assert!(page.contains("<li><strong>First point down</strong>"));
+        assert!(page.contains("(earned 2025-01-03)"));
+        assert!(page.contains("Progress for Sam &lt;Coach's client&gt;"));
+        assert!(page.contains("<path d=\"M 0.00 25.00 L 50.00 75.00 L 100.00 50.00\""));
+        assert!(page.contains("(-1.0 points since 2025-01-01)"));
+        assert!(page.contains("<td>+1.0 points</td>"));
+        assert!(page.contains("<td>20.0%</td>"));
+        assert!(!page.contains("http"));
+        assert!(!page.contains("src="));
+        assert_eq!(
+            file_name(DEFAULT_NAME_TEMPLATE, "Sam Lee", "7-site", day(6), "html"),
+            "progress-sam-lee-2025-01-06.html"
+        );
+        assert_eq!(
+            file_name(
+                "{date}/{profile} {method}.html",
+                "Sam Lee",
+                "Jackson & Pollock 7-site",
+                day(6),
+                "html"
+            ),
+            "2025-01-06-sam-lee jackson-pollock-7-site.html"
+        );
+        assert_eq!(
+            file_name(" ", "Sam", "", day(6), "pdf"),
+            "progress-sam-2025-01-06.pdf"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "pdf-reports")]
+    fn test_progress_pdf_paginates_the_session_table() {
+        let day = |d| Local.with_ymd_and_hms(2025, 1, 1, 8, 0, 0).unwrap() + chrono::Days::new(d);
+        let series: Vec<_> = (0..80).map(|d| (day(d), 20.0 - d as f64 * 0.05)).collect();
+        let file = progress_pdf("Sam (coach)", &series, day(80), Comparison::Change, &[]);
+        let text = String::from_utf8(file).unwrap();
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("(Progress for Sam \\(coach\\)) Tj"));
+        assert!(text.contains("(2025-03-21) Tj"));
+        assert!(text.contains("(-0.1 points) Tj"));
+        assert!(text.contains("(20.0%) Tj"));
+        assert!(text.contains("/Count 3"));
+    }
+
+    #[test]
+    fn test_share_card_shows_latest_result_and_badges() {
+        let day = |d| Local.with_ymd_and_hms(2025, 1, d, 8, 0, 0).unwrap();
+        let series = [(day(1), 20.0), (day(3), 18.0), (day(5), 18.5)];
+        let card = share_card(
+            "Sam & Alex",
+            &series,
+            &[(&crate::achievements::BADGES[1], day(3))],
+        );
+        assert!(card.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(card.contains(">Sam &amp; Alex</text>"));
+        assert!(card.contains(">18.5%</text>"));
+        assert!(card.contains(">-1.5 points since 2025-01-01</text>"));
+        let trend = chart::trend_chart(&series).unwrap();
+        assert!(card.contains(&format!("<path d=\"{}\"", trend.commands)));
+        assert!(card.contains(">★ First point down</text>"));
+        assert!(card.trim_end().ends_with("</svg>"));
+
+        let empty = share_card("Sam", &[], &[]);
+        assert!(empty.contains("No sessions recorded yet"));
+        assert!(!empty.contains("<path"));
+    }
+
+    #[test]
+    #[cfg(feature = "pdf-reports")]
+    fn test_shared_file_names_get_the_profile_id() {
+        let mut files = vec![
+            ("progress-sam-2025-01-06.pdf".to_string(), 1),
+            ("progress-alex-2025-01-06.pdf".to_string(), 2),
+            ("progress-sam-2025-01-06.pdf".to_string(), 3),
+        ];
+        distinct_file_names(&mut files);
+        assert_eq!(files[0].0, "progress-sam-2025-01-06-1.pdf");
+        assert_eq!(files[1].0, "progress-alex-2025-01-06.pdf");
+        assert_eq!(files[2].0, "progress-sam-2025-01-06-3.pdf");
+    }
+}
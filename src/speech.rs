@@ -0,0 +1,21 @@
+use std::cell::RefCell;
+
+/// The platform's text-to-speech voice, connected the first time something
+/// is read out so builds with the feature start as quickly as those without.
+#[derive(Default)]
+pub struct Speaker {
+    tts: RefCell<Option<tts::Tts>>,
+}
+
+impl Speaker {
+    /// Speak `text`, cutting off anything still being read.
+    pub fn say(&self, text: &str) -> Result<(), String> {
+        let mut tts = self.tts.borrow_mut();
+        let tts = match &mut *tts {
+            Some(tts) => tts,
+            None => tts.insert(tts::Tts::default().map_err(|e| e.to_string())?),
+        };
+        tts.speak(text, true).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
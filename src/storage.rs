@@ -0,0 +1,1089 @@
+use crate::goal::Goal;
+use crate::profile::Profile;
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use rusqlite::{Connection, OpenFlags, params};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Schema changes in order; the database's `user_version` counts how many
+/// have been applied.
+const MIGRATIONS: [&str; 9] = [
+    "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    recorded_at TEXT NOT NULL,
+    formula TEXT NOT NULL,
+    age INTEGER NOT NULL,
+    is_male INTEGER NOT NULL,
+    body_fat REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS readings (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    site TEXT NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (session_id, site)
+);
+",
+    "
+CREATE TABLE profiles (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    birth_date TEXT NOT NULL,
+    is_male INTEGER NOT NULL,
+    preferred_formula TEXT NOT NULL
+);
+ALTER TABLE sessions ADD COLUMN profile_id INTEGER REFERENCES profiles(id) ON DELETE CASCADE;
+CREATE INDEX sessions_by_profile ON sessions(profile_id, recorded_at);
+",
+    "
+CREATE TABLE trials (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    site TEXT NOT NULL,
+    value REAL NOT NULL
+);
+CREATE INDEX trials_by_session ON trials(session_id);
+",
+    "
+CREATE TABLE goals (
+    profile_id INTEGER PRIMARY KEY REFERENCES profiles(id) ON DELETE CASCADE,
+    body_fat REAL NOT NULL,
+    target_date TEXT NOT NULL
+);
+",
+    "
+ALTER TABLE sessions ADD COLUMN caliper TEXT;
+",
+    "
+CREATE TABLE badges (
+    profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+    badge TEXT NOT NULL,
+    earned_at TEXT NOT NULL,
+    PRIMARY KEY (profile_id, badge)
+);
+",
+    "
+ALTER TABLE sessions ADD COLUMN tag TEXT;
+",
+    "
+ALTER TABLE sessions ADD COLUMN excluded_sites TEXT NOT NULL DEFAULT '';
+",
+    "
+-- recorded_at keeps the local offset, so its text sorts out of order when
+-- the offset changes (e.g. the hour repeated at the end of summer time).
+-- Sessions are ordered by seconds since the epoch instead.
+ALTER TABLE sessions ADD COLUMN recorded_unix INTEGER NOT NULL DEFAULT 0;
+UPDATE sessions SET recorded_unix = CAST(strftime('%s', recorded_at) AS INTEGER);
+DROP INDEX sessions_by_profile;
+CREATE INDEX sessions_by_profile ON sessions(profile_id, recorded_unix);
+",
+];
+
+/// One saved calculation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Profile the session belongs to, `None` for anonymous quick calculations.
+    pub profile_id: Option<i64>,
+    pub recorded_at: DateTime<Local>,
+    /// Display name of the formula used.
+    pub formula: String,
+    pub age: u32,
+    pub is_male: bool,
+    pub body_fat: f64,
+    /// Readings by site key: skinfolds in mm, tape measurements in cm.
+    pub readings: Vec<(String, f64)>,
+    /// Every reading at sites measured more than once, in the order taken.
+    /// `readings` holds their combined value.
+    pub trials: Vec<(String, f64)>,
+    /// Skinfold caliper used, when recorded. Readings are already converted
+    /// to the Harpenden equivalent if the caliper correction was on.
+    #[serde(default)]
+    pub caliper: Option<String>,
+    /// Free-text label for grouping sessions, e.g. "pre-season".
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Sites that couldn't be measured, e.g. under a cast. `formula` is the
+    /// one picked to do without them.
+    #[serde(default)]
+    pub excluded_sites: Vec<String>,
+}
+
+/// A change applied to several saved sessions at once.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkEdit {
+    Delete,
+    /// Move sessions in time, e.g. to fix readings saved with the wrong timezone.
+    ShiftDates(Duration),
+    /// Replace the formula name shown for the sessions.
+    Relabel(String),
+    /// Set the sessions' tag, or clear it with `None`.
+    Retag(Option<String>),
+}
+
+/// Columns read by `session_from_row`, in order.
+const SESSION_COLUMNS: &str =
+    "id, recorded_at, formula, age, is_male, body_fat, profile_id, caliper, tag, excluded_sites";
+
+/// Database `user_version` once every migration has run.
+#[cfg(feature = "pdf-reports")]
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+/// How long a write waits for another connection, e.g. a second window or
+/// the remote entry thread, to finish before giving up.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Calculation history kept in a SQLite database.
+pub struct Storage {
+    conn: Connection,
+}
+
+impl Storage {
+    /// Open the database in WAL mode, so readers don't block the writer and
+    /// writes from other connections wait their turn instead of failing.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Self::init(conn)
+    }
+
+    /// Open a damaged database read-only and unmigrated, to read back
+    /// whatever still can be.
+    pub fn open_damaged(path: &Path) -> rusqlite::Result<Self> {
+        Ok(Self {
+            conn: Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (applied, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", applied + 1)?;
+            tx.commit()?;
+        }
+        Ok(Self { conn })
+    }
+
+    /// What SQLite's integrity check finds wrong with the database, empty
+    /// when it is sound.
+    pub fn integrity_problems(&self) -> rusqlite::Result<Vec<String>> {
+        let _span = tracing::info_span!("integrity_check").entered();
+        let mut statement = self.conn.prepare("PRAGMA integrity_check")?;
+        let problems = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(if problems == ["ok"] {
+            Vec::new()
+        } else {
+            problems
+        })
+    }
+
+    /// `history.sqlite3` in the platform's per-user data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("com", "gregorycarnegie", "body_fat_calculator")
+            .map(|dirs| dirs.data_dir().join("history.sqlite3"))
+    }
+
+    pub fn save(&mut self, session: &Session) -> rusqlite::Result<i64> {
+        let _span = tracing::info_span!("save_session").entered();
+        let tx = self.conn.transaction()?;
+        let id = insert_session(&tx, None, session)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Replace a saved session's details and readings.
+    pub fn update(&mut self, id: i64, session: &Session) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE sessions
+             SET profile_id = ?1, recorded_at = ?2, formula = ?3, age = ?4, is_male = ?5,
+                 body_fat = ?6, caliper = ?7, recorded_unix = ?8
+             WHERE id = ?9",
+            params![
+                session.profile_id,
+                session.recorded_at.to_rfc3339(),
+                session.formula,
+                session.age,
+                session.is_male,
+                session.body_fat,
+                session.caliper,
+                session.recorded_at.timestamp(),
+                id
+            ],
+        )?;
+        tx.execute("DELETE FROM readings WHERE session_id = ?1", [id])?;
+        tx.execute("DELETE FROM trials WHERE session_id = ?1", [id])?;
+        insert_readings(&tx, id, session)?;
+        tx.commit()
+    }
+
+    /// Id and time of the profile's session closest to `at`, if one was
+    /// recorded within `window` of it.
+    pub fn find_near(
+        &self,
+        profile_id: Option<i64>,
+        at: DateTime<Local>,
+        window: Duration,
+    ) -> rusqlite::Result<Option<(i64, DateTime<Local>)>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT id, recorded_at FROM sessions WHERE profile_id IS ?1")?;
+        let rows = statement.query_map([profile_id], |row| {
+            Ok((row.get(0)?, parse_recorded_at(row.get(1)?)?))
+        })?;
+
+        let mut nearest: Option<(i64, DateTime<Local>)> = None;
+        for row in rows {
+            let (id, recorded_at) = row?;
+            let distance = (recorded_at - at).abs();
+            if distance <= window && nearest.is_none_or(|(_, best)| distance < (best - at).abs()) {
+                nearest = Some((id, recorded_at));
+            }
+        }
+        Ok(nearest)
+    }
+
+    /// Save many sessions in a single transaction.
+    pub fn save_all(&mut self, sessions: &[Session]) -> rusqlite::Result<()> {
+        let _span = tracing::info_span!("save_sessions", count = sessions.len()).entered();
+        let tx = self.conn.transaction()?;
+        for session in sessions {
+            insert_session(&tx, None, session)?;
+        }
+        tx.commit()
+    }
+
+    /// Apply `edit` to the sessions in one transaction, returning them as
+    /// they were beforehand so the edit can be undone with `restore`.
+    pub fn bulk_edit(
+        &mut self,
+        ids: &[i64],
+        edit: &BulkEdit,
+    ) -> rusqlite::Result<Vec<(i64, Session)>> {
+        let _span = tracing::info_span!("bulk_edit", count = ids.len()).entered();
+        let tx = self.conn.transaction()?;
+        let mut before = Vec::new();
+        for &id in ids {
+            let Some(session) = load_session(&tx, id)? else {
+                continue;
+            };
+            match edit {
+                BulkEdit::Delete => tx.execute("DELETE FROM sessions WHERE id = ?1", [id])?,
+                BulkEdit::ShiftDates(by) => {
+                    let recorded_at = session.recorded_at + *by;
+                    tx.execute(
+                        "UPDATE sessions SET recorded_at = ?1, recorded_unix = ?2 WHERE id = ?3",
+                        params![recorded_at.to_rfc3339(), recorded_at.timestamp(), id],
+                    )?
+                }
+                BulkEdit::Relabel(formula) => tx.execute(
+                    "UPDATE sessions SET formula = ?1 WHERE id = ?2",
+                    params![formula, id],
+                )?,
+                BulkEdit::Retag(tag) => tx.execute(
+                    "UPDATE sessions SET tag = ?1 WHERE id = ?2",
+                    params![tag, id],
+                )?,
+            };
+            before.push((id, session));
+        }
+        tx.commit()?;
+        Ok(before)
+    }
+
+    /// Replace sessions in one transaction, returning them as they were
+    /// beforehand so the change can be undone with `restore`.
+    pub fn rewrite(
+        &mut self,
+        sessions: &[(i64, Session)],
+    ) -> rusqlite::Result<Vec<(i64, Session)>> {
+        let tx = self.conn.transaction()?;
+        let mut before = Vec::new();
+        for (id, session) in sessions {
+            let Some(previous) = load_session(&tx, *id)? else {
+                continue;
+            };
+            tx.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+            insert_session(&tx, Some(*id), session)?;
+            before.push((*id, previous));
+        }
+        tx.commit()?;
+        Ok(before)
+    }
+
+    /// Put sessions back under their original ids, undoing a `bulk_edit`.
+    pub fn restore(&mut self, sessions: &[(i64, Session)]) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        for (id, session) in sessions {
+            tx.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+            insert_session(&tx, Some(*id), session)?;
+        }
+        tx.commit()
+    }
+
+    pub fn save_profile(&mut self, profile: &Profile) -> rusqlite::Result<i64> {
+        self.conn.execute(
+            "INSERT INTO profiles (name, birth_date, is_male, preferred_formula)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                profile.name,
+                profile.birth_date.to_string(),
+                profile.is_male,
+                profile.preferred_formula
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Every profile, sorted by name.
+    pub fn profiles(&self) -> rusqlite::Result<Vec<Profile>> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, name, birth_date, is_male, preferred_formula
+             FROM profiles ORDER BY name COLLATE NOCASE, id",
+        )?;
+        statement
+            .query_map([], |row| {
+                let birth_date: String = row.get(2)?;
+                Ok(Profile {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    birth_date: birth_date.parse::<NaiveDate>().map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            2,
+                            rusqlite::types::Type::Text,
+                            Box::new(e),
+                        )
+                    })?,
+                    is_male: row.get(3)?,
+                    preferred_formula: row.get(4)?,
+                })
+            })?
+            .collect()
+    }
+
+    /// Set the profile's goal, replacing any earlier one.
+    pub fn save_goal(&mut self, profile_id: i64, goal: &Goal) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO goals (profile_id, body_fat, target_date) VALUES (?1, ?2, ?3)
+             ON CONFLICT(profile_id) DO UPDATE
+             SET body_fat = excluded.body_fat, target_date = excluded.target_date",
+            params![profile_id, goal.body_fat, goal.target_date.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn goal(&self, profile_id: i64) -> rusqlite::Result<Option<Goal>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT body_fat, target_date FROM goals WHERE profile_id = ?1")?;
+        let Some(row) = statement
+            .query_map([profile_id], |row| {
+                let target_date: String = row.get(1)?;
+                Ok(Goal {
+                    body_fat: row.get(0)?,
+                    target_date: target_date.parse::<NaiveDate>().map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            1,
+                            rusqlite::types::Type::Text,
+                            Box::new(e),
+                        )
+                    })?,
+                })
+            })?
+            .next()
+        else {
+            return Ok(None);
+        };
+        row.map(Some)
+    }
+
+    /// Record that the profile has earned `badges`, keeping the first date
+    /// each was earned. Returns the ones that are new.
+    pub fn award_badges(
+        &mut self,
+        profile_id: i64,
+        badges: &[&str],
+        earned_at: DateTime<Local>,
+    ) -> rusqlite::Result<Vec<String>> {
+        let tx = self.conn.transaction()?;
+        let mut new = Vec::new();
+        for badge in badges {
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO badges (profile_id, badge, earned_at) VALUES (?1, ?2, ?3)",
+                params![profile_id, badge, earned_at.to_rfc3339()],
+            )?;
+            if inserted > 0 {
+                new.push(badge.to_string());
+            }
+        }
+        tx.commit()?;
+        Ok(new)
+    }
+
+    /// The profile's badges with when they were earned, oldest first.
+    pub fn badges(&self, profile_id: i64) -> rusqlite::Result<Vec<(String, DateTime<Local>)>> {
+        let mut statement = self.conn.prepare(
+            "SELECT badge, earned_at FROM badges WHERE profile_id = ?1 ORDER BY earned_at, badge",
+        )?;
+        let rows = statement.query_map([profile_id], |row| {
+            Ok((row.get(0)?, parse_recorded_at(row.get(1)?)?))
+        })?;
+        rows.collect()
+    }
+
+    /// Every profile's goal, by profile id.
+    pub fn goals(&self) -> rusqlite::Result<Vec<(i64, Goal)>> {
+        let mut goals = Vec::new();
+        for id in self.profiles()?.iter().filter_map(|profile| profile.id) {
+            if let Some(goal) = self.goal(id)? {
+                goals.push((id, goal));
+            }
+        }
+        Ok(goals)
+    }
+
+    /// Every profile's badges with when they were earned, by profile id.
+    pub fn all_badges(&self) -> rusqlite::Result<Vec<(i64, String, DateTime<Local>)>> {
+        let mut badges = Vec::new();
+        for id in self.profiles()?.iter().filter_map(|profile| profile.id) {
+            badges.extend(
+                self.badges(id)?
+                    .into_iter()
+                    .map(|(badge, earned_at)| (id, badge, earned_at)),
+            );
+        }
+        Ok(badges)
+    }
+
+    /// The profile's last `limit` readings at `site` with when they were
+    /// taken, newest first.
+    pub fn site_history(
+        &self,
+        profile_id: Option<i64>,
+        site: &str,
+        limit: usize,
+    ) -> rusqlite::Result<Vec<(DateTime<Local>, f64)>> {
+        let mut statement = self.conn.prepare_cached(
+            "SELECT sessions.recorded_at, readings.value
+             FROM readings JOIN sessions ON sessions.id = readings.session_id
+             WHERE sessions.profile_id IS ?1 AND readings.site = ?2
+             ORDER BY sessions.recorded_unix DESC, sessions.id DESC LIMIT ?3",
+        )?;
+        statement
+            .query_map(params![profile_id, site, limit as i64], |row| {
+                Ok((parse_recorded_at(row.get(0)?)?, row.get(1)?))
+            })?
+            .collect()
+    }
+
+    /// Ids of every saved session.
+    pub fn session_ids(&self) -> rusqlite::Result<Vec<i64>> {
+        let mut statement = self.conn.prepare("SELECT id FROM sessions ORDER BY id")?;
+        statement.query_map([], |row| row.get(0))?.collect()
+    }
+
+    /// A saved session with its readings, or `None` if it was deleted.
+    pub fn session(&self, id: i64) -> rusqlite::Result<Option<Session>> {
+        load_session(&self.conn, id)
+    }
+
+    /// Every saved session, anonymous or not, oldest first.
+    pub fn all_sessions(&self) -> rusqlite::Result<Vec<Session>> {
+        let _span = tracing::info_span!("all_sessions").entered();
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS} FROM sessions ORDER BY recorded_unix, id"
+        ))?;
+        let rows = statement.query_map([], session_from_row)?;
+
+        let mut sessions = Vec::new();
+        for row in rows {
+            let (id, mut session) = row?;
+            session.readings = load_site_values(&self.conn, "readings", id)?;
+            session.trials = load_site_values(&self.conn, "trials", id)?;
+            sessions.push(session);
+        }
+        Ok(sessions)
+    }
+
+    /// Replace everything stored, e.g. when restoring a backup. Profiles keep
+    /// their ids so sessions, goals and badges stay with them.
+    pub fn replace_all(
+        &mut self,
+        profiles: &[Profile],
+        goals: &[(i64, Goal)],
+        badges: &[(i64, String, DateTime<Local>)],
+        sessions: &[Session],
+    ) -> rusqlite::Result<()> {
+        let _span = tracing::info_span!("replace_all", sessions = sessions.len()).entered();
+        let tx = self.conn.transaction()?;
+        tx.execute_batch("DELETE FROM sessions; DELETE FROM goals; DELETE FROM profiles;")?;
+        for profile in profiles {
+            tx.execute(
+                "INSERT INTO profiles (id, name, birth_date, is_male, preferred_formula)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    profile.id,
+                    profile.name,
+                    profile.birth_date.to_string(),
+                    profile.is_male,
+                    profile.preferred_formula
+                ],
+            )?;
+        }
+        for (profile_id, goal) in goals {
+            tx.execute(
+                "INSERT INTO goals (profile_id, body_fat, target_date) VALUES (?1, ?2, ?3)",
+                params![profile_id, goal.body_fat, goal.target_date.to_string()],
+            )?;
+        }
+        // Deleting the profiles above removed their badges as well
+        for (profile_id, badge, earned_at) in badges {
+            tx.execute(
+                "INSERT INTO badges (profile_id, badge, earned_at) VALUES (?1, ?2, ?3)",
+                params![profile_id, badge, earned_at.to_rfc3339()],
+            )?;
+        }
+        for session in sessions {
+            insert_session(&tx, None, session)?;
+        }
+        tx.commit()
+    }
+
+    /// Date and result of the profile's sessions since `since` (all when
+    /// `None`), oldest first.
+    pub fn body_fat_series(
+        &self,
+        profile_id: Option<i64>,
+        since: Option<DateTime<Local>>,
+    ) -> rusqlite::Result<Vec<(DateTime<Local>, f64)>> {
+        let _span = tracing::info_span!("body_fat_series").entered();
+        let mut statement = self.conn.prepare(
+            "SELECT recorded_at, body_fat FROM sessions
+             WHERE profile_id IS ?1 AND recorded_unix >= ?2
+             ORDER BY recorded_unix, id",
+        )?;
+        let since = since.map_or(i64::MIN, |since| since.timestamp());
+        statement
+            .query_map(params![profile_id, since], |row| {
+                Ok((parse_recorded_at(row.get(0)?)?, row.get(1)?))
+            })?
+            .collect()
+    }
+
+    /// Up to `limit` of the profile's sessions with their ids, newest first.
+    pub fn history(
+        &self,
+        profile_id: Option<i64>,
+        limit: usize,
+    ) -> rusqlite::Result<Vec<(i64, Session)>> {
+        let _span = tracing::info_span!("history", limit).entered();
+        let mut sessions = self.conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS} FROM sessions WHERE profile_id IS ?1
+             ORDER BY recorded_unix DESC, id DESC LIMIT ?2"
+        ))?;
+        let rows = sessions.query_map(params![profile_id, limit as i64], session_from_row)?;
+
+        let mut history = Vec::new();
+        for row in rows {
+            let (id, mut session) = row?;
+            session.readings = load_site_values(&self.conn, "readings", id)?;
+            session.trials = load_site_values(&self.conn, "trials", id)?;
+            history.push((id, session));
+        }
+        Ok(history)
+    }
+}
+
+/// Session id and details from a row selected with `SESSION_COLUMNS`,
+/// without its readings.
+fn session_from_row(row: &rusqlite::Row) -> rusqlite::Result<(i64, Session)> {
+    Ok((
+        row.get(0)?,
+        Session {
+            profile_id: row.get(6)?,
+            recorded_at: parse_recorded_at(row.get(1)?)?,
+            formula: row.get(2)?,
+            age: row.get(3)?,
+            is_male: row.get(4)?,
+            body_fat: row.get(5)?,
+            readings: Vec::new(),
+            trials: Vec::new(),
+            caliper: row.get(7)?,
+            tag: row.get(8)?,
+            excluded_sites: row
+                .get::<_, String>(9)?
+                .split(',')
+                .filter(|site| !site.is_empty())
+                .map(str::to_string)
+                .collect(),
+        },
+    ))
+}
+
+/// Site and value rows of a session from the `readings` or `trials` table.
+fn load_site_values(
+    conn: &Connection,
+    table: &str,
+    id: i64,
+) -> rusqlite::Result<Vec<(String, f64)>> {
+    let mut statement = conn.prepare_cached(&format!(
+        "SELECT site, value FROM {table} WHERE session_id = ?1 ORDER BY rowid"
+    ))?;
+    statement
+        .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect()
+}
+
+fn load_session(conn: &Connection, id: i64) -> rusqlite::Result<Option<Session>> {
+    let mut statement = conn.prepare_cached(&format!(
+        "SELECT {SESSION_COLUMNS} FROM sessions WHERE id = ?1"
+    ))?;
+    let Some(row) = statement.query_map([id], session_from_row)?.next() else {
+        return Ok(None);
+    };
+    let (_, mut session) = row?;
+    session.readings = load_site_values(conn, "readings", id)?;
+    session.trials = load_site_values(conn, "trials", id)?;
+    Ok(Some(session))
+}
+
+fn parse_recorded_at(text: String) -> rusqlite::Result<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(&text)
+        .map(|at| at.with_timezone(&Local))
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+        })
+}
+
+/// Insert a session under `id`, or a new id when `None`.
+fn insert_session(
+    tx: &rusqlite::Transaction,
+    id: Option<i64>,
+    session: &Session,
+) -> rusqlite::Result<i64> {
+    tx.execute(
+        "INSERT INTO sessions (id, profile_id, recorded_at, formula, age, is_male, body_fat,
+                               caliper, tag, excluded_sites, recorded_unix)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            id,
+            session.profile_id,
+            session.recorded_at.to_rfc3339(),
+            session.formula,
+            session.age,
+            session.is_male,
+            session.body_fat,
+            session.caliper,
+            session.tag,
+            session.excluded_sites.join(","),
+            session.recorded_at.timestamp()
+        ],
+    )?;
+    let id = tx.last_insert_rowid();
+    insert_readings(tx, id, session)?;
+    Ok(id)
+}
+
+/// Insert the session's combined readings and individual trials.
+fn insert_readings(tx: &rusqlite::Transaction, id: i64, session: &Session) -> rusqlite::Result<()> {
+    let mut insert =
+        tx.prepare_cached("INSERT INTO readings (session_id, site, value) VALUES (?1, ?2, ?3)")?;
+    for (site, value) in &session.readings {
+        insert.execute(params![id, site, value])?;
+    }
+    let mut insert =
+        tx.prepare_cached("INSERT INTO trials (session_id, site, value) VALUES (?1, ?2, ?3)")?;
+    for (site, value) in &session.trials {
+        insert.execute(params![id, site, value])?;
+    }
+    Ok(())
+}
+
+/// Whether `error` means the file is damaged or not a database at all, as
+/// opposed to e.g. locked or unreadable.
+pub fn is_corruption(error: &rusqlite::Error) -> bool {
+    matches!(
+        error.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn session(day: u32, body_fat: f64) -> Session {
+        Session {
+            profile_id: None,
+            recorded_at: Local.with_ymd_and_hms(2025, 3, day, 9, 30, 0).unwrap(),
+            formula: "Jackson & Pollock 3-site".to_string(),
+            age: 34,
+            is_male: true,
+            body_fat,
+            readings: vec![
+                ("chest".to_string(), 12.0),
+                ("abdominal".to_string(), 20.5),
+                ("thigh".to_string(), 15.0),
+            ],
+            trials: vec![
+                ("chest".to_string(), 11.5),
+                ("chest".to_string(), 12.0),
+                ("chest".to_string(), 12.5),
+            ],
+            caliper: Some("Harpenden".to_string()),
+            tag: None,
+            excluded_sites: vec!["triceps".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_history_round_trip_newest_first() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        storage.save(&session(1, 18.2)).unwrap();
+        storage.save(&session(8, 17.6)).unwrap();
+
+        let history = storage.history(None, 10).unwrap();
+        assert_eq!(
+            history.into_iter().map(|(_, s)| s).collect::<Vec<_>>(),
+            vec![session(8, 17.6), session(1, 18.2)]
+        );
+        assert_eq!(storage.history(None, 1).unwrap().len(), 1);
+
+        storage
+            .save_all(&[session(15, 17.1), session(22, 16.9)])
+            .unwrap();
+        assert_eq!(storage.history(None, 10).unwrap()[0].1, session(22, 16.9));
+        assert_eq!(storage.history(None, 10).unwrap().len(), 4);
+
+        let series = storage
+            .body_fat_series(None, Some(session(8, 0.0).recorded_at))
+            .unwrap();
+        assert_eq!(
+            series.iter().map(|(_, bf)| *bf).collect::<Vec<_>>(),
+            [17.6, 17.1, 16.9]
+        );
+    }
+
+    #[test]
+    fn test_profiles_keep_separate_history() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        let client = Profile {
+            id: None,
+            name: "Alex".to_string(),
+            birth_date: NaiveDate::from_ymd_opt(1991, 4, 2).unwrap(),
+            is_male: true,
+            preferred_formula: "3-site".to_string(),
+        };
+        let id = storage.save_profile(&client).unwrap();
+        assert_eq!(
+            storage.profiles().unwrap(),
+            vec![Profile {
+                id: Some(id),
+                ..client
+            }]
+        );
+
+        storage.save(&session(1, 20.0)).unwrap();
+        storage
+            .save(&Session {
+                profile_id: Some(id),
+                ..session(2, 15.0)
+            })
+            .unwrap();
+        assert_eq!(storage.history(Some(id), 10).unwrap().len(), 1);
+        assert_eq!(storage.history(None, 10).unwrap()[0].1.body_fat, 20.0);
+        assert_eq!(storage.body_fat_series(Some(id), None).unwrap().len(), 1);
+
+        assert_eq!(storage.goal(id).unwrap(), None);
+        let goal = Goal {
+            body_fat: 15.0,
+            target_date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+        };
+        storage.save_goal(id, &goal).unwrap();
+        storage
+            .save_goal(
+                id,
+                &Goal {
+                    body_fat: 14.0,
+                    ..goal
+                },
+            )
+            .unwrap();
+        assert_eq!(storage.goal(id).unwrap().unwrap().body_fat, 14.0);
+
+        let earned_at = session(3, 0.0).recorded_at;
+        assert_eq!(
+            storage
+                .award_badges(id, &["ten-sessions"], earned_at)
+                .unwrap(),
+            ["ten-sessions"]
+        );
+        assert_eq!(
+            storage
+                .award_badges(id, &["ten-sessions", "first-point-lost"], earned_at)
+                .unwrap(),
+            ["first-point-lost"]
+        );
+        assert_eq!(storage.badges(id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_nearby_session_can_be_updated() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        let first = storage.save(&session(1, 18.2)).unwrap();
+        storage.save(&session(8, 17.6)).unwrap();
+
+        let retake = Session {
+            recorded_at: session(1, 0.0).recorded_at + Duration::minutes(90),
+            ..session(1, 18.0)
+        };
+        let near = storage
+            .find_near(None, retake.recorded_at, Duration::hours(2))
+            .unwrap();
+        assert_eq!(near.map(|(id, _)| id), Some(first));
+        assert_eq!(
+            storage
+                .find_near(None, retake.recorded_at, Duration::hours(1))
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            storage
+                .find_near(Some(1), retake.recorded_at, Duration::hours(2))
+                .unwrap(),
+            None
+        );
+
+        storage.update(first, &retake).unwrap();
+        let history = storage.history(None, 10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1], (first, retake));
+    }
+
+    #[test]
+    fn test_rewrite_can_be_undone() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        let id = storage.save(&session(1, 18.2)).unwrap();
+        let before = storage
+            .rewrite(&[(id, session(1, 19.0)), (id + 1, session(2, 1.0))])
+            .unwrap();
+        assert_eq!(before, vec![(id, session(1, 18.2))]);
+        assert_eq!(
+            storage.history(None, 10).unwrap(),
+            vec![(id, session(1, 19.0))]
+        );
+        storage.restore(&before).unwrap();
+        assert_eq!(
+            storage.history(None, 10).unwrap(),
+            vec![(id, session(1, 18.2))]
+        );
+    }
+
+    #[test]
+    fn test_sessions_sort_by_time_across_offset_changes() {
+        // A database from before sessions were ordered by timestamp, with
+        // two sessions either side of the clocks going back: 00:30 and
+        // 01:10 UTC, whose local text sorts the other way round
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in &MIGRATIONS[..8] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", 8).unwrap();
+        for (id, recorded_at, body_fat) in [
+            (1, "2025-10-26T01:30:00.250+01:00", 18.0),
+            (2, "2025-10-26T01:10:00+00:00", 17.5),
+        ] {
+            conn.execute(
+                "INSERT INTO sessions (id, recorded_at, formula, age, is_male, body_fat)
+                 VALUES (?1, ?2, '3-site', 30, 1, ?3)",
+                params![id, recorded_at, body_fat],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO readings (session_id, site, value) VALUES (?1, 'chest', ?2)",
+                params![id, body_fat],
+            )
+            .unwrap();
+        }
+        let mut storage = Storage::init(conn).unwrap();
+        let oldest_first = |storage: &Storage| -> Vec<f64> {
+            storage
+                .all_sessions()
+                .unwrap()
+                .iter()
+                .map(|session| session.body_fat)
+                .collect()
+        };
+        assert_eq!(oldest_first(&storage), [18.0, 17.5]);
+        let series = storage.body_fat_series(None, None).unwrap();
+        assert_eq!(series[1].1, 17.5);
+        assert_eq!(storage.history(None, 1).unwrap()[0].1.body_fat, 17.5);
+        assert_eq!(storage.site_history(None, "chest", 1).unwrap()[0].1, 17.5);
+
+        // New and shifted sessions are ordered by their timestamp too
+        let mut later = session(1, 16.0);
+        later.recorded_at = series[1].0 + Duration::minutes(5);
+        storage.save(&later).unwrap();
+        assert_eq!(oldest_first(&storage), [18.0, 17.5, 16.0]);
+        storage
+            .bulk_edit(&[1], &BulkEdit::ShiftDates(Duration::hours(2)))
+            .unwrap();
+        assert_eq!(oldest_first(&storage), [17.5, 16.0, 18.0]);
+    }
+
+    #[test]
+    fn test_site_history_newest_first() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        for day in 1..=7 {
+            let mut session = session(day, 18.0);
+            session.readings[0].1 = day as f64;
+            storage.save(&session).unwrap();
+        }
+        let chest: Vec<f64> = storage
+            .site_history(None, "chest", 5)
+            .unwrap()
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(chest, [7.0, 6.0, 5.0, 4.0, 3.0]);
+        assert!(storage.site_history(None, "biceps", 5).unwrap().is_empty());
+        assert!(
+            storage
+                .site_history(Some(1), "chest", 5)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_bulk_edit_can_be_undone() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        let first = storage.save(&session(1, 18.2)).unwrap();
+        let second = storage.save(&session(8, 17.6)).unwrap();
+        let original = storage.history(None, 10).unwrap();
+
+        let before = storage
+            .bulk_edit(&[first, second], &BulkEdit::ShiftDates(Duration::hours(-1)))
+            .unwrap();
+        assert_eq!(before.len(), 2);
+        assert_eq!(
+            storage.history(None, 10).unwrap()[1].1.recorded_at,
+            session(1, 0.0).recorded_at - Duration::hours(1)
+        );
+        storage.restore(&before).unwrap();
+        assert_eq!(storage.history(None, 10).unwrap(), original);
+
+        let before = storage.bulk_edit(&[first], &BulkEdit::Delete).unwrap();
+        assert_eq!(storage.history(None, 10).unwrap().len(), 1);
+        storage.restore(&before).unwrap();
+        assert_eq!(storage.history(None, 10).unwrap(), original);
+
+        storage
+            .bulk_edit(&[second], &BulkEdit::Relabel("Manual".to_string()))
+            .unwrap();
+        assert_eq!(storage.history(None, 10).unwrap()[0].1.formula, "Manual");
+
+        let before = storage
+            .bulk_edit(&[first, second], &BulkEdit::Retag(Some("cut".to_string())))
+            .unwrap();
+        assert!(
+            storage
+                .history(None, 10)
+                .unwrap()
+                .iter()
+                .all(|(_, session)| session.tag.as_deref() == Some("cut"))
+        );
+        storage.restore(&before).unwrap();
+        assert_eq!(storage.history(None, 10).unwrap()[1].1.tag, None);
+    }
+
+    /// Loading the history panel and the chart series must not hold up the
+    /// UI, even for a client measured daily for decades.
+    const HISTORY_LOAD_BUDGET: std::time::Duration = std::time::Duration::from_millis(100);
+
+    #[test]
+    fn test_history_load_stays_within_budget() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        let start = session(1, 20.0).recorded_at;
+        let sessions: Vec<Session> = (0..10_000)
+            .map(|day| Session {
+                recorded_at: start + Duration::days(day),
+                body_fat: 20.0 - day as f64 * 0.0005,
+                ..session(1, 0.0)
+            })
+            .collect();
+        storage.save_all(&sessions).unwrap();
+
+        let timer = std::time::Instant::now();
+        let history = storage.history(None, 50).unwrap();
+        let series = storage.body_fat_series(None, None).unwrap();
+        let elapsed = timer.elapsed();
+        assert_eq!((history.len(), series.len()), (50, 10_000));
+        assert!(
+            elapsed < HISTORY_LOAD_BUDGET,
+            "Loading 10,000 sessions took {:?}",
+            elapsed
+        );
+    }
+
+    #[test]
+    fn test_two_connections_write_at_once() {
+        let dir = std::env::temp_dir().join(format!("bfc-storage-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.sqlite3");
+        // Create the schema before both connections race to migrate it
+        drop(Storage::open(&path).unwrap());
+
+        let writers: Vec<_> = (0..2)
+            .map(|_| {
+                let mut storage = Storage::open(&path).unwrap();
+                std::thread::spawn(move || {
+                    for day in 1..=28 {
+                        storage.save(&session(day, 18.0)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let storage = Storage::open(&path).unwrap();
+        assert_eq!(storage.all_sessions().unwrap().len(), 56);
+        let journal_mode: String = storage
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+        drop(storage);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_damaged_file_is_told_apart() {
+        let storage = Storage::open_in_memory().unwrap();
+        assert_eq!(storage.integrity_problems().unwrap(), Vec::<String>::new());
+
+        let dir = std::env::temp_dir().join(format!("bfc-damaged-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.sqlite3");
+        std::fs::write(&path, [0x5a; 4096]).unwrap();
+        let error = Storage::open(&path).map(|_| ()).unwrap_err();
+        assert!(is_corruption(&error), "{}", error);
+        // A missing directory is a different problem
+        let error = Storage::open(&dir.join("missing").join("history.sqlite3"))
+            .map(|_| ())
+            .unwrap_err();
+        assert!(!is_corruption(&error));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{Datelike, Duration, NaiveDate};
+
+/// Runs of consecutive weeks with at least one measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Streaks {
+    /// Weeks in the run reaching this week or last week, zero once a whole
+    /// week has been missed.
+    pub current: u32,
+    pub longest: u32,
+}
+
+/// Monday starting the week that holds `date`.
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Streaks from the local calendar dates of each session, so a session late
+/// on Sunday evening counts for that week wherever the offset puts it in UTC.
+/// The current streak survives until the end of the week after the last
+/// measurement, giving the user all of this week to keep it going.
+pub fn streaks(dates: &[NaiveDate], today: NaiveDate) -> Streaks {
+    let mut weeks: Vec<NaiveDate> = dates.iter().map(|date| week_start(*date)).collect();
+    weeks.sort();
+    weeks.dedup();
+
+    let mut longest = 0;
+    let mut run = 0;
+    for (i, week) in weeks.iter().enumerate() {
+        run = match i.checked_sub(1).map(|i| weeks[i]) {
+            Some(previous) if *week - previous == Duration::weeks(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+    }
+
+    let this_week = week_start(today);
+    let current = match weeks.last() {
+        Some(last) if *last == this_week || *last == this_week - Duration::weeks(1) => run,
+        _ => 0,
+    };
+    Streaks { current, longest }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaks_count_calendar_weeks() {
+        let day = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+        // Mondays 3, 10, 17 and 31 March; Sunday 16 March belongs to the week of the 10th
+        let dates = [
+            day(3, 4),
+            day(3, 9),
+            day(3, 16),
+            day(3, 17),
+            day(3, 31),
+            day(4, 1),
+        ];
+        assert_eq!(
+            streaks(&dates, day(4, 2)),
+            Streaks {
+                current: 1,
+                longest: 3
+            }
+        );
+        assert_eq!(streaks(&dates[..4], day(3, 23)).current, 3);
+        assert_eq!(streaks(&dates[..4], day(3, 31)).current, 0);
+        assert_eq!(
+            streaks(&[], day(3, 31)),
+            Streaks {
+                current: 0,
+                longest: 0
+            }
+        );
+    }
+}
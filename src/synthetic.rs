@@ -0,0 +1,99 @@
+use crate::profile::Profile;
+use crate::storage::Session;
+use body_fat_core::Formula;
+use body_fat_core::uncertainty::SplitMix64;
+use chrono::{Duration, Local};
+
+/// `profiles` simulated clients with `sessions` weekly sessions each, for
+/// stress-testing. Profiles have no id yet, and their sessions no profile,
+/// until they are saved.
+pub fn generate_clients(
+    profiles: usize,
+    sessions: usize,
+    seed: u64,
+) -> Vec<(Profile, Vec<Session>)> {
+    let mut rng = SplitMix64(seed);
+    let formula = Formula::JacksonPollock7;
+    (1..=profiles)
+        .map(|n| {
+            let age_in_days = (25 * 365) as i64 + (rng.next_u64() % (40 * 365)) as i64;
+            let profile = Profile {
+                id: None,
+                name: format!("Synthetic client {}", n),
+                birth_date: Local::now().date_naive() - Duration::days(age_in_days),
+                is_male: rng.next_f64() < 0.5,
+                preferred_formula: formula.protocol_label().to_string(),
+            };
+            let sessions = generate_sessions(&profile, sessions, &mut rng);
+            (profile, sessions)
+        })
+        .collect()
+}
+
+/// Weekly sessions for one simulated client, oldest first and ending now.
+/// Skinfolds drift slowly from week to week with per-site reading noise.
+fn generate_sessions(profile: &Profile, count: usize, rng: &mut SplitMix64) -> Vec<Session> {
+    let mut mean_skinfold = 8.0 + rng.next_f64() * 17.0;
+    let formula = Formula::JacksonPollock7;
+    let now = Local::now();
+
+    (0..count)
+        .map(|i| {
+            let recorded_at = now - Duration::weeks((count - 1 - i) as i64);
+            mean_skinfold = (mean_skinfold + rng.next_gaussian() * 0.3).clamp(4.0, 35.0);
+            let readings: Vec<(String, f64)> = formula
+                .sites(profile.is_male)
+                .iter()
+                .map(|site| {
+                    let reading = (mean_skinfold + rng.next_gaussian() * 2.0).max(2.0);
+                    (site.to_string(), (reading * 2.0).round() / 2.0)
+                })
+                .collect();
+            let total = readings.iter().map(|(_, value)| value).sum();
+            let age = profile.age_on(recorded_at.date_naive()).max(18);
+            Session {
+                profile_id: None,
+                recorded_at,
+                formula: formula.name().to_string(),
+                age,
+                is_male: profile.is_male,
+                body_fat: formula.body_fat(total, age, profile.is_male),
+                readings,
+                trials: Vec::new(),
+                caliper: None,
+                tag: None,
+                excluded_sites: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_sessions_are_weekly_and_plausible() {
+        let clients = generate_clients(3, 200, 7);
+        assert_eq!(clients.len(), 3);
+        assert_eq!(clients[2].0.name, "Synthetic client 3");
+        for (profile, sessions) in &clients {
+            assert_eq!(sessions.len(), 200);
+            assert!(
+                sessions
+                    .windows(2)
+                    .all(|pair| pair[1].recorded_at - pair[0].recorded_at == Duration::weeks(1))
+            );
+            assert!(sessions.iter().all(|s| s.readings.len() == 7));
+            assert!(sessions.iter().all(|s| (2.0..60.0).contains(&s.body_fat)));
+            assert!(sessions.iter().all(|s| s.is_male == profile.is_male));
+            let latest = sessions.last().unwrap();
+            assert_eq!(latest.age, profile.age_on(latest.recorded_at.date_naive()));
+            assert!((25..65).contains(&latest.age));
+        }
+        assert_eq!(
+            generate_clients(1, 5, 7)[0].1[0].readings,
+            generate_clients(1, 5, 7)[0].1[0].readings
+        );
+    }
+}
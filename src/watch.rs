@@ -0,0 +1,91 @@
+use crate::report;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+/// Change in percentage points between the last two sessions below which
+/// the trend shows as flat, matching one decimal place on the watch.
+const FLAT_CHANGE: f64 = 0.05;
+
+/// Latest result in the small JSON shape watch-face complication apps read,
+/// e.g. `{"label":"Body fat","value":15.2,"text":"15.2%","trend":"down",
+/// "arrow":"↓","last_measured":"2025-01-05"}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Complication {
+    pub label: &'static str,
+    /// Body fat percentage to one decimal place.
+    pub value: f64,
+    /// Value formatted for complications that only show text.
+    pub text: String,
+    /// "up", "down" or "flat" against the previous session, "flat" when
+    /// there is only one.
+    pub trend: &'static str,
+    pub arrow: &'static str,
+    pub last_measured: String,
+}
+
+/// Summary of a profile's sessions, oldest first, or `None` before the
+/// first session.
+pub fn complication(series: &[(DateTime<Local>, f64)]) -> Option<Complication> {
+    let (at, body_fat) = *series.last()?;
+    let change = match series.len().checked_sub(2).map(|i| series[i].1) {
+        Some(previous) => body_fat - previous,
+        None => 0.0,
+    };
+    let (trend, arrow) = if change >= FLAT_CHANGE {
+        ("up", "↑")
+    } else if change <= -FLAT_CHANGE {
+        ("down", "↓")
+    } else {
+        ("flat", "→")
+    };
+    let value = (body_fat * 10.0).round() / 10.0;
+    Some(Complication {
+        label: "Body fat",
+        value,
+        text: format!("{:.1}%", value),
+        trend,
+        arrow,
+        last_measured: at.format("%Y-%m-%d").to_string(),
+    })
+}
+
+/// File in the sync folder for a profile, e.g. `body-fat-sam.json`, kept
+/// the same between saves so the watch app always finds the latest.
+pub fn file_name(profile_name: &str) -> String {
+    match report::slug(profile_name) {
+        slug if slug.is_empty() => "body-fat.json".to_string(),
+        slug => format!("body-fat-{}.json", slug),
+    }
+}
+
+pub fn to_json(complication: &Complication) -> String {
+    serde_json::to_string(complication).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_complication_from_latest_sessions() {
+        let day = |d| Local.with_ymd_and_hms(2025, 1, d, 8, 0, 0).unwrap();
+        assert_eq!(complication(&[]), None);
+
+        let single = complication(&[(day(3), 18.04)]).unwrap();
+        assert_eq!((single.value, single.trend), (18.0, "flat"));
+
+        let falling = complication(&[(day(1), 20.0), (day(3), 18.0), (day(5), 17.46)]).unwrap();
+        assert_eq!(falling.text, "17.5%");
+        assert_eq!((falling.trend, falling.arrow), ("down", "↓"));
+        assert_eq!(
+            to_json(&falling),
+            r#"{"label":"Body fat","value":17.5,"text":"17.5%","trend":"down","arrow":"↓","last_measured":"2025-01-05"}"#
+        );
+        let rising = complication(&[(day(1), 18.0), (day(2), 18.3)]).unwrap();
+        assert_eq!(rising.trend, "up");
+
+        assert_eq!(file_name("Sam O'Neil"), "body-fat-sam-o-neil.json");
+        assert_eq!(file_name("?"), "body-fat.json");
+    }
+}